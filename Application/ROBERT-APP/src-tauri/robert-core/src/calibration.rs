@@ -0,0 +1,116 @@
+// Zero offsets of the joints. The firmware counts steps from the limit switches, but mechanical
+// tolerances put each switch a little away from the kinematic zero of its joint. The offset of a
+// joint is the positive angle from its switch to its zero, every angle read or commanded is
+// measured from the zero once it is set. Stored positions, home poses and teach recordings hold
+// such angles, so changing an offset shifts them all along with it
+use crate::constants;
+use crate::script::format_number;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Largest offset accepted in degrees, anything further is more likely a joint that was not at its
+// zero when the offset was captured
+pub const MAX_ZERO_OFFSET: f32 = 15.0;
+
+#[derive(Serialize, Deserialize)]
+struct ZeroOffsetsFile {
+    offsets: [f32; 6],
+}
+
+// Offsets are never negative, a zero past the switch could never be reached
+pub fn check_zero_offsets(offsets: &[f32; 6]) -> Result<(), String> {
+    for (i, offset) in offsets.iter().enumerate() {
+        if !(0.0..=MAX_ZERO_OFFSET).contains(offset) {
+            return Err(format!(
+                "Zero offset of J{} must be between 0° and {}°, got {}°",
+                i + 1,
+                MAX_ZERO_OFFSET,
+                offset
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Range of a joint in positive angles from its zero. The travel in `MAX_ANGLES` starts at the limit
+// switch, which is `offset` below the zero
+pub fn joint_range(joint: i8, zero_offsets: &[f32; 6]) -> Result<(f32, f32), String> {
+    if !(1..=6).contains(&joint) {
+        return Err(format!("Invalid Joint: {}", joint));
+    }
+
+    let max_angle = constants::get_max_angle(joint as u8).ok_or_else(|| format!("Invalid Joint: {}", joint))?;
+    let offset = zero_offsets[(joint - 1) as usize];
+
+    Ok((-offset, max_angle - offset))
+}
+
+// Checks a positive angle from the zero of `joint` is within its range
+pub fn check_joint_angle(joint: i8, angle: f32, zero_offsets: &[f32; 6]) -> Result<(), String> {
+    let (min_angle, max_angle) = joint_range(joint, zero_offsets)?;
+
+    match (min_angle..=max_angle).contains(&angle) {
+        true => Ok(()),
+        false => Err(format!(
+            "J{} would reach {:.1}°, outside its range of {}° to {}°",
+            joint,
+            angle,
+            format_number(min_angle),
+            format_number(max_angle)
+        )),
+    }
+}
+
+// Offsets stored in `path`, all 0 when the file does not exist yet
+pub fn load_zero_offsets(path: &Path) -> Result<[f32; 6], String> {
+    if !path.exists() {
+        return Ok([0.0; 6]);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read zero offsets {}: {}", path.display(), e))?;
+    let file: ZeroOffsetsFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid zero offsets {}: {}", path.display(), e))?;
+
+    check_zero_offsets(&file.offsets).map_err(|e| format!("Invalid zero offsets {}: {}", path.display(), e))?;
+
+    Ok(file.offsets)
+}
+
+pub fn save_zero_offsets(path: &Path, offsets: &[f32; 6]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&ZeroOffsetsFile { offsets: *offsets }).map_err(|e| e.to_string())?;
+
+    utils::write_atomic(path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_are_positive_and_bounded() {
+        assert!(check_zero_offsets(&[0.0, 15.0, 2.5, 0.0, 0.0, 0.0]).is_ok());
+        assert_eq!(
+            check_zero_offsets(&[0.0, 0.0, -1.0, 0.0, 0.0, 0.0]).unwrap_err(),
+            "Zero offset of J3 must be between 0° and 15°, got -1°"
+        );
+        assert!(check_zero_offsets(&[15.5, 0.0, 0.0, 0.0, 0.0, 0.0]).is_err());
+        assert!(check_zero_offsets(&[f32::NAN, 0.0, 0.0, 0.0, 0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn ranges_start_at_the_switch() {
+        let zero_offsets = [0.0, 0.0, 0.0, 0.0, 15.0, 0.0];
+
+        assert_eq!(joint_range(5, &zero_offsets).unwrap(), (-15.0, 30.0));
+        assert_eq!(joint_range(5, &[0.0; 6]).unwrap(), (0.0, 45.0));
+        assert!(check_joint_angle(5, -15.0, &zero_offsets).is_ok());
+        assert_eq!(
+            check_joint_angle(5, 45.0, &zero_offsets).unwrap_err(),
+            "J5 would reach 45.0°, outside its range of -15° to 30°"
+        );
+        assert!(joint_range(i8::MIN, &zero_offsets).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+// Cartesian paths: the tool pose is interpolated between the start and the target, and every
+// waypoint is solved to joint angles, checking limits, singularities and continuity on the way
+use crate::constants;
+use crate::kinematics::{self, CartesianPose, CartesianPosition, Matrix3, Transform, Vector3};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+// Largest distance between two waypoints, in mm
+const MAX_WAYPOINT_DISTANCE: f64 = 5.0;
+// Largest rotation between two waypoints, in degrees
+const MAX_WAYPOINT_ROTATION: f64 = 5.0;
+// Largest change of a joint between two waypoints, in degrees. Bigger jumps mean the solution
+// switched to another configuration of the arm
+const MAX_WAYPOINT_JOINT_CHANGE: f32 = 15.0;
+
+// Axis a Cartesian jog moves along (mm) or rotates around (degrees)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CartesianAxis {
+    X,
+    Y,
+    Z,
+    Roll,
+    Pitch,
+    Yaw,
+}
+
+impl FromStr for CartesianAxis {
+    type Err = String;
+
+    fn from_str(axis: &str) -> Result<Self, Self::Err> {
+        match axis.to_lowercase().as_str() {
+            "x" => Ok(CartesianAxis::X),
+            "y" => Ok(CartesianAxis::Y),
+            "z" => Ok(CartesianAxis::Z),
+            "roll" => Ok(CartesianAxis::Roll),
+            "pitch" => Ok(CartesianAxis::Pitch),
+            "yaw" => Ok(CartesianAxis::Yaw),
+            _ => Err(format!("Invalid axis '{}', expected x, y, z, roll, pitch or yaw", axis)),
+        }
+    }
+}
+
+// Frame the axes of a Cartesian jog are taken from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JogFrame {
+    Base,
+    Tool,
+}
+
+impl FromStr for JogFrame {
+    type Err = String;
+
+    fn from_str(frame: &str) -> Result<Self, Self::Err> {
+        match frame.to_lowercase().as_str() {
+            "base" => Ok(JogFrame::Base),
+            "tool" => Ok(JogFrame::Tool),
+            _ => Err(format!("Invalid frame '{}', expected base or tool", frame)),
+        }
+    }
+}
+
+// Orientation at `t` (0 to 1) of a constant rate rotation from `start` to `end`
+pub fn interpolate_rotation(start: &Matrix3, end: &Matrix3, t: f64) -> Matrix3 {
+    let relative = kinematics::mat_mul(&kinematics::transpose(start), end);
+    let rotation_vector = kinematics::rotation_vector(&relative);
+    let angle = kinematics::norm(&rotation_vector);
+
+    if angle < 1e-9 {
+        return *start;
+    }
+
+    let axis = kinematics::scale(&rotation_vector, 1.0 / angle);
+    kinematics::mat_mul(start, &kinematics::axis_angle_rotation(&axis, angle * t))
+}
+
+// Tool pose at `t` (0 to 1) along the straight line from `start` to `end`
+pub fn interpolate_linear(start: &Transform, end: &Transform, t: f64) -> Transform {
+    let offset = kinematics::sub(&end.translation, &start.translation);
+
+    Transform {
+        rotation: interpolate_rotation(&start.rotation, &end.rotation, t),
+        translation: kinematics::add(&start.translation, &kinematics::scale(&offset, t)),
+    }
+}
+
+// Circle through three points, travelled from the first one through the second to the third
+pub struct Arc {
+    center: Vector3,
+    radius: f64,
+    // In-plane unit vectors, `first_axis` points from the center to the start
+    first_axis: Vector3,
+    second_axis: Vector3,
+    // Angle swept from the start to the end, in rad
+    sweep: f64,
+}
+
+impl Arc {
+    pub fn through(start: &Vector3, via: &Vector3, end: &Vector3) -> Result<Self, String> {
+        let u = kinematics::sub(via, start);
+        let v = kinematics::sub(end, start);
+        let normal = kinematics::cross(&u, &v);
+        let normal_length = kinematics::norm(&normal);
+
+        if normal_length < 1e-6 * kinematics::norm(&u).max(1.0) * kinematics::norm(&v).max(1.0) {
+            return Err("Arc points are aligned or repeated, there is no circle through them".to_string());
+        }
+
+        // Circumcenter of the triangle start, via, end
+        let numerator = kinematics::add(
+            &kinematics::scale(&kinematics::cross(&v, &normal), kinematics::dot(&u, &u)),
+            &kinematics::scale(&kinematics::cross(&normal, &u), kinematics::dot(&v, &v)),
+        );
+        let center = kinematics::add(start, &kinematics::scale(&numerator, 0.5 / (normal_length * normal_length)));
+        let radius = kinematics::norm(&kinematics::sub(start, &center));
+
+        let first_axis = kinematics::scale(&kinematics::sub(start, &center), 1.0 / radius);
+        let second_axis = kinematics::cross(&kinematics::scale(&normal, 1.0 / normal_length), &first_axis);
+
+        // Start, via and end wind around the normal, so the end is reached after the via point
+        let mut arc = Self {
+            center,
+            radius,
+            first_axis,
+            second_axis,
+            sweep: 0.0,
+        };
+        arc.sweep = arc.angle_of(end);
+
+        Ok(arc)
+    }
+
+    // Angle of `point` around the center from the start, from 0 to 2π
+    fn angle_of(&self, point: &Vector3) -> f64 {
+        let offset = kinematics::sub(point, &self.center);
+        let angle = kinematics::dot(&offset, &self.second_axis).atan2(kinematics::dot(&offset, &self.first_axis));
+
+        if angle < 0.0 {
+            angle + 2.0 * PI
+        } else {
+            angle
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.radius * self.sweep
+    }
+
+    // Point at `t` (0 to 1) along the arc
+    pub fn point_at(&self, t: f64) -> Vector3 {
+        let (sin, cos) = (self.sweep * t).sin_cos();
+
+        kinematics::add(
+            &self.center,
+            &kinematics::add(
+                &kinematics::scale(&self.first_axis, self.radius * cos),
+                &kinematics::scale(&self.second_axis, self.radius * sin),
+            ),
+        )
+    }
+}
+
+// Number of segments keeping waypoints within `MAX_WAYPOINT_DISTANCE` and `MAX_WAYPOINT_ROTATION`
+pub fn segment_count(distance: f64, rotation: f64) -> usize {
+    let by_distance = (distance / MAX_WAYPOINT_DISTANCE).ceil();
+    let by_rotation = (rotation.to_degrees() / MAX_WAYPOINT_ROTATION).ceil();
+
+    by_distance.max(by_rotation).max(1.0) as usize
+}
+
+// Solves every pose of a path, starting from the joint angles the arm is at. Joint ranges are
+// checked from the zeros set by `zero_offsets`
+pub fn solve_path(start_angles: &[f32; 6], poses: &[Transform], zero_offsets: &[f32; 6]) -> Result<Vec<[f32; 6]>, String> {
+    let mut previous = *start_angles;
+    let mut waypoints = Vec::with_capacity(poses.len());
+
+    for (i, pose) in poses.iter().enumerate() {
+        let waypoint_error = |e: String| format!("Waypoint {}/{}: {}", i + 1, poses.len(), e);
+
+        let angles = kinematics::inverse_kinematics(pose, &previous).map_err(waypoint_error)?;
+        kinematics::check_joint_limits(&angles, zero_offsets).map_err(waypoint_error)?;
+        kinematics::check_singularity(&angles).map_err(waypoint_error)?;
+
+        if let Some(joint) = (0..6).find(|&joint| (angles[joint] - previous[joint]).abs() > MAX_WAYPOINT_JOINT_CHANGE) {
+            return Err(waypoint_error(format!(
+                "J{} would jump {:.1}°, the path is not continuous",
+                joint + 1,
+                (angles[joint] - previous[joint]).abs()
+            )));
+        }
+
+        waypoints.push(angles);
+        previous = angles;
+    }
+
+    Ok(waypoints)
+}
+
+// Waypoints of a Cartesian path as joint angles, with the length of the path in mm and how far the
+// tool turns along it in radians
+pub struct CartesianPath {
+    pub waypoints: Vec<[f32; 6]>,
+    pub length: f64,
+    pub rotation: f64,
+}
+
+impl CartesianPath {
+    // Shortest time each segment may take to keep the tool under `speed` mm/s. The tool never goes
+    // over `MAX_TOOL_SPEED`, which is also the speed when none is given, and never turns faster than
+    // `MAX_ROTATION_SPEED`
+    pub fn segment_duration(&self, speed: Option<f32>) -> Result<f32, String> {
+        let speed = speed.unwrap_or(constants::MAX_TOOL_SPEED);
+
+        if speed.is_nan() || speed <= 0.0 {
+            return Err(format!("Speed must be positive, got {} mm/s", speed));
+        }
+        let speed = speed.min(constants::MAX_TOOL_SPEED);
+
+        let duration = (self.length / speed as f64).max(self.rotation.to_degrees() / constants::MAX_ROTATION_SPEED as f64);
+
+        Ok((duration / self.waypoints.len().max(1) as f64) as f32)
+    }
+}
+
+// Fails while the kinematic model is the nominal one and nobody opted in to use it anyway
+pub fn check_kinematic_model() -> Result<(), String> {
+    if constants::DH_PARAMETERS_MEASURED || std::env::var_os(constants::NOMINAL_KINEMATICS_ENV).is_some() {
+        return Ok(());
+    }
+
+    Err(format!(
+        "Cartesian motion is disabled until the DH parameters are measured on the arm, set {}=1 to use the nominal ones",
+        constants::NOMINAL_KINEMATICS_ENV
+    ))
+}
+
+// Joint angles of the waypoints of a straight line from `start_angles` to `target`
+pub fn linear_path(
+    start_angles: &[f32; 6],
+    target: &CartesianPose,
+    zero_offsets: &[f32; 6],
+) -> Result<CartesianPath, String> {
+    check_kinematic_model()?;
+    transform_path(start_angles, &Transform::from_pose(target), zero_offsets)
+}
+
+// Joint angles of the waypoints of a jog by `increment` mm or degrees along or around `axis` of `frame`
+pub fn jog_path(
+    start_angles: &[f32; 6],
+    axis: CartesianAxis,
+    frame: JogFrame,
+    increment: f32,
+    zero_offsets: &[f32; 6],
+) -> Result<CartesianPath, String> {
+    check_kinematic_model()?;
+    let start = kinematics::forward_kinematics(start_angles);
+    let increment = increment as f64;
+
+    let (direction, translation) = match axis {
+        CartesianAxis::X => ([1.0, 0.0, 0.0], true),
+        CartesianAxis::Y => ([0.0, 1.0, 0.0], true),
+        CartesianAxis::Z => ([0.0, 0.0, 1.0], true),
+        CartesianAxis::Roll => ([1.0, 0.0, 0.0], false),
+        CartesianAxis::Pitch => ([0.0, 1.0, 0.0], false),
+        CartesianAxis::Yaw => ([0.0, 0.0, 1.0], false),
+    };
+
+    let target = match (translation, frame) {
+        (true, JogFrame::Base) => Transform {
+            rotation: start.rotation,
+            translation: kinematics::add(&start.translation, &kinematics::scale(&direction, increment)),
+        },
+        (true, JogFrame::Tool) => {
+            let direction = kinematics::mat_vec(&start.rotation, &direction);
+            Transform {
+                rotation: start.rotation,
+                translation: kinematics::add(&start.translation, &kinematics::scale(&direction, increment)),
+            }
+        }
+        // Rotations keep the tool flange in place
+        (false, JogFrame::Base) => Transform {
+            rotation: kinematics::mat_mul(
+                &kinematics::axis_angle_rotation(&direction, increment.to_radians()),
+                &start.rotation,
+            ),
+            translation: start.translation,
+        },
+        (false, JogFrame::Tool) => Transform {
+            rotation: kinematics::mat_mul(
+                &start.rotation,
+                &kinematics::axis_angle_rotation(&direction, increment.to_radians()),
+            ),
+            translation: start.translation,
+        },
+    };
+
+    transform_path(start_angles, &target, zero_offsets)
+}
+
+fn transform_path(start_angles: &[f32; 6], end: &Transform, zero_offsets: &[f32; 6]) -> Result<CartesianPath, String> {
+    let start = kinematics::forward_kinematics(start_angles);
+    let (distance, rotation) = kinematics::transform_distance(&start, end);
+    let segments = segment_count(distance, rotation);
+
+    let poses: Vec<Transform> = (1..=segments)
+        .map(|i| interpolate_linear(&start, end, i as f64 / segments as f64))
+        .collect();
+
+    Ok(CartesianPath {
+        waypoints: solve_path(start_angles, &poses, zero_offsets)?,
+        length: distance,
+        rotation,
+    })
+}
+
+// Joint angles of the waypoints of an arc from `start_angles` through `via` to `end`. The
+// orientation turns at a constant rate from the start to the end orientation
+pub fn arc_path(
+    start_angles: &[f32; 6],
+    via: &CartesianPosition,
+    end: &CartesianPose,
+    zero_offsets: &[f32; 6],
+) -> Result<CartesianPath, String> {
+    check_kinematic_model()?;
+    let start = kinematics::forward_kinematics(start_angles);
+    let end = Transform::from_pose(end);
+    let arc = Arc::through(&start.translation, &via.to_vector(), &end.translation)?;
+    let (_, rotation) = kinematics::transform_distance(&start, &end);
+    let segments = segment_count(arc.length(), rotation);
+
+    let poses: Vec<Transform> = (1..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+            Transform {
+                rotation: interpolate_rotation(&start.rotation, &end.rotation, t),
+                translation: arc.point_at(t),
+            }
+        })
+        .collect();
+
+    Ok(CartesianPath {
+        waypoints: solve_path(start_angles, &poses, zero_offsets)?,
+        length: arc.length(),
+        rotation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turning_in_place_is_bounded_by_the_rotation_speed() {
+        let path = CartesianPath {
+            waypoints: vec![[0.0; 6]; 6],
+            length: 0.0,
+            rotation: 90f64.to_radians(),
+        };
+        let expected = 90.0 / constants::MAX_ROTATION_SPEED / 6.0;
+
+        assert!((path.segment_duration(None).unwrap() - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn the_slower_bound_sets_the_duration() {
+        let path = CartesianPath {
+            waypoints: vec![[0.0; 6]; 10],
+            length: 100.0,
+            rotation: 1f64.to_radians(),
+        };
+
+        assert!((path.segment_duration(Some(10.0)).unwrap() - 1.0).abs() < 1e-4);
+        assert!(path.segment_duration(Some(-1.0)).is_err());
+    }
+}
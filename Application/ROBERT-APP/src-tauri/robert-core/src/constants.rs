@@ -15,6 +15,8 @@ impl CommandCodes {
     pub const STATE: &'static str = "STATE>";
     pub const STEPS: &'static str = "STEPS>";
     pub const PARAMS: &'static str = "PARAMS>";
+    pub const JOG: &'static str = "JOG>";
+    pub const STOP: &'static str = "STOP>";
 }
 
 // Response Codes
@@ -53,7 +55,7 @@ pub static JOINT_REDUCTIONS: Lazy<HashMap<u8, f32>> = Lazy::new(|| {
     m.insert(3, 100.0 / 16.0); 
     m.insert(4, 60.0 / 16.0); 
     m.insert(5, 32.0 / 16.0);
-    m.insert(6, 1.0 / 1.0); //TODO: register actual reduction
+    m.insert(6, 1.0); //TODO: register actual reduction
     m
 });
 
@@ -92,6 +94,11 @@ pub fn get_degrees_per_step(joint_id: u8) -> Option<f32> {
     DEGREES_PER_STEP.get(&joint_id).copied()
 }
 
+// Motor steps turning a joint by one degree
+pub fn get_steps_per_degree(joint_id: u8) -> Option<f32> {
+    Some(get_reduction_ratio(joint_id)? / get_degrees_per_step(joint_id)?)
+}
+
 lazy_static::lazy_static! {
     pub static ref STEPPER_POSITIVE_TO_LIMIT: HashMap<u8, bool> = {
         let mut map = HashMap::new();
@@ -105,10 +112,78 @@ lazy_static::lazy_static! {
     };
 }
 
-pub const PARAMETERS_MULTIPLIER: u8 = 10;
+// Velocity (degrees/s) and acceleration (degrees/s²) of every joint until set otherwise
+pub const DEFAULT_VELOCITY: f32 = 30.0;
+pub const DEFAULT_ACCELERATION: f32 = 30.0;
+
+// Highest velocity each stepper can be set to, in steps/s
+pub static MAX_STEPPER_VELOCITIES: Lazy<HashMap<u8, f32>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert(1, 1000.0);
+    m.insert(2, 1200.0);
+    m.insert(3, 1000.0);
+    m.insert(4, 1200.0);
+    m.insert(5, 800.0);
+    m.insert(6, 800.0);
+    m
+});
+
+pub fn get_max_stepper_velocity(joint_id: u8) -> Option<f32> {
+    MAX_STEPPER_VELOCITIES.get(&joint_id).copied()
+}
+
+// Highest acceleration each stepper can be set to, in steps/s²
+pub static MAX_STEPPER_ACCELERATIONS: Lazy<HashMap<u8, f32>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert(1, 800.0);
+    m.insert(2, 1000.0);
+    m.insert(3, 800.0);
+    m.insert(4, 1000.0);
+    m.insert(5, 600.0);
+    m.insert(6, 600.0);
+    m
+});
+
+pub fn get_max_stepper_acceleration(joint_id: u8) -> Option<f32> {
+    MAX_STEPPER_ACCELERATIONS.get(&joint_id).copied()
+}
+
+// Denavit-Hartenberg parameters of a joint: link length `a` and offset `d` in mm, twist `alpha` in
+// degrees. The joint variable is the positive angle from the joint zero plus `theta_offset` degrees
+#[derive(Clone, Copy, Debug)]
+pub struct DhParameters {
+    pub a: f32,
+    pub alpha: f32,
+    pub d: f32,
+    pub theta_offset: f32,
+}
+
+// Kinematic model used for Cartesian moves. Nominal values, not measured on the built arm: check
+// them against it before relying on Cartesian moves, then set `DH_PARAMETERS_MEASURED`
+pub const DH_PARAMETERS: [DhParameters; 6] = [
+    DhParameters { a: 0.0, alpha: 90.0, d: 170.0, theta_offset: -135.0 },
+    DhParameters { a: 220.0, alpha: 0.0, d: 0.0, theta_offset: 40.0 },
+    DhParameters { a: 0.0, alpha: 90.0, d: 0.0, theta_offset: -60.0 },
+    DhParameters { a: 0.0, alpha: -90.0, d: 220.0, theta_offset: -135.0 },
+    DhParameters { a: 0.0, alpha: 90.0, d: 0.0, theta_offset: 20.0 },
+    DhParameters { a: 0.0, alpha: 0.0, d: 60.0, theta_offset: -180.0 },
+];
 
+// Whether `DH_PARAMETERS` were measured on the arm. Until they are, Cartesian moves and jogs are
+// refused unless `NOMINAL_KINEMATICS_ENV` is set, as the tool would not go where it is sent
+pub const DH_PARAMETERS_MEASURED: bool = false;
+pub const NOMINAL_KINEMATICS_ENV: &str = "ROBERT_NOMINAL_KINEMATICS";
 
-#[derive(Clone, Serialize)]
+// Highest tool speed accepted by Cartesian moves, in mm/s
+pub const MAX_TOOL_SPEED: f32 = 100.0;
+// Highest tool rotation speed of Cartesian moves and jogs, in degrees/s
+pub const MAX_ROTATION_SPEED: f32 = 30.0;
+
+// Port name that connects to the simulated controller instead of a serial port
+pub const SIMULATOR_PORT: &str = "SIMULATOR";
+
+
+#[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SteppersAngles {
     pub j1: Option<f32>,
@@ -118,3 +193,16 @@ pub struct SteppersAngles {
     pub j5: Option<f32>,
     pub j6: Option<f32>,
 }
+
+impl From<[Option<f32>; 6]> for SteppersAngles {
+    fn from(angles: [Option<f32>; 6]) -> Self {
+        Self {
+            j1: angles[0],
+            j2: angles[1],
+            j3: angles[2],
+            j4: angles[3],
+            j5: angles[4],
+            j6: angles[5],
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Stepping through scripts. The interpreter asks its `Debugger` before every statement whether to
+// pause, and while paused waits for the next command sent through the `DebugHandle`
+use crate::interpreter::CallSite;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum DebugCommand {
+    Continue,
+    // Pause at the next statement, inside a subroutine when the statement is a CALL
+    StepInto,
+    // Pause at the next statement without stopping inside the subroutines it calls
+    StepOver,
+    // Pause once a line of the script itself is reached
+    RunToLine { line: usize },
+    Pause,
+    Stop,
+}
+
+// Sent whenever the script pauses, with everything needed to inspect it
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptPaused {
+    pub line: usize,
+    pub file: Option<String>,
+    pub script_line: usize,
+    pub call_stack: Vec<CallSite>,
+    pub variables: BTreeMap<String, f32>,
+    // Positive angles as scripts use them, `None` for joints that are not calibrated
+    pub angles: [Option<f32>; 6],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Run,
+    Step,
+    // Pause at a call depth up to this one
+    StepOver(usize),
+    RunToLine(usize),
+}
+
+// What the debugger decided about the next statement
+pub(crate) enum Decision {
+    Run,
+    Pause,
+    Stop,
+}
+
+// Controls a script run from outside, e.g. from the UI. Cheap to clone
+#[derive(Clone)]
+pub struct DebugHandle {
+    breakpoints: Arc<Mutex<BTreeSet<usize>>>,
+    commands: mpsc::UnboundedSender<DebugCommand>,
+}
+
+impl DebugHandle {
+    pub fn send(&self, command: DebugCommand) -> Result<(), String> {
+        self.commands
+            .send(command)
+            .map_err(|_| "The script is not running".to_string())
+    }
+
+    // Lines of the script itself to pause at, they apply from the next statement on
+    pub fn set_breakpoints(&self, lines: impl IntoIterator<Item = usize>) {
+        *self.breakpoints.lock().unwrap() = lines.into_iter().collect();
+    }
+
+    pub fn breakpoints(&self) -> Vec<usize> {
+        self.breakpoints.lock().unwrap().iter().copied().collect()
+    }
+}
+
+pub struct Debugger {
+    breakpoints: Arc<Mutex<BTreeSet<usize>>>,
+    commands: mpsc::UnboundedReceiver<DebugCommand>,
+    mode: Mode,
+}
+
+// Debugger for a run and the handle controlling it. With `pause_at_start` the run pauses before its
+// first statement, otherwise at the first breakpoint
+pub fn debugger(breakpoints: impl IntoIterator<Item = usize>, pause_at_start: bool) -> (DebugHandle, Debugger) {
+    let breakpoints = Arc::new(Mutex::new(breakpoints.into_iter().collect()));
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    let handle = DebugHandle {
+        breakpoints: breakpoints.clone(),
+        commands: sender,
+    };
+    let debugger = Debugger {
+        breakpoints,
+        commands: receiver,
+        mode: if pause_at_start { Mode::Step } else { Mode::Run },
+    };
+
+    (handle, debugger)
+}
+
+impl Debugger {
+    // Whether to pause before a statement at `script_line` of the script itself, if it is there,
+    // with `depth` subroutines running
+    pub(crate) fn before(&mut self, script_line: Option<usize>, depth: usize) -> Decision {
+        // Commands sent while running, only pausing and stopping make sense there
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                DebugCommand::Pause => self.mode = Mode::Step,
+                DebugCommand::Stop => return Decision::Stop,
+                _ => {}
+            }
+        }
+
+        let at_breakpoint = script_line.is_some_and(|line| self.breakpoints.lock().unwrap().contains(&line));
+        let pause = at_breakpoint
+            || match self.mode {
+                Mode::Run => false,
+                Mode::Step => true,
+                Mode::StepOver(max_depth) => depth <= max_depth,
+                Mode::RunToLine(line) => script_line == Some(line),
+            };
+
+        match pause {
+            true => Decision::Pause,
+            false => Decision::Run,
+        }
+    }
+
+    // Waits while paused, `false` when the run has to stop
+    pub(crate) async fn wait(&mut self, depth: usize) -> bool {
+        loop {
+            // The handle is gone, nobody can resume the run
+            let Some(command) = self.commands.recv().await else {
+                return false;
+            };
+
+            self.mode = match command {
+                DebugCommand::Continue => Mode::Run,
+                DebugCommand::StepInto => Mode::Step,
+                DebugCommand::StepOver => Mode::StepOver(depth),
+                DebugCommand::RunToLine { line } => Mode::RunToLine(line),
+                DebugCommand::Pause => continue,
+                DebugCommand::Stop => return false,
+            };
+
+            return true;
+        }
+    }
+}
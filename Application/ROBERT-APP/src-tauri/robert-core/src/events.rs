@@ -0,0 +1,53 @@
+use crate::constants::SteppersAngles;
+use crate::debugger::ScriptPaused;
+use crate::homing::HomingProgress;
+use crate::interpreter::ScriptProgress;
+use crate::jog::JogReport;
+use crate::parameters::MotionParameters;
+use crate::planner::TrajectoryPlan;
+use crate::tracking::PositionDrift;
+use serde::Serialize;
+
+// Events reported by the robot, serialized as their payload so they can be forwarded as-is
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum RobotEvent {
+    SteppersAngles(SteppersAngles),
+    // Sent right before a move starts, with its expected duration
+    TrajectoryPlanned(TrajectoryPlan),
+    // Sent once a continuous jog is over and the arm is at rest
+    JogStopped(JogReport),
+    // Sent when a reading of the steppers disagrees with the position model
+    PositionDrift(Vec<PositionDrift>),
+    // Whether each stepper is enabled, sent by the poller when it changes
+    SteppersState([bool; 6]),
+    // Velocity and acceleration as configured, sent by the poller when they change.
+    // `Robot::read_parameters` reads back what the firmware runs with
+    Parameters(MotionParameters),
+    // Percentage of the configured speed motions run at, sent when it changes
+    SpeedOverride(f32),
+    // Line of a script about to run
+    ScriptProgress(ScriptProgress),
+    // A script being debugged paused before a line
+    ScriptPaused(ScriptPaused),
+    // Homing started a stage
+    HomingProgress(HomingProgress),
+}
+
+impl RobotEvent {
+    // Name under which the event is reported to the frontend
+    pub fn name(&self) -> &'static str {
+        match self {
+            RobotEvent::SteppersAngles(_) => "report-steppers-angles",
+            RobotEvent::TrajectoryPlanned(_) => "report-trajectory-plan",
+            RobotEvent::JogStopped(_) => "report-jog-stopped",
+            RobotEvent::PositionDrift(_) => "report-position-drift",
+            RobotEvent::SteppersState(_) => "report-steppers-state",
+            RobotEvent::Parameters(_) => "report-params",
+            RobotEvent::SpeedOverride(_) => "report-speed-override",
+            RobotEvent::ScriptProgress(_) => "report-script-progress",
+            RobotEvent::ScriptPaused(_) => "report-script-paused",
+            RobotEvent::HomingProgress(_) => "report-homing-progress",
+        }
+    }
+}
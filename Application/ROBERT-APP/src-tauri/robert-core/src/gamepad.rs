@@ -0,0 +1,442 @@
+// Teleoperation with a game controller read through Linux evdev (/dev/input/event*). Sticks and
+// buttons start continuous jogs, so they go through the same checks and heartbeat watchdog as the
+// jogs started from the UI. Any evdev device works, including uinput virtual devices
+use crate::cartesian::JogFrame;
+use crate::jog::JogTarget;
+use crate::positions::{self, SharedPositionStore};
+use crate::robot::Robot;
+use serde::Deserialize;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+
+// Event types from linux/input-event-codes.h
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+// Size of struct input_event: a timeval followed by type, code and value. Assumes the timeval is
+// two native words, 16 bytes on 64-bit Linux. Userspaces whose longs are narrower than the
+// kernel's, such as x32, lay the event out differently and are not supported
+const INPUT_EVENT_SIZE: usize = 2 * std::mem::size_of::<usize>() + 8;
+// Well within `jog::HEARTBEAT_TIMEOUT`
+const HEARTBEAT_PERIOD: Duration = Duration::from_millis(150);
+
+fn default_deadzone() -> f32 {
+    0.2
+}
+
+fn default_speed_presets() -> Vec<f32> {
+    vec![5.0, 10.0, 20.0]
+}
+
+fn default_axis_min() -> i32 {
+    -32768
+}
+
+fn default_axis_max() -> i32 {
+    32767
+}
+
+// Stick or pad axis jogging `target` while it is pushed past the deadzone
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxisMapping {
+    // ABS_* code, e.g. 0 for ABS_X
+    pub code: u16,
+    pub target: JogTarget,
+    #[serde(default)]
+    pub invert: bool,
+    #[serde(default)]
+    pub frame: Option<JogFrame>,
+    // Raw range reported by the device, e.g. -1 to 1 for a hat
+    #[serde(default = "default_axis_min")]
+    pub min: i32,
+    #[serde(default = "default_axis_max")]
+    pub max: i32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ButtonAction {
+    // Jogs while the button is held
+    Jog {
+        target: JogTarget,
+        direction: i8,
+        #[serde(default)]
+        frame: Option<JogFrame>,
+    },
+    SpeedUp,
+    SpeedDown,
+    // Saves the current pose as "Gamepad <n>", needs a position store
+    SavePosition,
+    EmergencyStop,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ButtonMapping {
+    // BTN_* code, e.g. 304 for BTN_SOUTH
+    pub code: u16,
+    pub action: ButtonAction,
+}
+
+// Mapping file contents, see the README for an example
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GamepadMapping {
+    // Event device, can be overridden when starting
+    #[serde(default)]
+    pub device: Option<String>,
+    // Fraction of an axis travel from the center ignored, from 0 to 1
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f32,
+    // Jog speeds cycled through by SpeedUp and SpeedDown, in degrees/s (mm/s along x, y and z).
+    // Jogs start at the first one
+    #[serde(default = "default_speed_presets")]
+    pub speed_presets: Vec<f32>,
+    #[serde(default)]
+    pub axes: Vec<AxisMapping>,
+    #[serde(default)]
+    pub buttons: Vec<ButtonMapping>,
+}
+
+impl GamepadMapping {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read gamepad mapping {}: {}", path.display(), e))?;
+        let mapping: GamepadMapping = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid gamepad mapping {}: {}", path.display(), e))?;
+
+        mapping.validate()?;
+
+        Ok(mapping)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.deadzone) {
+            return Err("Gamepad deadzone must be between 0 and 1".to_string());
+        }
+        if self.speed_presets.is_empty() || self.speed_presets.iter().any(|speed| *speed <= 0.0) {
+            return Err("Gamepad speed presets must be greater than 0".to_string());
+        }
+        if let Some(axis) = self.axes.iter().find(|axis| axis.min >= axis.max) {
+            return Err(format!("Gamepad axis {} has a min not below its max", axis.code));
+        }
+
+        for button in &self.buttons {
+            if let ButtonAction::Jog { direction, .. } = button.action {
+                if direction != 1 && direction != -1 {
+                    return Err(format!("Gamepad button {} must jog in direction 1 or -1", button.code));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Jog direction for a raw axis value: 0 within the deadzone, otherwise 1 or -1
+    fn axis_direction(&self, axis: &AxisMapping, value: i32) -> i8 {
+        let position = 2.0 * (value - axis.min) as f32 / (axis.max - axis.min) as f32 - 1.0;
+
+        if position.abs() <= self.deadzone {
+            0
+        } else if (position > 0.0) != axis.invert {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InputSource {
+    Axis(u16),
+    Button(u16),
+}
+
+struct Teleoperation {
+    robot: Robot,
+    mapping: GamepadMapping,
+    positions: Option<SharedPositionStore>,
+    preset: usize,
+    // Input holding the running jog and its direction, other inputs are ignored until it is released
+    active: Option<(InputSource, i8)>,
+}
+
+impl Teleoperation {
+    async fn handle(&mut self, event_type: u16, code: u16, value: i32) {
+        match event_type {
+            EV_ABS => {
+                let Some(axis) = self.mapping.axes.iter().find(|axis| axis.code == code).cloned() else {
+                    return;
+                };
+                let direction = self.mapping.axis_direction(&axis, value);
+
+                self.update_jog(InputSource::Axis(code), direction, axis.target, axis.frame).await;
+            }
+            // 1 is a press, 0 a release and 2 an autorepeat
+            EV_KEY if value != 2 => {
+                let Some(button) = self.mapping.buttons.iter().find(|button| button.code == code).cloned() else {
+                    return;
+                };
+
+                match (button.action, value) {
+                    (ButtonAction::Jog { target, direction, frame }, _) => {
+                        let direction = if value == 1 { direction } else { 0 };
+                        self.update_jog(InputSource::Button(code), direction, target, frame).await;
+                    }
+                    (ButtonAction::SpeedUp, 1) => self.change_preset(1),
+                    (ButtonAction::SpeedDown, 1) => self.change_preset(-1),
+                    (ButtonAction::SavePosition, 1) => self.save_position().await,
+                    (ButtonAction::EmergencyStop, 1) => {
+                        self.active = None;
+                        if let Err(e) = self.robot.emergency_stop().await {
+                            eprintln!("###DEBUG### - Gamepad emergency stop failed: {}", e);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Starts, reverses or stops the jog held by `source`, `direction` 0 means released
+    async fn update_jog(&mut self, source: InputSource, direction: i8, target: JogTarget, frame: Option<JogFrame>) {
+        match self.active {
+            Some((active, active_direction)) if active == source => {
+                if direction == active_direction {
+                    return;
+                }
+                self.robot.stop_jog().await;
+                self.active = None;
+            }
+            Some(_) => return,
+            None => {}
+        }
+
+        if direction == 0 {
+            return;
+        }
+
+        let speed = self.mapping.speed_presets[self.preset];
+        match self
+            .robot
+            .start_jog(target, direction, speed, frame.unwrap_or(JogFrame::Base))
+            .await
+        {
+            Ok(()) => self.active = Some((source, direction)),
+            Err(e) => eprintln!("###DEBUG### - Gamepad jog of {:?}: {}", target, e),
+        }
+    }
+
+    // Applies to the next jog, a running one keeps its speed
+    fn change_preset(&mut self, step: isize) {
+        let last = self.mapping.speed_presets.len() - 1;
+        self.preset = self.preset.saturating_add_signed(step).min(last);
+
+        eprintln!(
+            "###DEBUG### - Gamepad jog speed: {}",
+            self.mapping.speed_presets[self.preset]
+        );
+    }
+
+    async fn save_position(&self) {
+        let Some(store) = &self.positions else {
+            eprintln!("###DEBUG### - Gamepad has no position store to save to");
+            return;
+        };
+
+        let name = {
+            let store = store.lock().await;
+            (1..)
+                .map(|n| format!("Gamepad {}", n))
+                .find(|name| store.get(name).is_err())
+                .unwrap_or_default()
+        };
+
+        match positions::save_current_position(&self.robot, store, &name, None, None).await {
+            Ok(_) => eprintln!("###DEBUG### - Gamepad saved position '{}'", name),
+            Err(e) => eprintln!("###DEBUG### - Gamepad could not save a position: {}", e),
+        }
+    }
+}
+
+// Reads raw events from `device` until it is closed or fails, e.g. when the controller is unplugged
+async fn read_events(device: String, events: mpsc::Sender<(u16, u16, i32)>) -> Result<(), String> {
+    let mut file = tokio::fs::File::open(&device)
+        .await
+        .map_err(|e| format!("Failed to open gamepad {}: {}", device, e))?;
+    let mut buffer = [0u8; INPUT_EVENT_SIZE];
+    let offset = INPUT_EVENT_SIZE - 8;
+
+    loop {
+        match file.read_exact(&mut buffer).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(format!("Failed to read gamepad {}: {}", device, e)),
+        }
+
+        let event_type = u16::from_ne_bytes([buffer[offset], buffer[offset + 1]]);
+        let code = u16::from_ne_bytes([buffer[offset + 2], buffer[offset + 3]]);
+        let value = i32::from_ne_bytes(buffer[offset + 4..offset + 8].try_into().unwrap());
+
+        if events.send((event_type, code, value)).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+// Drives the arm from `device` (the mapping device when `None`) until the device is closed. Any
+// jog still running is then stopped
+pub async fn run_gamepad(
+    robot: Robot,
+    mapping: GamepadMapping,
+    device: Option<String>,
+    positions: Option<SharedPositionStore>,
+) -> Result<(), String> {
+    let device = device
+        .or_else(|| mapping.device.clone())
+        .ok_or("No gamepad device given, set one in the mapping or pass it")?;
+
+    let (sender, mut receiver) = mpsc::channel(64);
+    let reader = tokio::spawn(read_events(device.clone(), sender));
+
+    eprintln!("###DEBUG### - Gamepad reading from {}", device);
+
+    let mut teleoperation = Teleoperation {
+        robot: robot.clone(),
+        mapping,
+        positions,
+        preset: 0,
+        active: None,
+    };
+    let mut heartbeat = interval(HEARTBEAT_PERIOD);
+
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some((event_type, code, value)) => teleoperation.handle(event_type, code, value).await,
+                None => break,
+            },
+            _ = heartbeat.tick() => {
+                // The jog may have ended by itself, at the end of its range
+                if teleoperation.active.is_some() && !robot.heartbeat_jog() {
+                    teleoperation.active = None;
+                }
+            }
+        }
+    }
+
+    if teleoperation.active.is_some() {
+        robot.stop_jog().await;
+    }
+
+    reader.await.map_err(|e| e.to_string())?
+}
+
+// Runs `run_gamepad` on a background task
+pub fn spawn_gamepad(
+    robot: Robot,
+    mapping: GamepadMapping,
+    device: Option<String>,
+    positions: Option<SharedPositionStore>,
+) -> JoinHandle<Result<(), String>> {
+    tokio::spawn(run_gamepad(robot, mapping, device, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(json: &str) -> GamepadMapping {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn stick(mapping: &GamepadMapping) -> &AxisMapping {
+        &mapping.axes[0]
+    }
+
+    #[test]
+    fn input_events_have_the_64_bit_layout() {
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(INPUT_EVENT_SIZE, 24);
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(INPUT_EVENT_SIZE, 16);
+    }
+
+    #[test]
+    fn stick_directions_ignore_the_deadzone() {
+        let mapping = mapping(r#"{ "deadzone": 0.2, "axes": [{ "code": 0, "target": { "joint": 1 } }] }"#);
+        let axis = stick(&mapping);
+
+        assert_eq!(mapping.axis_direction(axis, 0), 0);
+        assert_eq!(mapping.axis_direction(axis, 6000), 0);
+        assert_eq!(mapping.axis_direction(axis, -6000), 0);
+        assert_eq!(mapping.axis_direction(axis, 8000), 1);
+        assert_eq!(mapping.axis_direction(axis, -8000), -1);
+        assert_eq!(mapping.axis_direction(axis, 32767), 1);
+        assert_eq!(mapping.axis_direction(axis, -32768), -1);
+    }
+
+    #[test]
+    fn inverted_axes_jog_the_other_way() {
+        let mapping = mapping(r#"{ "axes": [{ "code": 1, "target": { "axis": "z" }, "invert": true }] }"#);
+        let axis = stick(&mapping);
+
+        assert_eq!(mapping.axis_direction(axis, 32767), -1);
+        assert_eq!(mapping.axis_direction(axis, -32768), 1);
+        assert_eq!(mapping.axis_direction(axis, 0), 0);
+    }
+
+    #[test]
+    fn hats_and_triggers_use_their_own_range() {
+        let mapping = mapping(
+            r#"{ "axes": [
+                { "code": 16, "target": { "joint": 2 }, "min": -1, "max": 1 },
+                { "code": 2, "target": { "joint": 3 }, "min": 0, "max": 255 }
+            ] }"#,
+        );
+        let (hat, trigger) = (&mapping.axes[0], &mapping.axes[1]);
+
+        assert_eq!(mapping.axis_direction(hat, -1), -1);
+        assert_eq!(mapping.axis_direction(hat, 0), 0);
+        assert_eq!(mapping.axis_direction(hat, 1), 1);
+
+        assert_eq!(mapping.axis_direction(trigger, 128), 0);
+        assert_eq!(mapping.axis_direction(trigger, 255), 1);
+        assert_eq!(mapping.axis_direction(trigger, 0), -1);
+    }
+
+    #[test]
+    fn valid_mappings_pass() {
+        assert!(mapping("{}").validate().is_ok());
+        assert!(mapping(
+            r#"{ "deadzone": 0, "speedPresets": [2.5], "buttons": [
+                { "code": 304, "action": { "jog": { "target": { "joint": 6 }, "direction": -1 } } },
+                { "code": 305, "action": "emergencyStop" }
+            ] }"#
+        )
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn invalid_mappings_are_rejected() {
+        let error = |json: &str| mapping(json).validate().unwrap_err();
+
+        assert_eq!(error(r#"{ "deadzone": 1 }"#), "Gamepad deadzone must be between 0 and 1");
+        assert_eq!(error(r#"{ "deadzone": -0.1 }"#), "Gamepad deadzone must be between 0 and 1");
+        assert_eq!(error(r#"{ "speedPresets": [] }"#), "Gamepad speed presets must be greater than 0");
+        assert_eq!(error(r#"{ "speedPresets": [5, 0] }"#), "Gamepad speed presets must be greater than 0");
+        assert_eq!(
+            error(r#"{ "axes": [{ "code": 16, "target": { "joint": 1 }, "min": 1, "max": 1 }] }"#),
+            "Gamepad axis 16 has a min not below its max"
+        );
+        assert_eq!(
+            error(r#"{ "buttons": [{ "code": 304, "action": { "jog": { "target": { "joint": 1 }, "direction": 2 } } }] }"#),
+            "Gamepad button 304 must jog in direction 1 or -1"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+// Import of G-code from CAM tools. Coordinates are relative to a work object frame and turned into
+// LIN> moves in the base frame of the arm, the result is a .rob script. Supported codes:
+//
+//   G0 / G1 X.. Y.. Z..     rapid / feed move, axes left out keep their value. Modal, so lines with
+//                           only axes repeat the last one
+//   G4 P<ms> | G4 S<s>      dwell
+//   G20 / G21               inches / mm
+//   G90 / G91               absolute / relative coordinates
+//   G17 / G94               XY plane, feed per minute, the only ones there are here
+//   F<feed>                 feed rate of G1 in mm/min, or inches/min after G20
+//   M<n>                    the .rob line configured for it. M2 and M30 end the program unless configured
+//
+// N line numbers and comments in parentheses or after ';' are ignored. Anything else is reported with
+// its line and left out of the script
+use crate::constants;
+use crate::kinematics::{self, CartesianPose, Transform};
+use crate::script::format_number;
+use crate::validation::{Diagnostic, Severity};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const MM_PER_INCH: f32 = 25.4;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GcodeConfig {
+    // Pose of the work object frame in the base frame, in mm and degrees
+    pub work_object: CartesianPose,
+    // Roll, pitch and yaw of the tool in the base frame during moves. When left out the tool keeps
+    // the orientation it has when the program starts
+    pub orientation: Option<[f32; 3]>,
+    // .rob line run for an M-code, e.g. 3 -> "CALL close_gripper()"
+    pub mcodes: BTreeMap<u32, String>,
+    // Lines put at the top of the script, e.g. the INCLUDE of the subroutines M-codes call
+    pub header: Vec<String>,
+}
+
+impl Default for GcodeConfig {
+    fn default() -> Self {
+        Self {
+            work_object: CartesianPose { x: 0.0, y: 0.0, z: 0.0, roll: 0.0, pitch: 0.0, yaw: 0.0 },
+            orientation: None,
+            mcodes: BTreeMap::new(),
+            header: Vec::new(),
+        }
+    }
+}
+
+impl GcodeConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read G-code configuration {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content).map_err(|e| format!("Invalid G-code configuration {}: {}", path.display(), e))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcodeImport {
+    pub script: String,
+    // Unsupported codes are errors, their lines are left out of the script
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl GcodeImport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Motion {
+    Rapid,
+    Feed,
+}
+
+// Letter and value of every word of a line, without comments and line numbers
+fn parse_words(line: &str) -> Result<Vec<(char, f32)>, String> {
+    let mut code = String::new();
+    let mut in_comment = false;
+    for c in line.chars() {
+        match c {
+            ';' if !in_comment => break,
+            '(' => in_comment = true,
+            ')' => in_comment = false,
+            // Program start and end markers
+            '%' => {}
+            c if !in_comment && !c.is_whitespace() => code.push(c.to_ascii_uppercase()),
+            _ => {}
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut chars = code.chars().peekable();
+    while let Some(letter) = chars.next() {
+        if !letter.is_ascii_alphabetic() {
+            return Err(format!("Expected a letter, got '{}'", letter));
+        }
+
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+')) {
+            number.push(c);
+        }
+        let value = number
+            .parse::<f32>()
+            .map_err(|_| format!("Invalid number '{}' after {}", number, letter))?;
+
+        if letter != 'N' {
+            words.push((letter, value));
+        }
+    }
+
+    Ok(words)
+}
+
+struct Importer<'a> {
+    config: &'a GcodeConfig,
+    work_object: Transform,
+    lines: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    motion: Option<Motion>,
+    absolute: bool,
+    // Millimeters per unit of the coordinates and feed
+    scale: f32,
+    // Feed rate in mm/min
+    feed: Option<f32>,
+    // Tool position in the work object frame, `None` until a move sets the axis
+    position: [Option<f32>; 3],
+    ended: bool,
+}
+
+impl Importer<'_> {
+    fn report(&mut self, line: usize, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            file: None,
+            severity,
+            message,
+        });
+    }
+
+    // Converts one line. On errors it is left out and changes nothing
+    fn line(&mut self, content: &str, line: usize) -> Result<(), String> {
+        let words = parse_words(content)?;
+        let mut motion = None;
+        let mut dwell = false;
+        let mut scale = None;
+        let mut absolute = None;
+        let mut axes = [None; 3];
+        let mut feed = None;
+        let mut dwell_time = None;
+        let mut mcodes = Vec::new();
+        let mut unsupported = Vec::new();
+
+        for &(letter, value) in &words {
+            match (letter, value) {
+                ('G', 0.0) => motion = Some(Motion::Rapid),
+                ('G', 1.0) => motion = Some(Motion::Feed),
+                ('G', 4.0) => dwell = true,
+                ('G', 20.0) => scale = Some(MM_PER_INCH),
+                ('G', 21.0) => scale = Some(1.0),
+                ('G', 90.0) => absolute = Some(true),
+                ('G', 91.0) => absolute = Some(false),
+                ('G', 17.0 | 94.0) => {}
+                ('X', value) => axes[0] = Some(value),
+                ('Y', value) => axes[1] = Some(value),
+                ('Z', value) => axes[2] = Some(value),
+                ('F', value) => feed = Some(value),
+                ('P', value) => dwell_time = Some(value),
+                ('S', value) => dwell_time = Some(value * 1000.0),
+                ('M', value) if self.config.mcodes.contains_key(&(value as u32)) || value == 2.0 || value == 30.0 => {
+                    mcodes.push(value as u32)
+                }
+                (letter, value) => unsupported.push(format!("{}{}", letter, value)),
+            }
+        }
+
+        if !unsupported.is_empty() {
+            let hint = match unsupported.iter().any(|code| code.starts_with('M')) {
+                true => ", M-codes can be mapped to script lines in the configuration",
+                false => "",
+            };
+            return Err(format!("Unsupported code {}{}", unsupported.join(" "), hint));
+        }
+        if dwell_time.is_some() && !dwell {
+            return Err("P and S are only supported with G4".to_string());
+        }
+        if dwell && (motion.is_some() || axes.iter().any(|axis| axis.is_some())) {
+            return Err("G4 cannot be on the same line as a move".to_string());
+        }
+
+        let mut statements = Vec::new();
+        let mut ended = false;
+        for mcode in mcodes {
+            match self.config.mcodes.get(&mcode) {
+                Some(statement) => statements.push(statement.clone()),
+                // M2 or M30
+                None => ended = true,
+            }
+        }
+
+        // Modes apply to the whole line, whatever the order of the words
+        let scale = scale.unwrap_or(self.scale);
+        let absolute = absolute.unwrap_or(self.absolute);
+        let feed = feed.map(|feed| feed * scale).or(self.feed);
+        let motion = motion.or(self.motion);
+
+        if dwell {
+            let milliseconds = dwell_time.ok_or("G4 needs P<ms> or S<s>")?;
+            if milliseconds < 0.0 {
+                return Err(format!("Dwell must be at least 0 ms, got {}", milliseconds));
+            }
+            statements.insert(0, format!("WAIT {}", format_number(milliseconds)));
+        }
+        if axes.iter().any(|axis| axis.is_some()) {
+            let motion = motion.ok_or("Move without G0 or G1")?;
+            let (statement, position) = self.linear_move(motion, axes, scale, absolute, feed, line)?;
+            statements.insert(0, statement);
+            self.position = position;
+        }
+
+        self.scale = scale;
+        self.absolute = absolute;
+        self.feed = feed;
+        self.motion = motion;
+        self.ended = ended;
+        self.lines
+            .extend(statements.into_iter().map(|statement| format!("{} // G-code line {}", statement, line)));
+
+        Ok(())
+    }
+
+    // LIN> line of a move and the position it ends at
+    fn linear_move(
+        &mut self,
+        motion: Motion,
+        axes: [Option<f32>; 3],
+        scale: f32,
+        absolute: bool,
+        feed: Option<f32>,
+        line: usize,
+    ) -> Result<(String, [Option<f32>; 3]), String> {
+        let mut position = self.position;
+        for (current, axis) in position.iter_mut().zip(axes) {
+            let Some(value) = axis else {
+                continue;
+            };
+            *current = match (absolute, *current) {
+                (true, _) => Some(value * scale),
+                (false, Some(current)) => Some(current + value * scale),
+                (false, None) => return Err("Relative move before the position is known".to_string()),
+            };
+        }
+
+        let [Some(x), Some(y), Some(z)] = position else {
+            return Err("The first move needs X, Y and Z".to_string());
+        };
+
+        let target = kinematics::add(
+            &kinematics::mat_vec(&self.work_object.rotation, &[x as f64, y as f64, z as f64]),
+            &self.work_object.translation,
+        );
+        let mut statement = format!(
+            "LIN>X_{};Y_{};Z_{};",
+            format_number(target[0] as f32),
+            format_number(target[1] as f32),
+            format_number(target[2] as f32)
+        );
+
+        if let Some([roll, pitch, yaw]) = self.config.orientation {
+            statement.push_str(&format!("ROLL_{};PITCH_{};YAW_{};", format_number(roll), format_number(pitch), format_number(yaw)));
+        }
+
+        if motion == Motion::Feed {
+            match feed {
+                Some(feed) if feed <= 0.0 => return Err(format!("Feed rate must be greater than 0, got {}", feed)),
+                Some(feed) => {
+                    let mut speed = feed / 60.0;
+                    if speed > constants::MAX_TOOL_SPEED {
+                        self.report(
+                            line,
+                            Severity::Warning,
+                            format!(
+                                "Feed of {} mm/min is over the tool speed limit, capped at {} mm/s",
+                                format_number(feed),
+                                constants::MAX_TOOL_SPEED
+                            ),
+                        );
+                        speed = constants::MAX_TOOL_SPEED;
+                    }
+                    statement.push_str(&format!("SPEED_{};", format_number(speed)));
+                }
+                None => self.report(
+                    line,
+                    Severity::Warning,
+                    format!("G1 without a feed rate runs at the tool speed limit of {} mm/s", constants::MAX_TOOL_SPEED),
+                ),
+            }
+        }
+
+        Ok((statement, position))
+    }
+}
+
+// Converts G-code into a .rob script. Lines with unsupported codes are reported as errors and left
+// out, with a comment where they were
+pub fn import_gcode(content: &str, config: &GcodeConfig) -> GcodeImport {
+    let mut importer = Importer {
+        config,
+        work_object: Transform::from_pose(&config.work_object),
+        lines: vec!["// Imported from G-code".to_string()],
+        diagnostics: Vec::new(),
+        motion: None,
+        absolute: true,
+        scale: 1.0,
+        feed: None,
+        position: [None; 3],
+        ended: false,
+    };
+    importer.lines.extend(config.header.iter().cloned());
+
+    for (i, content) in content.lines().enumerate() {
+        if importer.ended {
+            break;
+        }
+
+        if let Err(e) = importer.line(content, i + 1) {
+            importer.lines.push(format!("// Skipped G-code line {}: {}", i + 1, content.trim()));
+            importer.report(i + 1, Severity::Error, e);
+        }
+    }
+
+    importer.lines.push(String::new());
+
+    GcodeImport {
+        script: importer.lines.join("\n"),
+        diagnostics: importer.diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Statements of the script without the G-code line comments
+    fn statements(import: &GcodeImport) -> Vec<&str> {
+        import
+            .script
+            .lines()
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(|line| line.split(" // G-code line").next().unwrap())
+            .collect()
+    }
+
+    fn messages(import: &GcodeImport, severity: Severity) -> Vec<(usize, &str)> {
+        import
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn moves_are_modal() {
+        let import = import_gcode("G0 X1 Y2 Z3\nX4\nG1 X5 F600\nY6", &GcodeConfig::default());
+
+        assert!(!import.has_errors());
+        assert_eq!(
+            statements(&import),
+            ["LIN>X_1;Y_2;Z_3;", "LIN>X_4;Y_2;Z_3;", "LIN>X_5;Y_2;Z_3;SPEED_10;", "LIN>X_5;Y_6;Z_3;SPEED_10;"]
+        );
+    }
+
+    #[test]
+    fn modes_apply_to_the_whole_line() {
+        let import = import_gcode("G0 X0 Y0 Z0\nX1 G20 G91\nX1\nG21 G90 Z5", &GcodeConfig::default());
+
+        assert!(!import.has_errors());
+        assert_eq!(
+            statements(&import),
+            ["LIN>X_0;Y_0;Z_0;", "LIN>X_25.4;Y_0;Z_0;", "LIN>X_50.8;Y_0;Z_0;", "LIN>X_50.8;Y_0;Z_5;"]
+        );
+    }
+
+    #[test]
+    fn relative_moves_need_a_known_position() {
+        let import = import_gcode("G91 G0 X1 Y1 Z1\nG90 G0 X1\nG0 X1 Y1 Z1\nG91 X1", &GcodeConfig::default());
+
+        assert_eq!(
+            messages(&import, Severity::Error),
+            [(1, "Relative move before the position is known"), (2, "The first move needs X, Y and Z")]
+        );
+        assert_eq!(
+            statements(&import),
+            [
+                "// Skipped G-code line 1: G91 G0 X1 Y1 Z1",
+                "// Skipped G-code line 2: G90 G0 X1",
+                "LIN>X_1;Y_1;Z_1;",
+                "LIN>X_2;Y_1;Z_1;"
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_mcodes_take_over_program_ends() {
+        let config = GcodeConfig {
+            mcodes: BTreeMap::from([(3, "CALL close_gripper()".to_string()), (30, "CALL park()".to_string())]),
+            ..GcodeConfig::default()
+        };
+        let import = import_gcode("G0 X0 Y0 Z0\nM3\nM30\nM5\nX1\nM2\nX2", &config);
+
+        assert_eq!(
+            messages(&import, Severity::Error),
+            [(4, "Unsupported code M5, M-codes can be mapped to script lines in the configuration")]
+        );
+        assert_eq!(
+            statements(&import),
+            [
+                "LIN>X_0;Y_0;Z_0;",
+                "CALL close_gripper()",
+                "CALL park()",
+                "// Skipped G-code line 4: M5",
+                "LIN>X_1;Y_0;Z_0;"
+            ]
+        );
+    }
+
+    #[test]
+    fn feed_is_capped_at_the_tool_speed() {
+        let import = import_gcode("G1 X0 Y0 Z0 F12000\nG0 X1\nG1 X2 F0", &GcodeConfig::default());
+
+        assert_eq!(
+            messages(&import, Severity::Warning),
+            [(1, "Feed of 12000 mm/min is over the tool speed limit, capped at 100 mm/s")]
+        );
+        assert_eq!(messages(&import, Severity::Error), [(3, "Feed rate must be greater than 0, got 0")]);
+        assert_eq!(statements(&import)[..2], ["LIN>X_0;Y_0;Z_0;SPEED_100;", "LIN>X_1;Y_0;Z_0;"]);
+
+        let import = import_gcode("G1 X0 Y0 Z0", &GcodeConfig::default());
+        assert_eq!(
+            messages(&import, Severity::Warning),
+            [(1, "G1 without a feed rate runs at the tool speed limit of 100 mm/s")]
+        );
+        assert_eq!(statements(&import), ["LIN>X_0;Y_0;Z_0;"]);
+    }
+
+    #[test]
+    fn comments_and_line_numbers_are_ignored() {
+        let import = import_gcode("%\n(Setup)\nN10 g0 (rapid) x1 Y2 Z3 ; first point\nN20 G4 P250;", &GcodeConfig::default());
+
+        assert!(import.diagnostics.is_empty());
+        assert_eq!(statements(&import), ["LIN>X_1;Y_2;Z_3;", "WAIT 250"]);
+    }
+}
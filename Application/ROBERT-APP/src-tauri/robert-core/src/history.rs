@@ -0,0 +1,267 @@
+// History of script runs for traceability, persisted to a JSON file like the stored positions.
+// Every run records what was run, when, by whose note, how it ended and what the arm answered
+use crate::interpreter::Interpreter;
+use crate::script::Script;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+// Version written to history files, bump when the format changes
+pub const HISTORY_FILE_VERSION: u32 = 1;
+// Oldest runs are dropped past this many
+pub const MAX_RUNS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunResult {
+    Completed,
+    // Stopped by the operator
+    Aborted,
+    Error,
+}
+
+impl RunResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunResult::Completed => "completed",
+            RunResult::Aborted => "aborted",
+            RunResult::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineResponse {
+    pub line: usize,
+    pub response: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptRun {
+    pub id: u64,
+    pub script: String,
+    // SHA-256 of the script content, tells runs of different versions of a script apart. Included
+    // files are not part of it
+    pub hash: String,
+    // Milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub note: Option<String>,
+    pub result: RunResult,
+    // Line of the script where execution stopped, `None` when it completed
+    pub stopped_at: Option<usize>,
+    pub error: Option<String>,
+    // Response of every command sent to the arm, with the line that sent it
+    pub responses: Vec<LineResponse>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Unknown export format '{}', expected json or csv", format)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    runs: Vec<ScriptRun>,
+}
+
+fn read_history_file(path: &Path) -> Result<Vec<ScriptRun>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read history file {}: {}", path.display(), e))?;
+    let file: HistoryFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid history file {}: {}", path.display(), e))?;
+
+    if file.version > HISTORY_FILE_VERSION {
+        return Err(format!(
+            "History file {} has version {}, only up to {} is supported",
+            path.display(),
+            file.version,
+            HISTORY_FILE_VERSION
+        ));
+    }
+
+    Ok(file.runs)
+}
+
+fn write_history_file(path: &Path, runs: &[ScriptRun]) -> Result<(), String> {
+    let file = HistoryFile {
+        version: HISTORY_FILE_VERSION,
+        runs: runs.to_vec(),
+    };
+
+    utils::write_atomic(path, &serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?)
+}
+
+// Quotes a CSV field when it has to be
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+// One row per run, the responses of a run are in a single field with one "line: response" per line
+pub fn to_csv(runs: &[ScriptRun]) -> String {
+    let mut csv = "id,script,hash,startedAt,endedAt,note,result,stoppedAt,error,responses\r\n".to_string();
+
+    for run in runs {
+        let responses: Vec<String> = run
+            .responses
+            .iter()
+            .map(|response| format!("{}: {}", response.line, response.response))
+            .collect();
+        let fields = [
+            run.id.to_string(),
+            run.script.clone(),
+            run.hash.clone(),
+            run.started_at.to_string(),
+            run.ended_at.to_string(),
+            run.note.clone().unwrap_or_default(),
+            run.result.as_str().to_string(),
+            run.stopped_at.map(|line| line.to_string()).unwrap_or_default(),
+            run.error.clone().unwrap_or_default(),
+            responses.join("\n"),
+        ];
+
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+pub fn script_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Script runs persisted to a JSON file, oldest first. Every run is written straight to disk
+#[derive(Debug)]
+pub struct RunHistory {
+    path: PathBuf,
+    runs: Vec<ScriptRun>,
+}
+
+impl RunHistory {
+    // Loads the history from `path`, starting empty when the file does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let runs = if path.exists() {
+            read_history_file(&path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, runs })
+    }
+
+    pub fn list(&self) -> &[ScriptRun] {
+        &self.runs
+    }
+
+    // Adds `run` with the next id, which it returns
+    pub fn add(&mut self, mut run: ScriptRun) -> Result<u64, String> {
+        run.id = self.runs.last().map_or(1, |last| last.id + 1);
+        let id = run.id;
+
+        self.runs.push(run);
+        if self.runs.len() > MAX_RUNS {
+            self.runs.drain(..self.runs.len() - MAX_RUNS);
+        }
+
+        write_history_file(&self.path, &self.runs)?;
+
+        Ok(id)
+    }
+
+    pub fn export(&self, path: &Path, format: ExportFormat) -> Result<(), String> {
+        match format {
+            ExportFormat::Json => write_history_file(path, &self.runs),
+            ExportFormat::Csv => utils::write_atomic(path, &to_csv(&self.runs)),
+        }
+    }
+}
+
+pub type SharedRunHistory = Arc<Mutex<RunHistory>>;
+
+// Runs `script` with `interpreter` like `Interpreter::run` and records the run in `history`.
+// `content` is what the script was parsed from, for its hash. Failing to record is only logged,
+// the result is the one of the run
+pub async fn run_recorded<F>(
+    interpreter: &mut Interpreter,
+    script: &Script,
+    name: &str,
+    content: &str,
+    note: Option<String>,
+    history: &SharedRunHistory,
+    mut on_response: F,
+) -> Result<(), String>
+where
+    F: FnMut(usize, String),
+{
+    let started_at = now_millis();
+    let mut responses = Vec::new();
+
+    let result = interpreter
+        .run(script, |line, response| {
+            responses.push(LineResponse {
+                line,
+                response: response.trim().to_string(),
+            });
+            on_response(line, response);
+        })
+        .await;
+
+    let run = ScriptRun {
+        id: 0,
+        script: name.to_string(),
+        hash: script_hash(content),
+        started_at,
+        ended_at: now_millis(),
+        note: note.map(|note| note.trim().to_string()).filter(|note| !note.is_empty()),
+        result: match (&result, interpreter.was_stopped()) {
+            (Ok(()), _) => RunResult::Completed,
+            (Err(_), true) => RunResult::Aborted,
+            (Err(_), false) => RunResult::Error,
+        },
+        stopped_at: match result {
+            Ok(()) => None,
+            Err(_) => interpreter.progress().map(|progress| progress.script_line),
+        },
+        error: result.as_ref().err().cloned(),
+        responses,
+    };
+
+    if let Err(e) = history.lock().await.add(run) {
+        eprintln!("###DEBUG### - Failed to record the run of {}: {}", name, e);
+    }
+
+    result
+}
@@ -0,0 +1,236 @@
+// Homing routine. Calibration leaves the joints on their limit switches, homing calibrates them in
+// a safe order, backs each group off its switches and ends in a named home pose
+use crate::calibration;
+use crate::events::RobotEvent;
+use crate::robot::Robot;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HomingConfig {
+    // Groups of joints calibrated together, in order. Joints that are in no group are calibrated last
+    pub order: Vec<Vec<i8>>,
+    // Angle from the joint zero each joint is moved to right after its group is calibrated
+    pub offsets: [f32; 6],
+    // Home poses by name, in positive angles from the joint zero. `None` leaves a joint where it is.
+    // Offsets and poses follow the zero offsets, changing those moves every pose
+    pub poses: BTreeMap<String, [Option<f32>; 6]>,
+    // Pose homing ends in when none is asked for, homing stops after the offsets without one
+    pub pose: Option<String>,
+}
+
+impl Default for HomingConfig {
+    fn default() -> Self {
+        Self {
+            // The upper arm and forearm first, so the base does not swing them into anything
+            order: vec![vec![2, 3], vec![1, 4, 5, 6]],
+            offsets: [0.0; 6],
+            poses: BTreeMap::new(),
+            pose: None,
+        }
+    }
+}
+
+impl HomingConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read homing configuration {}: {}", path.display(), e))?;
+        let config: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid homing configuration {}: {}", path.display(), e))?;
+
+        config
+            .check()
+            .map_err(|e| format!("Invalid homing configuration {}: {}", path.display(), e))?;
+
+        Ok(config)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let mut seen = [false; 6];
+        for &joint in self.order.iter().flatten() {
+            if !(1..=6).contains(&joint) {
+                return Err(format!("Invalid joint J{} in the order", joint));
+            }
+            if std::mem::replace(&mut seen[joint as usize - 1], true) {
+                return Err(format!("J{} is more than once in the order", joint));
+            }
+        }
+
+        match &self.pose {
+            Some(pose) => self.home_pose(pose).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    // Joint ranges depend on the zero offsets, so offsets and poses are checked when homing
+    fn check_angles(&self, zero_offsets: &[f32; 6]) -> Result<(), String> {
+        for (i, offset) in self.offsets.iter().enumerate() {
+            calibration::check_joint_angle(i as i8 + 1, *offset, zero_offsets)
+                .map_err(|e| format!("Homing offset: {}", e))?;
+        }
+
+        for (name, angles) in &self.poses {
+            for (i, angle) in angles.iter().enumerate() {
+                if let Some(angle) = angle {
+                    calibration::check_joint_angle(i as i8 + 1, *angle, zero_offsets)
+                        .map_err(|e| format!("Pose '{}': {}", name, e))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn home_pose(&self, name: &str) -> Result<&[Option<f32>; 6], String> {
+        self.poses.get(name).ok_or_else(|| match self.poses.is_empty() {
+            true => format!("Home pose '{}' does not exist, none is configured", name),
+            false => format!(
+                "Home pose '{}' does not exist, the configured ones are {}",
+                name,
+                self.poses.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        })
+    }
+
+    // Groups to calibrate `joints` in, following the configured order
+    fn groups(&self, joints: &[i8]) -> Vec<Vec<i8>> {
+        let mut groups: Vec<Vec<i8>> = self
+            .order
+            .iter()
+            .map(|group| group.iter().copied().filter(|joint| joints.contains(joint)).collect())
+            .collect();
+
+        let mut rest: Vec<i8> = joints
+            .iter()
+            .copied()
+            .filter(|joint| !self.order.iter().flatten().any(|ordered| ordered == joint))
+            .collect();
+        rest.sort();
+        rest.dedup();
+        groups.push(rest);
+
+        groups.retain(|group| !group.is_empty());
+        groups
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HomingStage {
+    Calibrating,
+    // Moving off the limit switches to the configured offsets
+    Offsetting,
+    MovingToPose,
+    Done,
+}
+
+// Sent when homing starts a stage
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HomingProgress {
+    pub stage: HomingStage,
+    // Joints the stage moves
+    pub joints: Vec<i8>,
+    // Home pose moved to, only set on the last stages
+    pub pose: Option<String>,
+    // Stages done so far and how many there are
+    pub step: usize,
+    pub steps: usize,
+}
+
+// Calibrates `joints`, every joint when empty, group by group in the configured order. Each group
+// is moved to its offsets before the next one is calibrated. Ends in the home pose `pose`, or in the
+// configured one when left out
+pub async fn home(robot: &Robot, config: &HomingConfig, joints: &[i8], pose: Option<&str>) -> Result<String, String> {
+    if let Some(joint) = joints.iter().find(|joint| !(1..=6).contains(*joint)) {
+        return Err(format!("Invalid joint J{}", joint));
+    }
+    let joints: Vec<i8> = match joints.is_empty() {
+        true => (1..=6).collect(),
+        false => joints.to_vec(),
+    };
+
+    config.check_angles(&robot.zero_offsets().await)?;
+
+    let pose = pose.map(str::to_string).or_else(|| config.pose.clone());
+    let pose_angles = pose.as_deref().map(|pose| config.home_pose(pose)).transpose()?;
+
+    let groups = config.groups(&joints);
+    let offsets: Vec<Vec<(i8, f32)>> = groups
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .map(|&joint| (joint, config.offsets[joint as usize - 1]))
+                .filter(|(_, offset)| *offset != 0.0)
+                .collect()
+        })
+        .collect();
+    let steps = groups.len() + offsets.iter().filter(|offsets| !offsets.is_empty()).count() + pose_angles.is_some() as usize;
+
+    let mut step = 0;
+    let mut report = |stage: HomingStage, joints: Vec<i8>, pose: Option<String>| {
+        robot.emit(RobotEvent::HomingProgress(HomingProgress {
+            stage,
+            joints,
+            pose,
+            step,
+            steps,
+        }));
+        step += 1;
+    };
+
+    for (group, offsets) in groups.iter().zip(offsets) {
+        report(HomingStage::Calibrating, group.clone(), None);
+        robot.calibrate(group).await?;
+
+        // A joint that missed its switch is reported by the firmware without failing the command
+        let angles = robot.angles().await?;
+        let failed: Vec<String> = group
+            .iter()
+            .filter(|joint| angles[**joint as usize - 1].is_none())
+            .map(|joint| format!("J{}", joint))
+            .collect();
+        if !failed.is_empty() {
+            return Err(format!("Homing stopped, {} did not calibrate", failed.join(", ")));
+        }
+
+        if !offsets.is_empty() {
+            report(HomingStage::Offsetting, offsets.iter().map(|(joint, _)| *joint).collect(), None);
+            robot.move_joints(offsets).await?;
+        }
+    }
+
+    if let (Some(pose), Some(pose_angles)) = (&pose, pose_angles) {
+        // Joints that are not calibrated keep still, they may not be built or homed yet
+        let angles = robot.angles().await?;
+        let joints_angles: Vec<(i8, f32)> = pose_angles
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| angles[*i].is_some())
+            .filter_map(|(i, angle)| angle.map(|angle| (i as i8 + 1, angle)))
+            .collect();
+
+        report(
+            HomingStage::MovingToPose,
+            joints_angles.iter().map(|(joint, _)| *joint).collect(),
+            Some(pose.clone()),
+        );
+        if !joints_angles.is_empty() {
+            robot.move_joints(joints_angles).await?;
+        }
+    }
+
+    report(HomingStage::Done, joints.clone(), pose.clone());
+
+    Ok(match pose {
+        Some(pose) => format!("Homed {} to pose '{}'.", format_joints(&joints), pose),
+        None => format!("Homed {}.", format_joints(&joints)),
+    })
+}
+
+fn format_joints(joints: &[i8]) -> String {
+    joints.iter().map(|joint| format!("J{}", joint)).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,413 @@
+// Runs parsed .rob scripts. Blocks are walked with an explicit stack instead of recursion, each
+// frame keeps its place in the block and how many more times it repeats
+use crate::debugger::{Decision, Debugger, ScriptPaused};
+use crate::events::RobotEvent;
+use crate::positions::{self, SharedPositionStore};
+use crate::robot::Robot;
+use crate::script::{self, Expression, Instruction, ParsedStatement, Script, Statement};
+use crate::utils;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::time::sleep;
+
+// CALL of a subroutine that is still running
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallSite {
+    pub name: String,
+    pub line: usize,
+    // Included file the CALL is in, `None` for the script itself
+    pub file: Option<String>,
+}
+
+// Sent right before a script line runs
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptProgress {
+    pub line: usize,
+    pub file: Option<String>,
+    // Line of the script itself being run, the CALL or INCLUDE when `line` is in another file
+    pub script_line: usize,
+    // Outermost call first
+    pub call_stack: Vec<CallSite>,
+}
+
+struct Frame<'s> {
+    statements: &'s [ParsedStatement],
+    next: usize,
+    repeats_left: u32,
+    // Set on subroutine frames, with the variables of the caller to restore on return
+    call: Option<(CallSite, HashMap<String, f32>)>,
+}
+
+impl<'s> Frame<'s> {
+    fn new(statements: &'s [ParsedStatement]) -> Self {
+        Self {
+            statements,
+            next: 0,
+            repeats_left: 0,
+            call: None,
+        }
+    }
+
+    // Statement that last started in this frame
+    fn current(&self) -> Option<&'s ParsedStatement> {
+        self.statements.get(self.next.checked_sub(1)?)
+    }
+}
+
+// The innermost statement from the script itself, subroutines it defines included
+fn script_line(frames: &[Frame], parsed: &ParsedStatement) -> usize {
+    frames
+        .iter()
+        .rev()
+        .filter_map(|frame| frame.current())
+        .find(|statement| statement.file.is_none())
+        .map_or(parsed.line, |statement| statement.line)
+}
+
+fn call_stack(frames: &[Frame]) -> Vec<CallSite> {
+    frames
+        .iter()
+        .filter_map(|frame| frame.call.as_ref().map(|(site, _)| site.clone()))
+        .collect()
+}
+
+// Error of `parsed` with where it happened and the calls that led there, innermost first
+fn error_at(frames: &[Frame], parsed: &ParsedStatement, error: String) -> String {
+    let mut message = format!("{}: {}", script::location(parsed.line, parsed.file.as_deref()), error);
+
+    for site in call_stack(frames).iter().rev() {
+        message.push_str(&format!(
+            "\n    in {}, called from {}",
+            site.name,
+            script::location(site.line, site.file.as_deref())
+        ));
+    }
+
+    message
+}
+
+pub struct Interpreter {
+    robot: Robot,
+    // Stored positions for GOTO_POS, `None` when the frontend has none
+    positions: Option<SharedPositionStore>,
+    variables: HashMap<String, f32>,
+    debugger: Option<Debugger>,
+    // Statement the last run got to
+    progress: Option<ScriptProgress>,
+    // Whether the last run was stopped through the debugger
+    stopped: bool,
+}
+
+impl Interpreter {
+    pub fn new(robot: Robot, positions: Option<SharedPositionStore>) -> Self {
+        Self {
+            robot,
+            positions,
+            variables: HashMap::new(),
+            debugger: None,
+            progress: None,
+            stopped: false,
+        }
+    }
+
+    // Pauses the run at breakpoints and steps as told by the debugger's handle
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    pub fn variables(&self) -> &HashMap<String, f32> {
+        &self.variables
+    }
+
+    // Where the last run is or where it stopped, `None` before it runs a statement
+    pub fn progress(&self) -> Option<&ScriptProgress> {
+        self.progress.as_ref()
+    }
+
+    pub fn was_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    // Runs `script` to the end, calling `on_response` with the line and response of every command
+    // sent to the arm. Errors stop the script and name the line and calls they happened in
+    pub async fn run<F>(&mut self, script: &Script, mut on_response: F) -> Result<(), String>
+    where
+        F: FnMut(usize, String),
+    {
+        let mut frames = vec![Frame::new(&script.statements)];
+        self.progress = None;
+        self.stopped = false;
+
+        while let Some(frame) = frames.last_mut() {
+            let Some(parsed) = frame.statements.get(frame.next) else {
+                if frame.repeats_left > 0 {
+                    frame.repeats_left -= 1;
+                    frame.next = 0;
+                } else if let Some((_, caller_variables)) = frames.pop().and_then(|frame| frame.call) {
+                    self.variables = caller_variables;
+                }
+                continue;
+            };
+            frame.next += 1;
+
+            self.report_progress(&frames, parsed);
+            self.debug(&frames, parsed).await?;
+
+            match &parsed.statement {
+                Statement::Repeat(count, body) => {
+                    let count = self.repeat_count(count).map_err(|e| error_at(&frames, parsed, e))?;
+                    if count > 0 {
+                        frames.push(Frame {
+                            repeats_left: count - 1,
+                            ..Frame::new(body)
+                        });
+                    }
+                }
+                Statement::Include(_, statements) => frames.push(Frame::new(statements)),
+                Statement::Call(name, args) => {
+                    let subroutine = script
+                        .subroutines
+                        .get(name)
+                        .ok_or_else(|| error_at(&frames, parsed, format!("Unknown subroutine {}", name)))?;
+                    let args = args
+                        .iter()
+                        .map(|arg| arg.evaluate(&self.variables))
+                        .collect::<Result<Vec<_>, String>>()
+                        .map_err(|e| error_at(&frames, parsed, e))?;
+
+                    let mut variables = self.variables.clone();
+                    variables.extend(subroutine.params.iter().cloned().zip(args));
+                    let caller_variables = std::mem::replace(&mut self.variables, variables);
+
+                    let site = CallSite {
+                        name: name.clone(),
+                        line: parsed.line,
+                        file: parsed.file.as_deref().map(str::to_string),
+                    };
+                    frames.push(Frame {
+                        call: Some((site, caller_variables)),
+                        ..Frame::new(&subroutine.body)
+                    });
+                }
+                statement => {
+                    if let Some(response) = self
+                        .execute(statement)
+                        .await
+                        .map_err(|e| error_at(&frames, parsed, e))?
+                    {
+                        on_response(parsed.line, response);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report_progress(&mut self, frames: &[Frame], parsed: &ParsedStatement) {
+        let progress = ScriptProgress {
+            line: parsed.line,
+            file: parsed.file.as_deref().map(str::to_string),
+            script_line: script_line(frames, parsed),
+            call_stack: call_stack(frames),
+        };
+
+        self.robot.emit(RobotEvent::ScriptProgress(progress.clone()));
+        self.progress = Some(progress);
+    }
+
+    // Asks the debugger, if any, whether to pause before `parsed` and waits while paused
+    async fn debug(&mut self, frames: &[Frame<'_>], parsed: &ParsedStatement) -> Result<(), String> {
+        let Some(debugger) = self.debugger.as_mut() else {
+            return Ok(());
+        };
+        let depth = frames.iter().filter(|frame| frame.call.is_some()).count();
+
+        match debugger.before(parsed.file.is_none().then_some(parsed.line), depth) {
+            Decision::Run => return Ok(()),
+            Decision::Stop => {
+                self.stopped = true;
+                return Err(error_at(frames, parsed, "Script stopped".to_string()));
+            }
+            Decision::Pause => {}
+        }
+
+        // The model is enough, the arm is at rest between statements
+        let angles = utils::get_tracked_angles(self.robot.shared_state()).await.unwrap_or([None; 6]);
+        self.robot.emit(RobotEvent::ScriptPaused(ScriptPaused {
+            line: parsed.line,
+            file: parsed.file.as_deref().map(str::to_string),
+            script_line: script_line(frames, parsed),
+            call_stack: call_stack(frames),
+            variables: self.variables.iter().map(|(name, value)| (name.clone(), *value)).collect(),
+            angles: utils::to_positive_angles(angles),
+        }));
+
+        self.stopped = !self.debugger.as_mut().unwrap().wait(depth).await;
+        match self.stopped {
+            true => Err(error_at(frames, parsed, "Script stopped".to_string())),
+            false => Ok(()),
+        }
+    }
+
+    fn repeat_count(&self, count: &Expression) -> Result<u32, String> {
+        let count = count.evaluate(&self.variables)?;
+
+        if count < 0.0 || count.fract() != 0.0 || count > u32::MAX as f32 {
+            return Err(format!("REPEAT count must be a whole number of at least 0, got {}", count));
+        }
+
+        Ok(count as u32)
+    }
+
+    // Runs a single statement, `None` for those that only change the interpreter
+    async fn execute(&mut self, statement: &Statement) -> Result<Option<String>, String> {
+        let response = match statement {
+            Statement::Instruction(instruction) => script::execute_instruction(instruction, &self.robot).await?,
+            Statement::Move(joints_angles) => {
+                let joints_angles = joints_angles
+                    .iter()
+                    .map(|(joint, angle)| Ok((*joint, angle.evaluate(&self.variables)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                script::execute_instruction(&Instruction::Move(joints_angles), &self.robot).await?
+            }
+            Statement::Wait(milliseconds) => {
+                let milliseconds = milliseconds.evaluate(&self.variables)?;
+                sleep(script::wait_duration(milliseconds)?).await;
+                format!("Waited {} ms", milliseconds)
+            }
+            Statement::Let(name, value) => {
+                let value = value.evaluate(&self.variables)?;
+                self.variables.insert(name.clone(), value);
+                return Ok(None);
+            }
+            // Blocks and calls are run by `run`
+            Statement::Repeat(..) | Statement::Include(..) | Statement::Call(..) => return Ok(None),
+            Statement::GotoPos(name) => {
+                let store = self
+                    .positions
+                    .as_ref()
+                    .ok_or_else(|| format!("GOTO_POS {}: no stored positions are available here", name))?;
+                positions::go_to_position(&self.robot, store, name).await?
+            }
+            Statement::Home => self.home().await?,
+        };
+
+        Ok(Some(response))
+    }
+
+    // Moves every calibrated joint to 0°, where calibration leaves it. Unlike `homing::home`,
+    // it neither calibrates nor goes to the configured home pose
+    async fn home(&self) -> Result<String, String> {
+        let joints_angles: Vec<(i8, f32)> = self
+            .robot
+            .angles()
+            .await?
+            .iter()
+            .enumerate()
+            .filter(|(_, angle)| angle.is_some())
+            .map(|(i, _)| (i as i8 + 1, 0.0))
+            .collect();
+
+        if joints_angles.is_empty() {
+            return Err("HOME moves calibrated joints to 0° and none is calibrated".to_string());
+        }
+
+        self.robot.move_joints(joints_angles).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    async fn simulated_robot() -> Robot {
+        let robot = Robot::new();
+        robot.connect(constants::SIMULATOR_PORT).await.unwrap();
+        robot
+    }
+
+    async fn positive_angles(robot: &Robot) -> [Option<f32>; 6] {
+        utils::to_positive_angles(robot.angles().await.unwrap())
+    }
+
+    fn assert_angle(angle: Option<f32>, expected: f32) {
+        let angle = angle.expect("joint is not calibrated");
+        assert!((angle - expected).abs() < 1.0, "{}° is not {}°", angle, expected);
+    }
+
+    #[tokio::test]
+    async fn runs_a_script_on_the_simulator() {
+        let robot = simulated_robot().await;
+        let script = script::parse_script(
+            "CALIBRATE>J1;J2;\n\
+             LET base = 30\n\
+             REPEAT 2 {\n\
+               LET base = base + 5\n\
+             }\n\
+             DEF lift(angle)\n\
+               MOVE>J2_angle;\n\
+             END\n\
+             MOVE>J1_base;\n\
+             CALL lift(base * 2)\n\
+             WAIT 1",
+        )
+        .unwrap();
+
+        let mut interpreter = Interpreter::new(robot.clone(), None);
+        let mut lines = Vec::new();
+        interpreter.run(&script, |line, _| lines.push(line)).await.unwrap();
+
+        assert_eq!(interpreter.variables().get("base"), Some(&40.0));
+        // Lines of a subroutine are reported as themselves
+        assert_eq!(lines, vec![1, 9, 7, 11]);
+
+        let angles = positive_angles(&robot).await;
+        assert_angle(angles[0], 40.0);
+        assert_angle(angles[1], 80.0);
+        assert_eq!(angles[2], None);
+    }
+
+    #[tokio::test]
+    async fn home_moves_calibrated_joints_to_zero() {
+        let robot = simulated_robot().await;
+        let script = script::parse_script("CALIBRATE>J1;J3;\nMOVE>J1_90;J3_45;\nHOME").unwrap();
+
+        Interpreter::new(robot.clone(), None).run(&script, |_, _| {}).await.unwrap();
+
+        let angles = positive_angles(&robot).await;
+        assert_angle(angles[0], 0.0);
+        assert_angle(angles[2], 0.0);
+        assert_eq!(angles[1], None);
+    }
+
+    #[tokio::test]
+    async fn errors_stop_the_script_and_name_the_line() {
+        let robot = simulated_robot().await;
+        let script = script::parse_script("CALIBRATE>J1;\nDEF f(d)\n  MOVE>J1_10 / d;\nEND\nCALL f(0)\nMOVE>J1_20;").unwrap();
+
+        let mut interpreter = Interpreter::new(robot.clone(), None);
+        let mut lines = Vec::new();
+        let error = interpreter.run(&script, |line, _| lines.push(line)).await.unwrap_err();
+
+        assert_eq!(error, "Line 3: Division by zero\n    in f, called from Line 5");
+        assert_eq!(lines, vec![1]);
+        assert_eq!(interpreter.progress().map(|progress| progress.script_line), Some(3));
+        assert!(!interpreter.was_stopped());
+    }
+
+    #[tokio::test]
+    async fn invalid_waits_are_errors() {
+        let robot = simulated_robot().await;
+
+        for content in ["WAIT -5", "LET big = 1000000000000000000000000000000\nWAIT big"] {
+            let script = script::parse_script(content).unwrap();
+            let error = Interpreter::new(robot.clone(), None).run(&script, |_, _| {}).await.unwrap_err();
+            assert!(error.contains("WAIT needs a finite, positive duration"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+// Continuous jogging: the arm keeps moving until the jog is stopped, reaches the end of its range,
+// or the heartbeats keeping it alive stop arriving, so a frozen frontend never leaves it running
+use crate::calibration;
+use crate::cartesian::{self, CartesianAxis, JogFrame};
+use crate::constants::{self, SteppersAngles};
+use crate::events::RobotEvent;
+use crate::robot::Robot;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+// A jog is stopped when no heartbeat arrives for this long
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(500);
+// How often a running jog checks for stop requests and heartbeats
+const WATCHDOG_PERIOD: Duration = Duration::from_millis(50);
+// How often a joint jog reads the position back, to report it and notice the end of the range
+const POSITION_POLL_PERIOD: Duration = Duration::from_millis(250);
+// Cartesian jogs are streamed as short straight moves lasting this long, in seconds
+const CARTESIAN_CHUNK_DURATION: f32 = 0.25;
+
+// What a jog moves: a joint, or the tool along or around a Cartesian axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JogTarget {
+    Joint(i8),
+    Axis(CartesianAxis),
+}
+
+impl FromStr for JogTarget {
+    type Err = String;
+
+    // Either a joint (J1 to J6) or an axis (x, y, z, roll, pitch, yaw)
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        if let Some(joint) = target.strip_prefix(['J', 'j']).and_then(|joint| joint.parse::<i8>().ok()) {
+            return Ok(JogTarget::Joint(joint));
+        }
+
+        target
+            .parse::<CartesianAxis>()
+            .map(JogTarget::Axis)
+            .map_err(|_| format!("Invalid jog target '{}', expected J1 to J6 or an axis", target))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JogStopReason {
+    // `stop_jog` was called
+    Released,
+    // No heartbeat arrived within `HEARTBEAT_TIMEOUT`
+    HeartbeatLost,
+    // The joint reached its soft limit or the tool can not go further along the axis
+    RangeEnd,
+    // The connection failed while jogging
+    Error,
+}
+
+// Reported once a jog is over and the arm is at rest
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JogReport {
+    pub target: JogTarget,
+    pub reason: JogStopReason,
+    pub message: Option<String>,
+}
+
+struct JogSession {
+    last_heartbeat: Instant,
+    stop_requested: bool,
+    task: Option<JoinHandle<()>>,
+}
+
+// The jog currently running, at most one at a time
+#[derive(Clone, Default)]
+pub struct JogController {
+    session: Arc<Mutex<Option<JogSession>>>,
+}
+
+impl JogController {
+    pub fn is_jogging(&self) -> bool {
+        self.session.lock().unwrap().is_some()
+    }
+
+    // Keeps the running jog alive, returns whether there is one
+    pub fn heartbeat(&self) -> bool {
+        match self.session.lock().unwrap().as_mut() {
+            Some(session) => {
+                session.last_heartbeat = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Asks the running jog to stop without waiting for it
+    pub fn request_stop(&self) {
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.stop_requested = true;
+        }
+    }
+
+    // Asks the running jog to stop and waits until the arm is at rest
+    pub async fn stop(&self) {
+        let task = match self.session.lock().unwrap().as_mut() {
+            Some(session) => {
+                session.stop_requested = true;
+                session.task.take()
+            }
+            None => None,
+        };
+
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+
+    // Reserves the controller and spawns the jog. The task is registered with the session, so a
+    // `stop` at any point after this finds it and waits for it
+    fn begin(&self, jog: impl Future<Output = ()> + Send + 'static) -> Result<(), String> {
+        let mut session = self.session.lock().unwrap();
+
+        if session.is_some() {
+            return Err("A jog is already running, stop it first".to_string());
+        }
+
+        *session = Some(JogSession {
+            last_heartbeat: Instant::now(),
+            stop_requested: false,
+            task: Some(tokio::spawn(jog)),
+        });
+
+        Ok(())
+    }
+
+    fn end(&self) {
+        *self.session.lock().unwrap() = None;
+    }
+
+    // Why the running jog has to stop, if it has to
+    fn stop_reason(&self) -> Option<JogStopReason> {
+        let session = self.session.lock().unwrap();
+        let session = session.as_ref()?;
+
+        if session.stop_requested {
+            Some(JogStopReason::Released)
+        } else if session.last_heartbeat.elapsed() > HEARTBEAT_TIMEOUT {
+            Some(JogStopReason::HeartbeatLost)
+        } else {
+            None
+        }
+    }
+}
+
+// Starts jogging `target` in `direction` (1 or -1) at `speed` degrees/s for joints and rotations,
+// mm/s for translations. Returns once the arm is moving, the jog then runs on a background task
+pub async fn start(
+    robot: &Robot,
+    target: JogTarget,
+    direction: i8,
+    speed: f32,
+    frame: JogFrame,
+) -> Result<(), String> {
+    if direction != 1 && direction != -1 {
+        return Err("Direction must be 1 or -1".to_string());
+    }
+    if speed <= 0.0 {
+        return Err("Speed must be greater than 0".to_string());
+    }
+
+    // Jogs follow the speed override like every other motion
+    let speed = speed * robot.shared_state().read().await.speed_factor();
+
+    let (started_sender, started) = oneshot::channel();
+    robot
+        .jog_controller()
+        .begin(run(robot.clone(), target, direction, speed, frame, started_sender))?;
+
+    started
+        .await
+        .unwrap_or_else(|_| Err("The jog ended before starting".to_string()))
+        .map_err(|e| format!("Jog rejected. {}", e))
+}
+
+// Starts the jog, reports whether it started through `started`, then runs it until it has to stop
+async fn run(
+    robot: Robot,
+    target: JogTarget,
+    direction: i8,
+    speed: f32,
+    frame: JogFrame,
+    started: oneshot::Sender<Result<(), String>>,
+) {
+    match target {
+        JogTarget::Joint(joint) => match start_joint(&robot, joint, direction, speed).await {
+            Ok(target_steps) => {
+                let _ = started.send(Ok(()));
+                run_joint(robot, joint, target_steps).await;
+            }
+            Err(e) => reject(&robot, started, e),
+        },
+        JogTarget::Axis(axis) => match check_axis(&robot, axis, direction, speed, frame).await {
+            Ok(()) => {
+                let _ = started.send(Ok(()));
+                run_axis(robot, axis, direction, speed, frame).await;
+            }
+            Err(e) => reject(&robot, started, e),
+        },
+    }
+}
+
+fn reject(robot: &Robot, started: oneshot::Sender<Result<(), String>>, error: String) {
+    robot.jog_controller().end();
+    let _ = started.send(Err(error));
+}
+
+// Sends the joint towards the end of its range at `speed`, returns the absolute steps it heads to
+async fn start_joint(robot: &Robot, joint: i8, direction: i8, speed: f32) -> Result<i32, String> {
+    if !(1..=6).contains(&joint) {
+        return Err(format!("Invalid Joint: {}", joint));
+    }
+    // Stopped while starting, nothing has moved yet
+    if robot.jog_controller().stop_reason().is_some() {
+        return Err("The jog was stopped before it started".to_string());
+    }
+
+    let state = robot.shared_state();
+    let current_angles = utils::to_positive_angles(utils::get_tracked_angles(state.clone()).await?);
+    let current_angle = current_angles[(joint - 1) as usize]
+        .ok_or_else(|| format!("Current angle for J{} is unknown", joint))?;
+
+    let zero_offsets = state.read().await.zero_offsets;
+    let (min_angle, max_angle) = calibration::joint_range(joint, &zero_offsets)?;
+    let target_angle = if direction > 0 { max_angle } else { min_angle };
+
+    let current_steps = utils::angle_to_steps(joint, current_angle, &zero_offsets)?.round() as i32;
+    // Truncated so half steps never carry the joint past its range
+    let target_steps = utils::angle_to_steps(joint, target_angle, &zero_offsets)?.trunc() as i32;
+
+    if current_steps == target_steps {
+        return Err(format!("J{} is already at {:.1}°, the end of its range", joint, target_angle));
+    }
+
+    let steps_per_degree =
+        constants::get_steps_per_degree(joint as u8).ok_or_else(|| format!("Invalid Joint: {}", joint))?;
+    let max_velocity = constants::get_max_stepper_velocity(joint as u8).unwrap_or(0.0);
+    if speed * steps_per_degree > max_velocity {
+        return Err(format!(
+            "J{} can jog at up to {:.1}°/s",
+            joint,
+            max_velocity / steps_per_degree
+        ));
+    }
+
+    utils::send_and_receive_from_shared_state(
+        &format!("{}J{}_{:.2};", constants::CommandCodes::SETVEL, joint, speed * steps_per_degree),
+        state.clone(),
+        None,
+    )
+    .await?;
+
+    // The position model does not follow the joint until the jog is over
+    state.write().await.positions.set_free_running(true);
+
+    let started = utils::send_and_receive_from_shared_state(
+        &format!("{}J{}_{};", constants::CommandCodes::JOG, joint, target_steps - current_steps),
+        state.clone(),
+        None,
+    )
+    .await
+    .and_then(|response| match response.contains("JOGGING_STEPS") {
+        true => Ok(()),
+        false => Err(format!(
+            "The controller does not support continuous jogs, update its firmware. Response: {}",
+            response.trim()
+        )),
+    });
+
+    if let Err(e) = started {
+        state.write().await.positions.set_free_running(false);
+        restore_parameters(robot).await?;
+        return Err(e);
+    }
+
+    Ok(target_steps)
+}
+
+// Watches a joint jog until it has to stop, then waits for the joint to come to rest
+async fn run_joint(robot: Robot, joint: i8, target_steps: i32) {
+    let controller = robot.jog_controller();
+    let state = robot.shared_state();
+    let index = (joint - 1) as usize;
+
+    let mut last_poll = Instant::now();
+    let mut last_steps = None;
+
+    let (reason, mut message) = loop {
+        sleep(WATCHDOG_PERIOD).await;
+
+        if let Some(reason) = controller.stop_reason() {
+            let stopped = utils::send_and_receive_from_shared_state(constants::CommandCodes::STOP, state.clone(), None).await;
+            break (reason, stopped.err());
+        }
+
+        if last_poll.elapsed() < POSITION_POLL_PERIOD {
+            continue;
+        }
+        last_poll = Instant::now();
+
+        let all_steps = match utils::get_steppers_steps(state.clone()).await {
+            Ok(all_steps) => all_steps,
+            Err(e) => break (JogStopReason::Error, Some(e)),
+        };
+        let zero_offsets = state.read().await.zero_offsets;
+        robot.emit(RobotEvent::SteppersAngles(SteppersAngles::from(utils::steps_to_angles(all_steps, &zero_offsets))));
+
+        // The joint stopped by itself: it reached the target or hit the limit switch
+        let steps = all_steps[index];
+        if steps.map(|steps| steps.round() as i32) == Some(target_steps) || (steps.is_some() && steps == last_steps) {
+            break (JogStopReason::RangeEnd, None);
+        }
+        last_steps = steps;
+    };
+
+    // Raising the velocity back while the joint decelerates would speed it up again
+    if let Err(e) = wait_until_at_rest(&robot, index).await.and(restore_parameters(&robot).await) {
+        message.get_or_insert(e);
+    }
+    state.write().await.positions.set_free_running(false);
+
+    finish(&robot, JogTarget::Joint(joint), reason, message).await;
+}
+
+async fn wait_until_at_rest(robot: &Robot, index: usize) -> Result<(), String> {
+    let mut last_steps = None;
+
+    loop {
+        let steps = utils::get_steppers_steps(robot.shared_state()).await?[index];
+
+        if steps.is_none() || steps == last_steps {
+            return Ok(());
+        }
+
+        last_steps = steps;
+        sleep(WATCHDOG_PERIOD * 2).await;
+    }
+}
+
+async fn restore_parameters(robot: &Robot) -> Result<(), String> {
+    let state = robot.shared_state();
+    let parameters = state.read().await.motion_parameters();
+
+    utils::apply_parameters(&parameters, state, None)
+        .await
+        .map_err(|e| format!("Error restoring parameters: {}", e))
+}
+
+// Distance or angle covered by one chunk of a Cartesian jog
+fn chunk_increment(axis: CartesianAxis, direction: i8, speed: f32) -> Result<f32, String> {
+    let (max_speed, unit) = match axis {
+        CartesianAxis::X | CartesianAxis::Y | CartesianAxis::Z => (constants::MAX_TOOL_SPEED, "mm/s"),
+        _ => (constants::MAX_ROTATION_SPEED, "°/s"),
+    };
+
+    if speed > max_speed {
+        return Err(format!("Jogs along {:?} can go up to {} {}", axis, max_speed, unit));
+    }
+
+    Ok(direction as f32 * speed * CARTESIAN_CHUNK_DURATION)
+}
+
+// Checks the first chunk of a Cartesian jog can be made
+async fn check_axis(robot: &Robot, axis: CartesianAxis, direction: i8, speed: f32, frame: JogFrame) -> Result<(), String> {
+    let increment = chunk_increment(axis, direction, speed)?;
+    let start_angles = utils::get_joint_positions(robot.shared_state()).await?;
+    let zero_offsets = robot.zero_offsets().await;
+
+    cartesian::jog_path(&start_angles, axis, frame, increment, &zero_offsets).map(|_| ())
+}
+
+// Streams chunks of a Cartesian jog until it has to stop. The firmware runs each chunk before
+// reading the next one, so at most one chunk is left to run once a stop is noticed
+async fn run_axis(robot: Robot, axis: CartesianAxis, direction: i8, speed: f32, frame: JogFrame) {
+    let controller = robot.jog_controller();
+    let state = robot.shared_state();
+
+    let (reason, message) = loop {
+        if let Some(reason) = controller.stop_reason() {
+            break (reason, None);
+        }
+
+        // Never streams faster than real time, even if the controller runs a chunk sooner
+        let chunk_started = Instant::now();
+
+        let chunk = async {
+            let increment = chunk_increment(axis, direction, speed).map_err(|e| (JogStopReason::Error, e))?;
+            let start_angles = utils::get_joint_positions(state.clone())
+                .await
+                .map_err(|e| (JogStopReason::Error, e))?;
+            let zero_offsets = state.read().await.zero_offsets;
+
+            cartesian::jog_path(&start_angles, axis, frame, increment, &zero_offsets).map_err(|e| (JogStopReason::RangeEnd, e))
+        };
+
+        let path = match chunk.await {
+            Ok(path) => path,
+            Err((reason, e)) => break (reason, Some(e)),
+        };
+
+        let segment_duration = CARTESIAN_CHUNK_DURATION / path.waypoints.len() as f32;
+        if let Err(e) = utils::execute_joint_path(&path.waypoints, segment_duration, state.clone()).await {
+            break (JogStopReason::Error, Some(e));
+        }
+
+        let chunk_duration = Duration::from_secs_f32(CARTESIAN_CHUNK_DURATION);
+        sleep(chunk_duration.saturating_sub(chunk_started.elapsed())).await;
+    };
+
+    finish(&robot, JogTarget::Axis(axis), reason, message).await;
+}
+
+async fn finish(robot: &Robot, target: JogTarget, reason: JogStopReason, message: Option<String>) {
+    if let Some(message) = &message {
+        eprintln!("###DEBUG### - Jog of {:?} stopped ({:?}): {}", target, reason, message);
+    }
+
+    let _ = robot.angles().await;
+
+    robot.jog_controller().end();
+    robot.emit(RobotEvent::JogStopped(JogReport { target, reason, message }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn jogging_robot() -> Robot {
+        let robot = Robot::new();
+        robot.connect(constants::SIMULATOR_PORT).await.unwrap();
+        robot.calibrate(&[1]).await.unwrap();
+        robot.start_jog(JogTarget::Joint(1), 1, 10.0, JogFrame::Base).await.unwrap();
+        robot
+    }
+
+    #[tokio::test]
+    async fn motion_is_refused_while_jogging() {
+        let robot = jogging_robot().await;
+
+        assert!(robot.is_jogging());
+        assert!(robot.move_joints(vec![(1, 10.0)]).await.is_err());
+        assert!(robot.jog_joint(1, 5.0).await.is_err());
+        assert!(robot.jog(1, 100).await.is_err());
+        assert!(robot.calibrate(&[1]).await.is_err());
+
+        robot.stop_jog().await;
+
+        assert!(!robot.is_jogging());
+        robot.move_joints(vec![(1, 10.0)]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_stop_while_starting_cancels_the_jog() {
+        let robot = Robot::new();
+        robot.connect(constants::SIMULATOR_PORT).await.unwrap();
+        robot.calibrate(&[1]).await.unwrap();
+
+        let (started, _) = tokio::join!(
+            robot.start_jog(JogTarget::Joint(1), 1, 10.0, JogFrame::Base),
+            robot.stop_jog()
+        );
+
+        assert_eq!(started.unwrap_err(), "Jog rejected. The jog was stopped before it started");
+        assert!(!robot.is_jogging());
+        assert_eq!(utils::to_positive_angles(robot.angles().await.unwrap())[0], Some(0.0));
+    }
+}
@@ -0,0 +1,483 @@
+// Forward and inverse kinematics of the arm, built on `DH_PARAMETERS`. Joint angles are the positive
+// angles taken by `drive_steppers_to_angles`, in degrees. Lengths are in mm
+use crate::calibration;
+use crate::constants::{self, DhParameters};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+pub type Vector3 = [f64; 3];
+pub type Matrix3 = [[f64; 3]; 3];
+
+// Iterations allowed to the inverse kinematics solver before the pose is considered out of reach
+const IK_MAX_ITERATIONS: usize = 200;
+// Position (mm) and orientation (rad) errors the solver stops at
+const IK_POSITION_TOLERANCE: f64 = 0.01;
+const IK_ORIENTATION_TOLERANCE: f64 = 1e-4;
+// Converts orientation errors (rad) into mm so both weigh about the same in the solver
+const ORIENTATION_WEIGHT: f64 = 100.0;
+// Damping of the least squares solver, keeps steps bounded close to singularities
+const IK_DAMPING: f64 = 1.0;
+// Largest change of a joint in a single solver iteration, in rad
+const IK_MAX_STEP: f64 = 0.2;
+// Length used to make the Jacobian dimensionless when measuring the distance to a singularity
+const CHARACTERISTIC_LENGTH: f64 = 300.0;
+// Poses whose normalized manipulability falls below this are treated as singular
+const SINGULARITY_THRESHOLD: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CartesianPose {
+    // Tool flange position in the base frame, in mm
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    // Orientation in degrees, rotations around the base X, Y and Z axes applied in that order
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+// Point in the base frame, in mm
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CartesianPosition {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl CartesianPosition {
+    pub fn to_vector(&self) -> Vector3 {
+        [self.x as f64, self.y as f64, self.z as f64]
+    }
+}
+
+// Rigid transform, maps coordinates of a frame into its parent frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub rotation: Matrix3,
+    pub translation: Vector3,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        translation: [0.0; 3],
+    };
+
+    pub fn from_pose(pose: &CartesianPose) -> Self {
+        let (sr, cr) = (pose.roll as f64).to_radians().sin_cos();
+        let (sp, cp) = (pose.pitch as f64).to_radians().sin_cos();
+        let (sy, cy) = (pose.yaw as f64).to_radians().sin_cos();
+
+        // Rz(yaw) * Ry(pitch) * Rx(roll)
+        Self {
+            rotation: [
+                [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+                [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+                [-sp, cp * sr, cp * cr],
+            ],
+            translation: [pose.x as f64, pose.y as f64, pose.z as f64],
+        }
+    }
+
+    pub fn to_pose(&self) -> CartesianPose {
+        let r = &self.rotation;
+        let pitch = (-r[2][0]).clamp(-1.0, 1.0).asin();
+
+        // With the pitch at ±90° roll and yaw turn around the same axis, the whole rotation goes to yaw
+        let (roll, yaw) = if pitch.cos() > 1e-6 {
+            (r[2][1].atan2(r[2][2]), r[1][0].atan2(r[0][0]))
+        } else {
+            (0.0, (-r[0][1]).atan2(r[1][1]))
+        };
+
+        CartesianPose {
+            x: self.translation[0] as f32,
+            y: self.translation[1] as f32,
+            z: self.translation[2] as f32,
+            roll: roll.to_degrees() as f32,
+            pitch: pitch.to_degrees() as f32,
+            yaw: yaw.to_degrees() as f32,
+        }
+    }
+
+    // Applies `other` in the frame of `self`
+    pub fn compose(&self, other: &Transform) -> Transform {
+        Transform {
+            rotation: mat_mul(&self.rotation, &other.rotation),
+            translation: add(&mat_vec(&self.rotation, &other.translation), &self.translation),
+        }
+    }
+
+    // Transform of a DH link for the joint variable `theta`, in rad
+    fn from_dh(dh: &DhParameters, theta: f64) -> Self {
+        let (st, ct) = theta.sin_cos();
+        let (sa, ca) = (dh.alpha as f64).to_radians().sin_cos();
+        let a = dh.a as f64;
+
+        Transform {
+            rotation: [[ct, -st * ca, st * sa], [st, ct * ca, -ct * sa], [0.0, sa, ca]],
+            translation: [a * ct, a * st, dh.d as f64],
+        }
+    }
+}
+
+pub fn add(a: &Vector3, b: &Vector3) -> Vector3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: &Vector3, b: &Vector3) -> Vector3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: &Vector3, factor: f64) -> Vector3 {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+pub fn dot(a: &Vector3, b: &Vector3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: &Vector3, b: &Vector3) -> Vector3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn norm(a: &Vector3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub fn mat_mul(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+pub fn mat_vec(a: &Matrix3, v: &Vector3) -> Vector3 {
+    [dot(&a[0], v), dot(&a[1], v), dot(&a[2], v)]
+}
+
+pub fn transpose(a: &Matrix3) -> Matrix3 {
+    [
+        [a[0][0], a[1][0], a[2][0]],
+        [a[0][1], a[1][1], a[2][1]],
+        [a[0][2], a[1][2], a[2][2]],
+    ]
+}
+
+// Rotation of `angle` rad around the unit vector `axis` (Rodrigues' formula)
+pub fn axis_angle_rotation(axis: &Vector3, angle: f64) -> Matrix3 {
+    let (s, c) = angle.sin_cos();
+    let t = 1.0 - c;
+    let [x, y, z] = *axis;
+
+    [
+        [t * x * x + c, t * x * y - s * z, t * x * z + s * y],
+        [t * x * y + s * z, t * y * y + c, t * y * z - s * x],
+        [t * x * z - s * y, t * y * z + s * x, t * z * z + c],
+    ]
+}
+
+// Axis scaled by the angle (rad) of a rotation matrix, the inverse of `axis_angle_rotation`
+pub fn rotation_vector(r: &Matrix3) -> Vector3 {
+    let angle = ((r[0][0] + r[1][1] + r[2][2] - 1.0) / 2.0).clamp(-1.0, 1.0).acos();
+    let skew = [r[2][1] - r[1][2], r[0][2] - r[2][0], r[1][0] - r[0][1]];
+
+    if angle < 1e-6 {
+        return scale(&skew, 0.5);
+    }
+
+    if PI - angle < 1e-4 {
+        // The skew part vanishes at 180°, where R = 2·n·nᵀ - I gives the axis from the diagonal
+        let mut axis = [0.0; 3];
+        for (i, value) in axis.iter_mut().enumerate() {
+            *value = ((r[i][i] + 1.0) / 2.0).max(0.0).sqrt();
+        }
+
+        let largest = (0..3).max_by(|&a, &b| axis[a].total_cmp(&axis[b])).unwrap_or(0);
+        for i in (0..3).filter(|&i| i != largest) {
+            axis[i] = axis[i].copysign(r[largest][i] + r[i][largest]);
+        }
+
+        return scale(&axis, angle / norm(&axis));
+    }
+
+    scale(&skew, angle / (2.0 * angle.sin()))
+}
+
+// Frame of every link in the base frame for the joint angles `q`, in rad. Index 0 is the base
+fn link_frames(q: &[f64; 6]) -> [Transform; 7] {
+    let mut frames = [Transform::IDENTITY; 7];
+
+    for (i, dh) in constants::DH_PARAMETERS.iter().enumerate() {
+        let theta = q[i] + (dh.theta_offset as f64).to_radians();
+        frames[i + 1] = frames[i].compose(&Transform::from_dh(dh, theta));
+    }
+
+    frames
+}
+
+// Geometric Jacobian for the joint angles `q`, in rad. Rows are the linear velocity (mm/rad) and the
+// angular velocity scaled by `ORIENTATION_WEIGHT`
+fn jacobian(q: &[f64; 6]) -> [[f64; 6]; 6] {
+    let frames = link_frames(q);
+    let tool_position = frames[6].translation;
+    let mut jacobian = [[0.0; 6]; 6];
+
+    for joint in 0..6 {
+        let frame = &frames[joint];
+        let axis = [frame.rotation[0][2], frame.rotation[1][2], frame.rotation[2][2]];
+        let linear = cross(&axis, &sub(&tool_position, &frame.translation));
+
+        for row in 0..3 {
+            jacobian[row][joint] = linear[row];
+            jacobian[row + 3][joint] = axis[row] * ORIENTATION_WEIGHT;
+        }
+    }
+
+    jacobian
+}
+
+// Solves `a·x = b` by Gaussian elimination with partial pivoting, `None` if `a` is singular
+fn solve(mut a: [[f64; 6]; 6], mut b: [f64; 6]) -> Option<[f64; 6]> {
+    for column in 0..6 {
+        let pivot = (column..6).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+        if a[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        let pivot_row = a[column];
+        for row in column + 1..6 {
+            let factor = a[row][column] / pivot_row[column];
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = [0.0; 6];
+    for row in (0..6).rev() {
+        let known: f64 = (row + 1..6).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - known) / a[row][row];
+    }
+
+    Some(x)
+}
+
+fn determinant(mut a: [[f64; 6]; 6]) -> f64 {
+    let mut determinant = 1.0;
+
+    for column in 0..6 {
+        let pivot = (column..6)
+            .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+            .unwrap_or(column);
+        if a[pivot][column] == 0.0 {
+            return 0.0;
+        }
+        if pivot != column {
+            a.swap(column, pivot);
+            determinant = -determinant;
+        }
+        determinant *= a[column][column];
+
+        let pivot_row = a[column];
+        for row in a.iter_mut().skip(column + 1) {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    determinant
+}
+
+fn to_radians(angles: &[f32; 6]) -> [f64; 6] {
+    angles.map(|angle| (angle as f64).to_radians())
+}
+
+// Pose of the tool flange for the given joint angles
+pub fn forward_kinematics(angles: &[f32; 6]) -> Transform {
+    link_frames(&to_radians(angles))[6]
+}
+
+// Position (mm) and orientation (rad) distance between two transforms
+pub fn transform_distance(a: &Transform, b: &Transform) -> (f64, f64) {
+    let position = norm(&sub(&a.translation, &b.translation));
+    let orientation = norm(&rotation_vector(&mat_mul(&a.rotation, &transpose(&b.rotation))));
+
+    (position, orientation)
+}
+
+// Joint angles placing the tool flange at `target`, searched from `seed` with damped least squares.
+// Returns the solution closest to `seed`, which keeps consecutive waypoints of a path continuous
+pub fn inverse_kinematics(target: &Transform, seed: &[f32; 6]) -> Result<[f32; 6], String> {
+    let mut q = to_radians(seed);
+
+    for _ in 0..IK_MAX_ITERATIONS {
+        let current = link_frames(&q)[6];
+        let position_error = sub(&target.translation, &current.translation);
+        let orientation_error = rotation_vector(&mat_mul(&target.rotation, &transpose(&current.rotation)));
+
+        if norm(&position_error) < IK_POSITION_TOLERANCE && norm(&orientation_error) < IK_ORIENTATION_TOLERANCE {
+            return Ok(q.map(|angle| angle.to_degrees() as f32));
+        }
+
+        let error = [
+            position_error[0],
+            position_error[1],
+            position_error[2],
+            orientation_error[0] * ORIENTATION_WEIGHT,
+            orientation_error[1] * ORIENTATION_WEIGHT,
+            orientation_error[2] * ORIENTATION_WEIGHT,
+        ];
+        let jacobian = jacobian(&q);
+
+        // Δq = Jᵀ·(J·Jᵀ + λ²·I)⁻¹·e
+        let mut damped = [[0.0; 6]; 6];
+        for i in 0..6 {
+            for k in 0..6 {
+                damped[i][k] = (0..6).map(|m| jacobian[i][m] * jacobian[k][m]).sum();
+            }
+            damped[i][i] += IK_DAMPING * IK_DAMPING;
+        }
+        let y = solve(damped, error).ok_or("Inverse kinematics failed to converge")?;
+
+        let mut step = [0.0; 6];
+        for (m, value) in step.iter_mut().enumerate() {
+            *value = (0..6).map(|i| jacobian[i][m] * y[i]).sum();
+        }
+
+        let largest = step.iter().fold(0.0_f64, |largest, value| largest.max(value.abs()));
+        let factor = if largest > IK_MAX_STEP { IK_MAX_STEP / largest } else { 1.0 };
+        for (angle, value) in q.iter_mut().zip(step) {
+            *angle += value * factor;
+        }
+    }
+
+    let pose = target.to_pose();
+    Err(format!(
+        "Pose X{:.1} Y{:.1} Z{:.1} Roll{:.1} Pitch{:.1} Yaw{:.1} is out of reach",
+        pose.x, pose.y, pose.z, pose.roll, pose.pitch, pose.yaw
+    ))
+}
+
+// Dimensionless measure of how far the joint angles are from a singularity, 0 at a singularity
+pub fn manipulability(angles: &[f32; 6]) -> f64 {
+    let mut jacobian = jacobian(&to_radians(angles));
+
+    for (row, values) in jacobian.iter_mut().enumerate() {
+        let factor = if row < 3 { 1.0 / CHARACTERISTIC_LENGTH } else { 1.0 / ORIENTATION_WEIGHT };
+        for value in values.iter_mut() {
+            *value *= factor;
+        }
+    }
+
+    determinant(jacobian).abs()
+}
+
+// Angles are positive angles from the joint zeros set by `zero_offsets`
+pub fn check_joint_limits(angles: &[f32; 6], zero_offsets: &[f32; 6]) -> Result<(), String> {
+    for (i, angle) in angles.iter().enumerate() {
+        calibration::check_joint_angle((i + 1) as i8, *angle, zero_offsets)?;
+    }
+
+    Ok(())
+}
+
+pub fn check_singularity(angles: &[f32; 6]) -> Result<(), String> {
+    if manipulability(angles) < SINGULARITY_THRESHOLD {
+        return Err("Pose is too close to a singularity".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reachable poses inside the joint ranges and away from singularities
+    const POSES: [[f32; 6]; 4] = [
+        [90.0, 60.0, 40.0, 90.0, 22.0, 180.0],
+        [120.0, 45.0, 70.0, 100.0, 30.0, 150.0],
+        [60.0, 80.0, 20.0, 140.0, 10.0, 200.0],
+        [90.0, 10.0, 120.0, 90.0, 30.0, 180.0],
+    ];
+
+    #[test]
+    fn inverse_kinematics_inverts_forward_kinematics() {
+        for angles in POSES {
+            let target = forward_kinematics(&angles);
+            let seed = angles.map(|angle| angle + 3.0);
+
+            let solution = inverse_kinematics(&target, &seed).unwrap();
+            let (position, orientation) = transform_distance(&forward_kinematics(&solution), &target);
+
+            assert!(position < IK_POSITION_TOLERANCE, "{:?}: {} mm off", angles, position);
+            assert!(orientation < IK_ORIENTATION_TOLERANCE, "{:?}: {} rad off", angles, orientation);
+            for (solved, expected) in solution.iter().zip(angles) {
+                assert!((solved - expected).abs() < 0.1, "{:?} solved as {:?}", angles, solution);
+            }
+        }
+    }
+
+    #[test]
+    fn pose_converts_to_transform_and_back() {
+        let pose = forward_kinematics(&POSES[1]).to_pose();
+        let (position, orientation) = transform_distance(&Transform::from_pose(&pose), &forward_kinematics(&POSES[1]));
+
+        assert!(position < 1e-3);
+        assert!(orientation < 1e-5);
+    }
+
+    #[test]
+    fn unreachable_pose_is_rejected() {
+        let mut target = forward_kinematics(&POSES[0]);
+        target.translation = [2000.0, 0.0, 0.0];
+
+        assert!(inverse_kinematics(&target, &POSES[0]).unwrap_err().contains("out of reach"));
+    }
+
+    #[test]
+    fn singular_pose_is_rejected() {
+        assert!(check_singularity(&POSES[0]).is_ok());
+        assert_eq!(
+            check_singularity(&[90.0, 65.0, 120.0, 90.0, 0.0, 180.0]).unwrap_err(),
+            "Pose is too close to a singularity"
+        );
+    }
+
+    #[test]
+    fn angles_outside_the_joint_ranges_are_rejected() {
+        assert!(check_joint_limits(&POSES[0], &[0.0; 6]).is_ok());
+
+        // The solver does not know about the ranges, a pose reached with J5 past 45° is still solved
+        let angles = [90.0, 60.0, 40.0, 90.0, 50.0, 180.0];
+        let solution = inverse_kinematics(&forward_kinematics(&angles), &POSES[0]).unwrap();
+        assert!(check_joint_limits(&solution, &[0.0; 6]).unwrap_err().starts_with("J5 would reach"));
+
+        assert!(check_joint_limits(&[-1.0, 60.0, 40.0, 90.0, 22.0, 180.0], &[0.0; 6]).unwrap_err().starts_with("J1"));
+    }
+
+    #[test]
+    fn joint_ranges_follow_the_zero_offsets() {
+        // J5 travels 45° from its switch, which is 15° below its zero
+        let zero_offsets = [2.0, 0.0, 0.0, 0.0, 15.0, 0.0];
+        assert!(check_joint_limits(&[-1.0, 60.0, 40.0, 90.0, -15.0, 180.0], &zero_offsets).is_ok());
+        assert_eq!(
+            check_joint_limits(&[0.0, 60.0, 40.0, 90.0, 31.0, 180.0], &zero_offsets).unwrap_err(),
+            "J5 would reach 31.0°, outside its range of -15° to 30°"
+        );
+        assert!(check_joint_limits(&[-2.5, 60.0, 40.0, 90.0, 0.0, 180.0], &zero_offsets).is_err());
+    }
+}
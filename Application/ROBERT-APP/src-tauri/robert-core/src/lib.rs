@@ -0,0 +1,29 @@
+pub mod calibration;
+pub mod cartesian;
+pub mod constants;
+pub mod debugger;
+pub mod events;
+pub mod gamepad;
+pub mod gcode;
+pub mod history;
+pub mod homing;
+pub mod interpreter;
+pub mod jog;
+pub mod kinematics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod parameters;
+pub mod planner;
+pub mod poller;
+pub mod positions;
+pub mod robot;
+pub mod script;
+pub mod simulator;
+pub mod state;
+pub mod teach;
+pub mod tracking;
+pub mod utils;
+pub mod validation;
+
+pub use events::RobotEvent;
+pub use robot::Robot;
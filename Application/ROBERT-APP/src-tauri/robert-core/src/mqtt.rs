@@ -0,0 +1,308 @@
+// Bridge between the robot and an MQTT broker, for integration into lab automation buses.
+//
+// Topics, relative to `robert/<id>`:
+//   state  -> retained JSON snapshot with joint angles, enabled and calibration state
+//   status -> retained "online"/"offline" (last will)
+//   fault  -> JSON describing errors raised while polling or executing commands
+//   cmd    <- JSON commands, e.g. {"id": "1", "command": "move", "joints": {"1": 45.0}}
+//   ack    -> JSON acknowledgement for every command received on `cmd`
+use crate::cartesian::JogFrame;
+use crate::constants::SteppersAngles;
+use crate::history::{self, SharedRunHistory};
+use crate::interpreter::Interpreter;
+use crate::jog::JogTarget;
+use crate::robot::Robot;
+use crate::script;
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep, Duration};
+
+#[derive(Clone, Debug)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    // Identifies the arm in topic names
+    pub robot_id: String,
+    // Interval between state publications
+    pub state_interval: Duration,
+    // Where script runs are recorded, runs are not recorded without one
+    pub history: Option<SharedRunHistory>,
+}
+
+impl MqttConfig {
+    fn topic(&self, name: &str) -> String {
+        format!("robert/{}/{}", self.robot_id, name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum BridgeCommand {
+    // Keyed by joint, "1" or "J1"
+    Move { joints: BTreeMap<String, f32> },
+    Jog { joint: i8, steps: i16 },
+    JogJoint { joint: i8, degrees: f32 },
+    // Continuous jog, stopped when no heartbeat_jog arrives for jog::HEARTBEAT_TIMEOUT
+    StartJog { target: JogTarget, direction: i8, speed: f32, frame: Option<JogFrame> },
+    HeartbeatJog,
+    StopJog,
+    EmergencyStop,
+    // Percentage of the configured speed every motion runs at
+    SetSpeedOverride { percent: f32 },
+    Toggle { joint: i8, enabled: bool },
+    Calibrate { joints: Vec<i8> },
+    // Content of a .rob script, and where it is on the bridge host so it can INCLUDE the files next
+    // to it. `name` and `note` are recorded with the run
+    RunScript { script: String, path: Option<PathBuf>, name: Option<String>, note: Option<String> },
+}
+
+impl BridgeCommand {
+    // Commands that must not wait behind motion: a stop or e-stop queued behind a script would only
+    // run once the script is over, and a late heartbeat lets the watchdog end the jog
+    fn is_urgent(&self) -> bool {
+        matches!(self, BridgeCommand::EmergencyStop | BridgeCommand::StopJog | BridgeCommand::HeartbeatJog)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BridgeRequest {
+    // Echoed back in the acknowledgement so callers can match responses
+    id: Option<String>,
+    #[serde(flatten)]
+    command: BridgeCommand,
+}
+
+#[derive(Serialize)]
+struct RobotSnapshot {
+    connected: bool,
+    angles: Option<SteppersAngles>,
+    enabled: Option<[bool; 6]>,
+    calibrated: Option<[bool; 6]>,
+    speed_override: f32,
+}
+
+// Name runs are recorded under when the command gives neither a name nor a path
+const UNNAMED_SCRIPT: &str = "mqtt.rob";
+
+async fn execute(robot: &Robot, config: &MqttConfig, command: BridgeCommand) -> Result<String, String> {
+    match command {
+        BridgeCommand::Move { joints } => {
+            let joints_angles = joints
+                .into_iter()
+                .map(|(joint, angle)| {
+                    joint
+                        .trim_start_matches('J')
+                        .parse::<i8>()
+                        .map(|joint| (joint, angle))
+                        .map_err(|_| format!("Invalid joint '{}'", joint))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            robot.move_joints(joints_angles).await
+        }
+        BridgeCommand::Jog { joint, steps } => robot.jog(joint, steps).await,
+        BridgeCommand::JogJoint { joint, degrees } => robot
+            .jog_joint(joint, degrees)
+            .await
+            .map(|angle| format!("J{} at {:.2}°", joint, angle)),
+        BridgeCommand::StartJog { target, direction, speed, frame } => robot
+            .start_jog(target, direction, speed, frame.unwrap_or(JogFrame::Base))
+            .await
+            .map(|_| "Jog started".to_string()),
+        BridgeCommand::HeartbeatJog => match robot.heartbeat_jog() {
+            true => Ok("Jog running".to_string()),
+            false => Err("No jog is running".to_string()),
+        },
+        BridgeCommand::StopJog => {
+            robot.stop_jog().await;
+            Ok("Jog stopped".to_string())
+        }
+        BridgeCommand::EmergencyStop => robot.emergency_stop().await,
+        BridgeCommand::SetSpeedOverride { percent } => robot.set_speed_override(percent).await,
+        BridgeCommand::Toggle { joint, enabled } => {
+            robot.toggle(joint, if enabled { "ENABLED" } else { "DISABLED" }).await
+        }
+        BridgeCommand::Calibrate { joints } => robot.calibrate(&joints).await,
+        BridgeCommand::RunScript { script: content, path, name, note } => {
+            let script = match &path {
+                Some(path) => script::parse_script_at(&content, path)?,
+                None => script::parse_script(&content)?,
+            };
+            let mut interpreter = Interpreter::new(robot.clone(), None);
+            let mut responses = Vec::new();
+            let on_response = |_, response: String| responses.push(response.trim().to_string());
+
+            match &config.history {
+                Some(history) => {
+                    let name = name
+                        .or_else(|| path.and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned())))
+                        .unwrap_or_else(|| UNNAMED_SCRIPT.to_string());
+                    history::run_recorded(&mut interpreter, &script, &name, &content, note, history, on_response).await?
+                }
+                None => interpreter.run(&script, on_response).await?,
+            }
+            Ok(responses.join("\n"))
+        }
+    }
+}
+
+async fn snapshot(robot: &Robot) -> Result<RobotSnapshot, String> {
+    if !robot.is_connected().await {
+        return Ok(RobotSnapshot {
+            connected: false,
+            angles: None,
+            enabled: None,
+            calibrated: None,
+            speed_override: robot.speed_override().await,
+        });
+    }
+
+    let enabled = robot.steppers_state().await?;
+    let angles = robot.angles().await?;
+
+    Ok(RobotSnapshot {
+        connected: true,
+        angles: Some(SteppersAngles::from(angles)),
+        enabled: Some(enabled),
+        // Joints report an unknown position until they have been calibrated
+        calibrated: Some(angles.map(|angle| angle.is_some())),
+        speed_override: robot.speed_override().await,
+    })
+}
+
+async fn publish_json(client: &AsyncClient, topic: String, value: serde_json::Value, retain: bool) {
+    if let Err(e) = client
+        .publish(topic, QoS::AtLeastOnce, retain, value.to_string())
+        .await
+    {
+        eprintln!("###DEBUG### - Failed to publish MQTT message: {}", e);
+    }
+}
+
+// Executes a command and acknowledges it
+async fn respond(robot: &Robot, client: &AsyncClient, config: &MqttConfig, request: BridgeRequest) {
+    let ack = match execute(robot, config, request.command).await {
+        Ok(response) => json!({ "id": request.id, "ok": true, "response": response.trim() }),
+        Err(e) => {
+            publish_json(client, config.topic("fault"), json!({ "source": "command", "id": request.id, "error": e }), false).await;
+            json!({ "id": request.id, "ok": false, "error": e })
+        }
+    };
+
+    publish_json(client, config.topic("ack"), ack, false).await;
+}
+
+// Executes commands one at a time, in the order they were received. Urgent commands skip the queue
+async fn command_worker(
+    robot: Robot,
+    client: AsyncClient,
+    config: MqttConfig,
+    mut requests: mpsc::Receiver<BridgeRequest>,
+) {
+    while let Some(request) = requests.recv().await {
+        respond(&robot, &client, &config, request).await;
+    }
+}
+
+// Publishes a snapshot of the robot every `state_interval`, separate from the event loop so slow
+// serial queries never delay keep-alives
+async fn state_publisher(robot: Robot, client: AsyncClient, config: MqttConfig) {
+    let mut state_timer = interval(config.state_interval);
+
+    loop {
+        state_timer.tick().await;
+
+        match snapshot(&robot).await {
+            Ok(snapshot) => {
+                let value = serde_json::to_value(snapshot).unwrap_or_default();
+                publish_json(&client, config.topic("state"), value, true).await;
+            }
+            Err(e) => {
+                publish_json(&client, config.topic("fault"), json!({ "source": "state", "error": e }), false).await;
+            }
+        }
+    }
+}
+
+// Runs the bridge, reconnecting to the broker on transient errors
+pub async fn run_bridge(robot: Robot, config: MqttConfig) -> Result<(), String> {
+    let client_id = format!("robert-{}", config.robot_id);
+    let mut options = MqttOptions::new(client_id, config.host.clone(), config.port);
+    options.set_keep_alive(Duration::from_secs(5));
+    options.set_last_will(LastWill::new(config.topic("status"), "offline", QoS::AtLeastOnce, true));
+
+    let (client, mut eventloop) = AsyncClient::new(options, 16);
+
+    let (requests_sender, requests_receiver) = mpsc::channel(16);
+    tokio::spawn(command_worker(robot.clone(), client.clone(), config.clone(), requests_receiver));
+    tokio::spawn(state_publisher(robot.clone(), client.clone(), config.clone()));
+
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                // Subscriptions do not survive reconnections with a clean session. The event loop
+                // must keep polling, so requests are queued without waiting for room
+                client
+                    .try_subscribe(config.topic("cmd"), QoS::AtLeastOnce)
+                    .map_err(|e| format!("Failed to subscribe: {}", e))?;
+                client
+                    .try_publish(config.topic("status"), QoS::AtLeastOnce, true, "online")
+                    .map_err(|e| format!("Failed to publish status: {}", e))?;
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                let request = match serde_json::from_slice::<BridgeRequest>(&publish.payload) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        let ack = json!({ "id": null, "ok": false, "error": format!("Invalid command: {}", e) });
+                        let _ = client.try_publish(config.topic("ack"), QoS::AtLeastOnce, false, ack.to_string());
+                        continue;
+                    }
+                };
+
+                if request.command.is_urgent() {
+                    let (robot, client, config) = (robot.clone(), client.clone(), config.clone());
+                    tokio::spawn(async move { respond(&robot, &client, &config, request).await });
+                    continue;
+                }
+
+                match requests_sender.try_send(request) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(request)) => {
+                        let ack = json!({ "id": request.id, "ok": false, "error": "Command queue is full" });
+                        let _ = client.try_publish(config.topic("ack"), QoS::AtLeastOnce, false, ack.to_string());
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => {
+                        return Err("Command worker stopped".to_string());
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("###DEBUG### - MQTT connection error: {}, retrying", e);
+                sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(json: &str) -> BridgeCommand {
+        serde_json::from_str::<BridgeRequest>(json).unwrap().command
+    }
+
+    #[test]
+    fn stops_and_heartbeats_skip_the_queue() {
+        for urgent in ["emergency_stop", "stop_jog", "heartbeat_jog"] {
+            assert!(command(&format!(r#"{{"id": "1", "command": "{}"}}"#, urgent)).is_urgent(), "{}", urgent);
+        }
+
+        assert!(!command(r#"{"command": "move", "joints": {"J1": 10}}"#).is_urgent());
+        assert!(!command(r#"{"command": "run_script", "script": "HOME"}"#).is_urgent());
+    }
+}
@@ -0,0 +1,227 @@
+// Velocity and acceleration of the joints in degrees/s and degrees/s². A global value applies to
+// every joint without an override of its own. The firmware works in motor steps, so values are
+// converted through the reduction tables and checked against `MAX_STEPPER_VELOCITIES` and
+// `MAX_STEPPER_ACCELERATIONS` before being accepted
+use crate::constants;
+use serde::{Deserialize, Serialize};
+
+// Parameters may reach a limit computed back from steps, allow for the rounding
+const LIMIT_TOLERANCE: f32 = 1e-3;
+
+// Range of the speed override, in percent
+pub const MIN_SPEED_OVERRIDE: f32 = 1.0;
+pub const MAX_SPEED_OVERRIDE: f32 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Parameter {
+    Velocity,
+    Acceleration,
+}
+
+impl Parameter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Parameter::Velocity => "Velocity",
+            Parameter::Acceleration => "Acceleration",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Parameter::Velocity => "°/s",
+            Parameter::Acceleration => "°/s²",
+        }
+    }
+}
+
+// Values set on a single joint, `None` follows the global value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JointOverride {
+    pub velocity: Option<f32>,
+    pub acceleration: Option<f32>,
+}
+
+// Highest velocity and acceleration a joint accepts, in degrees/s and degrees/s²
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JointLimits {
+    pub joint: i8,
+    pub velocity: f32,
+    pub acceleration: f32,
+}
+
+// Limits of every joint, from the stepper limits
+pub fn joint_limits() -> Vec<JointLimits> {
+    (1..=6)
+        .filter_map(|joint| {
+            let steps_per_degree = constants::get_steps_per_degree(joint as u8)?;
+
+            Some(JointLimits {
+                joint,
+                velocity: constants::get_max_stepper_velocity(joint as u8)? / steps_per_degree,
+                acceleration: constants::get_max_stepper_acceleration(joint as u8)? / steps_per_degree,
+            })
+        })
+        .collect()
+}
+
+// Velocity and acceleration a joint runs with, in degrees/s and degrees/s²
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JointParameters {
+    pub joint: i8,
+    pub velocity: f32,
+    pub acceleration: f32,
+}
+
+impl JointParameters {
+    // Converts the steps/s and steps/s² the firmware works in
+    pub fn from_steps(joint: i8, velocity: f32, acceleration: f32) -> Result<Self, String> {
+        let steps_per_degree = constants::get_steps_per_degree(joint as u8)
+            .filter(|_| (1..=6).contains(&joint))
+            .ok_or_else(|| format!("Invalid Joint: {}", joint))?;
+
+        Ok(Self {
+            joint,
+            velocity: velocity / steps_per_degree,
+            acceleration: acceleration / steps_per_degree,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionParameters {
+    // Degrees/s
+    pub velocity: f32,
+    // Degrees/s²
+    pub acceleration: f32,
+    // Overrides of J1 to J6
+    pub joints: [JointOverride; 6],
+}
+
+impl Default for MotionParameters {
+    fn default() -> Self {
+        Self {
+            velocity: constants::DEFAULT_VELOCITY,
+            acceleration: constants::DEFAULT_ACCELERATION,
+            joints: [JointOverride::default(); 6],
+        }
+    }
+}
+
+impl MotionParameters {
+    // Velocity and acceleration a joint runs with, in degrees/s and degrees/s²
+    pub fn joint(&self, joint: i8) -> Result<(f32, f32), String> {
+        if !(1..=6).contains(&joint) {
+            return Err(format!("Invalid Joint: {}", joint));
+        }
+        let overrides = &self.joints[(joint - 1) as usize];
+
+        Ok((
+            overrides.velocity.unwrap_or(self.velocity),
+            overrides.acceleration.unwrap_or(self.acceleration),
+        ))
+    }
+
+    // Velocity and acceleration a joint runs with, in steps/s and steps/s² as the firmware takes them
+    pub fn steps(&self, joint: i8) -> Result<(f32, f32), String> {
+        let (velocity, acceleration) = self.joint(joint)?;
+        let steps_per_degree = constants::get_steps_per_degree(joint as u8)
+            .ok_or_else(|| format!("Invalid Joint: {}", joint))?;
+
+        Ok((velocity * steps_per_degree, acceleration * steps_per_degree))
+    }
+
+    // Parameters slowed down by `factor`, from 0 to 1. Acceleration is scaled by its square, so every
+    // move keeps its shape and only takes 1 / `factor` times longer
+    pub fn scaled(&self, factor: f32) -> MotionParameters {
+        MotionParameters {
+            velocity: self.velocity * factor,
+            acceleration: self.acceleration * factor * factor,
+            joints: self.joints.map(|overrides| JointOverride {
+                velocity: overrides.velocity.map(|velocity| velocity * factor),
+                acceleration: overrides.acceleration.map(|acceleration| acceleration * factor * factor),
+            }),
+        }
+    }
+
+    // Sets `parameter` on every joint without an override when `joint` is `None`. For a single
+    // joint, `None` as value removes its override. Nothing changes when a joint would end up
+    // outside its limits
+    pub fn set(&mut self, parameter: Parameter, joint: Option<i8>, value: Option<f32>) -> Result<(), String> {
+        if let Some(value) = value {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("{} must be greater than 0{}", parameter.name(), parameter.unit()));
+            }
+        }
+
+        let mut parameters = self.clone();
+        match joint {
+            Some(joint) => {
+                if !(1..=6).contains(&joint) {
+                    return Err(format!("Invalid Joint: {}", joint));
+                }
+                let overrides = &mut parameters.joints[(joint - 1) as usize];
+                match parameter {
+                    Parameter::Velocity => overrides.velocity = value,
+                    Parameter::Acceleration => overrides.acceleration = value,
+                }
+            }
+            None => {
+                let value = value.ok_or_else(|| format!("{} needs a value", parameter.name()))?;
+                match parameter {
+                    Parameter::Velocity => parameters.velocity = value,
+                    Parameter::Acceleration => parameters.acceleration = value,
+                }
+            }
+        }
+
+        self.replace(parameters, parameter)
+    }
+
+    // Takes over `parameters` if every joint stays within its limits
+    fn replace(&mut self, parameters: MotionParameters, parameter: Parameter) -> Result<(), String> {
+        for limits in joint_limits() {
+            let (velocity, acceleration) = parameters.joint(limits.joint)?;
+            let (value, limit) = match parameter {
+                Parameter::Velocity => (velocity, limits.velocity),
+                Parameter::Acceleration => (acceleration, limits.acceleration),
+            };
+
+            if value > limit + LIMIT_TOLERANCE {
+                return Err(format!(
+                    "{} of {:.1}{} exceeds the J{} limit of {:.1}{}",
+                    parameter.name(),
+                    value,
+                    parameter.unit(),
+                    limits.joint,
+                    limit,
+                    parameter.unit()
+                ));
+            }
+        }
+
+        *self = parameters;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_joints_are_rejected() {
+        let mut parameters = MotionParameters::default();
+
+        assert_eq!(parameters.joint(i8::MIN).unwrap_err(), "Invalid Joint: -128");
+        assert!(parameters.joint(0).is_err());
+        assert!(parameters.joint(7).is_err());
+        assert!(parameters.set(Parameter::Velocity, Some(i8::MIN), Some(10.0)).is_err());
+        assert!(parameters.set(Parameter::Velocity, Some(6), Some(10.0)).is_ok());
+    }
+}
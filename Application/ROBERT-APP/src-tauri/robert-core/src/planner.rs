@@ -0,0 +1,232 @@
+// Joint-space trajectory planning. The firmware drives every stepper with its own trapezoidal
+// profile (AccelStepper has no other), so moves are synchronized by giving each joint the
+// velocity and acceleration that make it arrive at the same time as the slowest one.
+use crate::parameters::MotionParameters;
+use serde::Serialize;
+
+// Lowest velocity (steps/s) or acceleration (steps/s²) given to a joint, AccelStepper stalls close to 0
+const MIN_PROFILE_VALUE: f32 = 1.0;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JointProfile {
+    pub joint: i8,
+    // Relative motor steps, signed as sent to the firmware
+    pub steps: i32,
+    // Cruise velocity in steps/s
+    pub velocity: f32,
+    // Acceleration in steps/s²
+    pub acceleration: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrajectoryPlan {
+    // Joints that move, joints without steps are left out
+    pub joints: Vec<JointProfile>,
+    // Time spent accelerating, and decelerating, in seconds. Shared by all joints
+    pub accel_time: f32,
+    // Expected duration of the move in seconds
+    pub duration: f32,
+}
+
+impl TrajectoryPlan {
+    // Whether every joint runs with the parameters already set on the steppers
+    pub fn uses_parameters(&self, parameters: &MotionParameters) -> bool {
+        self.joints.iter().all(|profile| {
+            parameters.steps(profile.joint).is_ok_and(|(velocity, acceleration)| {
+                (profile.velocity - round_parameter(velocity)).abs() < 0.01
+                    && (profile.acceleration - round_parameter(acceleration)).abs() < 0.01
+            })
+        })
+    }
+
+    // Slows the move down so it lasts at least `min_duration` seconds, keeping the joints synchronized
+    pub fn stretch(&mut self, min_duration: f32) {
+        if self.duration <= 0.0 || self.duration >= min_duration {
+            return;
+        }
+
+        let factor = min_duration / self.duration;
+
+        for profile in &mut self.joints {
+            profile.velocity = round_parameter(profile.velocity / factor);
+            profile.acceleration = round_parameter(profile.acceleration / (factor * factor));
+        }
+
+        self.accel_time *= factor;
+        self.duration = min_duration;
+    }
+}
+
+// Plans a move of `joints_steps` so all joints start and stop together. Each joint moves at most
+// at its own velocity and acceleration from `parameters`
+pub fn plan_trajectory(
+    joints_steps: &[(i8, i32)],
+    parameters: &MotionParameters,
+) -> Result<TrajectoryPlan, String> {
+    // Longest time any joint needs at its top velocity, and the same for acceleration
+    let mut cruise_bound: f32 = 0.0;
+    let mut accel_bound: f32 = 0.0;
+
+    for &(joint, steps) in joints_steps.iter().filter(|(_, steps)| *steps != 0) {
+        let (velocity, acceleration) = parameters.steps(joint)?;
+        let distance = steps.unsigned_abs() as f32;
+
+        cruise_bound = cruise_bound.max(distance / velocity);
+        accel_bound = accel_bound.max(distance / acceleration);
+    }
+
+    if cruise_bound == 0.0 {
+        return Ok(TrajectoryPlan {
+            joints: Vec::new(),
+            accel_time: 0.0,
+            duration: 0.0,
+        });
+    }
+
+    // A joint covering d steps with a shared acceleration time t_a and total time T cruises at
+    // d / (T - t_a). Without a cruise phase the profile is triangular and t_a = T / 2
+    let (accel_time, duration) = if cruise_bound <= accel_bound.sqrt() {
+        (accel_bound.sqrt(), 2.0 * accel_bound.sqrt())
+    } else {
+        let accel_time = accel_bound / cruise_bound;
+        (accel_time, accel_time + cruise_bound)
+    };
+
+    let joints = joints_steps
+        .iter()
+        .filter(|(_, steps)| *steps != 0)
+        .map(|&(joint, steps)| {
+            let joint_velocity = steps.unsigned_abs() as f32 / (duration - accel_time);
+
+            JointProfile {
+                joint,
+                steps,
+                velocity: round_parameter(joint_velocity),
+                acceleration: round_parameter(joint_velocity / accel_time),
+            }
+        })
+        .collect();
+
+    Ok(TrajectoryPlan {
+        joints,
+        accel_time,
+        duration,
+    })
+}
+
+// Parameters are sent with 2 decimals
+fn round_parameter(value: f32) -> f32 {
+    ((value * 100.0).round() / 100.0).max(MIN_PROFILE_VALUE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(velocity: f32, acceleration: f32) -> MotionParameters {
+        MotionParameters {
+            velocity,
+            acceleration,
+            ..Default::default()
+        }
+    }
+
+    // How long the firmware takes to run `profile`, trapezoidal or triangular
+    fn profile_duration(profile: &JointProfile) -> f32 {
+        let distance = profile.steps.unsigned_abs() as f32;
+
+        if distance >= profile.velocity * profile.velocity / profile.acceleration {
+            distance / profile.velocity + profile.velocity / profile.acceleration
+        } else {
+            2.0 * (distance / profile.acceleration).sqrt()
+        }
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= expected * 0.01, "{} is not {}", value, expected);
+    }
+
+    #[test]
+    fn joints_finish_together() {
+        let parameters = parameters(20.0, 40.0);
+        let plan = plan_trajectory(&[(1, 400), (2, -3000), (3, 50), (6, -120)], &parameters).unwrap();
+
+        assert_eq!(plan.joints.len(), 4);
+        assert!(plan.duration > 2.0 * plan.accel_time, "expected a cruise phase");
+        for profile in &plan.joints {
+            assert_close(profile_duration(profile), plan.duration);
+            assert_close(profile.velocity / profile.acceleration, plan.accel_time);
+
+            // No joint goes over its own limits
+            let (velocity, acceleration) = parameters.steps(profile.joint).unwrap();
+            assert!(profile.velocity <= velocity + 0.01);
+            assert!(profile.acceleration <= acceleration + 0.01);
+        }
+        assert_eq!(plan.joints[1].steps, -3000);
+    }
+
+    #[test]
+    fn short_moves_have_a_triangular_profile() {
+        let plan = plan_trajectory(&[(1, 20), (4, 10)], &parameters(200.0, 10.0)).unwrap();
+
+        assert_close(plan.duration, 2.0 * plan.accel_time);
+        for profile in &plan.joints {
+            assert_close(profile_duration(profile), plan.duration);
+        }
+    }
+
+    #[test]
+    fn joints_without_steps_are_left_out() {
+        let plan = plan_trajectory(&[(1, 0), (2, 500), (3, 0)], &parameters(20.0, 40.0)).unwrap();
+        assert_eq!(plan.joints.iter().map(|profile| profile.joint).collect::<Vec<_>>(), vec![2]);
+
+        let plan = plan_trajectory(&[(1, 0), (2, 0)], &parameters(20.0, 40.0)).unwrap();
+        assert!(plan.joints.is_empty());
+        assert_eq!(plan.duration, 0.0);
+    }
+
+    #[test]
+    fn invalid_joints_are_rejected() {
+        assert!(plan_trajectory(&[(7, 100)], &parameters(20.0, 40.0)).is_err());
+    }
+
+    #[test]
+    fn stretch_slows_every_joint_down_together() {
+        let parameters = parameters(20.0, 40.0);
+        let mut plan = plan_trajectory(&[(1, 400), (2, -3000)], &parameters).unwrap();
+        let duration = plan.duration;
+
+        plan.stretch(duration * 3.0);
+
+        assert_close(plan.duration, duration * 3.0);
+        for profile in &plan.joints {
+            assert_close(profile_duration(profile), plan.duration);
+        }
+        assert!(!plan.uses_parameters(&parameters));
+    }
+
+    #[test]
+    fn stretch_never_speeds_up() {
+        let mut plan = plan_trajectory(&[(1, 400)], &parameters(20.0, 40.0)).unwrap();
+        let velocity = plan.joints[0].velocity;
+        let duration = plan.duration;
+
+        plan.stretch(duration / 2.0);
+        assert_eq!(plan.duration, duration);
+        assert_eq!(plan.joints[0].velocity, velocity);
+
+        let mut empty = plan_trajectory(&[], &parameters(20.0, 40.0)).unwrap();
+        empty.stretch(1.0);
+        assert_eq!(empty.duration, 0.0);
+    }
+
+    #[test]
+    fn single_joint_moves_use_the_set_parameters() {
+        let parameters = parameters(20.0, 40.0);
+        let plan = plan_trajectory(&[(3, 1000)], &parameters).unwrap();
+
+        assert!(plan.uses_parameters(&parameters));
+    }
+}
@@ -0,0 +1,151 @@
+// Background polling of the steppers, so frontends learn about changes they did not cause, e.g. a
+// joint moved by hand while disabled. Only values that changed since the last poll are reported
+use crate::constants::SteppersAngles;
+use crate::events::RobotEvent;
+use crate::jog::JogController;
+use crate::parameters::MotionParameters;
+use crate::state::SharedAppState;
+use crate::utils;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Each poll sends two commands, faster would crowd out the ones from the user
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Interval shared with the polling task, `None` while polling is off
+#[derive(Clone)]
+pub struct PollerController {
+    interval: Arc<watch::Sender<Option<Duration>>>,
+}
+
+impl Default for PollerController {
+    fn default() -> Self {
+        let (interval, _) = watch::channel(None);
+
+        Self {
+            interval: Arc::new(interval),
+        }
+    }
+}
+
+impl PollerController {
+    pub fn interval(&self) -> Option<Duration> {
+        *self.interval.borrow()
+    }
+
+    pub fn set_interval(&self, interval: Option<Duration>) -> Result<(), String> {
+        if interval.is_some_and(|interval| interval < MIN_POLL_INTERVAL) {
+            return Err(format!(
+                "Poll interval must be at least {} ms",
+                MIN_POLL_INTERVAL.as_millis()
+            ));
+        }
+
+        self.interval.send_replace(interval);
+
+        Ok(())
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Option<Duration>> {
+        self.interval.subscribe()
+    }
+}
+
+// Values reported by the last poll, `None` until known
+#[derive(Default)]
+struct LastReport {
+    steps: Option<[Option<f32>; 6]>,
+    states: Option<[bool; 6]>,
+    parameters: Option<MotionParameters>,
+}
+
+// Whether the arm is busy with something the poll would get in the way of
+fn is_busy(state: &SharedAppState, jog: &JogController) -> bool {
+    // Jogs report their own angles while they run
+    if jog.is_jogging() {
+        return true;
+    }
+
+    // A command in flight holds the state, calibrations for as long as they take
+    let Ok(app_state) = state.try_read() else {
+        return true;
+    };
+
+    app_state.positions.is_free_running()
+        || app_state.motion_end.is_some_and(|end| end > Instant::now())
+}
+
+async fn poll(state: &SharedAppState, last: &mut LastReport) -> Result<(), String> {
+    let steps = utils::get_steppers_steps(state.clone()).await?;
+    let states = utils::get_steppers_state(state.clone()).await?;
+
+    let app_state = state.read().await;
+    // Parameters are only changed through the backend, which reports the configured ones. What the
+    // firmware runs with also follows the speed override, see `Robot::read_parameters`
+    let parameters = app_state.parameters.clone();
+
+    if last.steps != Some(steps) {
+        app_state.emit(RobotEvent::SteppersAngles(SteppersAngles::from(utils::steps_to_angles(
+            steps,
+            &app_state.zero_offsets,
+        ))));
+        last.steps = Some(steps);
+    }
+    if last.states != Some(states) {
+        app_state.emit(RobotEvent::SteppersState(states));
+        last.states = Some(states);
+    }
+    if last.parameters.as_ref() != Some(&parameters) {
+        app_state.emit(RobotEvent::Parameters(parameters.clone()));
+        last.parameters = Some(parameters);
+    }
+
+    Ok(())
+}
+
+// Polls every interval until the controller is dropped. Polls are skipped while disconnected or
+// while a move, jog or calibration runs
+pub async fn run(state: SharedAppState, jog: JogController, mut interval: watch::Receiver<Option<Duration>>) {
+    let mut last = LastReport::default();
+
+    loop {
+        let current = *interval.borrow_and_update();
+
+        match current {
+            Some(period) => {
+                tokio::select! {
+                    _ = sleep(period) => {}
+                    changed = interval.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+            }
+            None => {
+                if interval.changed().await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        }
+
+        if state.read().await.serial_connection.is_none() {
+            // Everything is reported again after reconnecting
+            last = LastReport::default();
+            continue;
+        }
+
+        if is_busy(&state, &jog) {
+            continue;
+        }
+
+        if let Err(e) = poll(&state, &mut last).await {
+            eprintln!("###DEBUG### - State poll failed: {}", e);
+        }
+    }
+}
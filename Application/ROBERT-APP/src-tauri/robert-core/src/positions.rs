@@ -0,0 +1,211 @@
+use crate::robot::Robot;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+// Version written to positions files, bump when the format changes
+pub const POSITIONS_FILE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredPosition {
+    pub name: String,
+    // Positive angles from the joint zero, as taken by `drive_steppers_to_angles`. `None` for
+    // joints that were not calibrated when the position was saved. They are not stored with the zero
+    // offsets they were saved under, changing the offsets shifts the position
+    pub angles: [Option<f32>; 6],
+    #[serde(default)]
+    pub notes: Option<String>,
+    // Tool mounted on the arm when the position was taught
+    #[serde(default)]
+    pub tool: Option<String>,
+    // Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+impl StoredPosition {
+    pub fn new(name: &str, angles: [Option<f32>; 6], notes: Option<String>, tool: Option<String>) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            name: name.trim().to_string(),
+            angles,
+            notes,
+            tool,
+            created_at,
+        }
+    }
+
+    // Joint angles to drive to, skipping joints without a stored angle
+    pub fn joints_angles(&self) -> Vec<(i8, f32)> {
+        self.angles
+            .iter()
+            .enumerate()
+            .filter_map(|(i, angle)| angle.map(|angle| ((i + 1) as i8, angle)))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PositionsFile {
+    version: u32,
+    positions: Vec<StoredPosition>,
+}
+
+fn read_positions_file(path: &Path) -> Result<Vec<StoredPosition>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read positions file {}: {}", path.display(), e))?;
+    let file: PositionsFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid positions file {}: {}", path.display(), e))?;
+
+    if file.version > POSITIONS_FILE_VERSION {
+        return Err(format!(
+            "Positions file {} has version {}, only up to {} is supported",
+            path.display(),
+            file.version,
+            POSITIONS_FILE_VERSION
+        ));
+    }
+
+    Ok(file.positions)
+}
+
+fn write_positions_file(path: &Path, positions: &[StoredPosition]) -> Result<(), String> {
+    let file = PositionsFile {
+        version: POSITIONS_FILE_VERSION,
+        positions: positions.to_vec(),
+    };
+    let content = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+
+    utils::write_atomic(path, &content)
+}
+
+// Named positions persisted to a JSON file, every change is written straight to disk
+pub struct PositionStore {
+    path: PathBuf,
+    positions: Vec<StoredPosition>,
+}
+
+impl PositionStore {
+    // Loads the store from `path`, starting empty when the file does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let positions = if path.exists() {
+            read_positions_file(&path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, positions })
+    }
+
+    pub fn list(&self) -> &[StoredPosition] {
+        &self.positions
+    }
+
+    pub fn get(&self, name: &str) -> Result<&StoredPosition, String> {
+        self.positions
+            .iter()
+            .find(|position| position.name == name)
+            .ok_or_else(|| format!("Position '{}' does not exist", name))
+    }
+
+    // Stores `position`, replacing any position with the same name
+    pub fn save(&mut self, position: StoredPosition) -> Result<(), String> {
+        if position.name.is_empty() {
+            return Err("Position name cannot be empty".to_string());
+        }
+
+        match self.positions.iter_mut().find(|stored| stored.name == position.name) {
+            Some(stored) => *stored = position,
+            None => self.positions.push(position),
+        }
+
+        write_positions_file(&self.path, &self.positions)
+    }
+
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Result<(), String> {
+        let new_name = new_name.trim();
+
+        if new_name.is_empty() {
+            return Err("Position name cannot be empty".to_string());
+        }
+        if name != new_name && self.get(new_name).is_ok() {
+            return Err(format!("Position '{}' already exists", new_name));
+        }
+
+        let position = self
+            .positions
+            .iter_mut()
+            .find(|position| position.name == name)
+            .ok_or_else(|| format!("Position '{}' does not exist", name))?;
+        position.name = new_name.to_string();
+
+        write_positions_file(&self.path, &self.positions)
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), String> {
+        let count = self.positions.len();
+        self.positions.retain(|position| position.name != name);
+
+        if self.positions.len() == count {
+            return Err(format!("Position '{}' does not exist", name));
+        }
+
+        write_positions_file(&self.path, &self.positions)
+    }
+
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        write_positions_file(path, &self.positions)
+    }
+
+    // Adds the positions stored in `path`, replacing those with the same name. Returns how many were imported
+    pub fn import(&mut self, path: &Path) -> Result<usize, String> {
+        let imported = read_positions_file(path)?;
+        let count = imported.len();
+
+        for position in imported {
+            match self.positions.iter_mut().find(|stored| stored.name == position.name) {
+                Some(stored) => *stored = position,
+                None => self.positions.push(position),
+            }
+        }
+
+        write_positions_file(&self.path, &self.positions)?;
+
+        Ok(count)
+    }
+}
+
+pub type SharedPositionStore = Arc<Mutex<PositionStore>>;
+
+// Saves the current pose of the arm under `name`
+pub async fn save_current_position(
+    robot: &Robot,
+    store: &SharedPositionStore,
+    name: &str,
+    notes: Option<String>,
+    tool: Option<String>,
+) -> Result<StoredPosition, String> {
+    let angles = utils::to_positive_angles(robot.angles().await?);
+
+    if angles.iter().all(|angle| angle.is_none()) {
+        return Err("No joint has a known angle, calibrate the arm first".to_string());
+    }
+
+    let position = StoredPosition::new(name, angles, notes, tool);
+    store.lock().await.save(position.clone())?;
+
+    Ok(position)
+}
+
+pub async fn go_to_position(robot: &Robot, store: &SharedPositionStore, name: &str) -> Result<String, String> {
+    let joints_angles = store.lock().await.get(name)?.joints_angles();
+
+    robot.move_joints(joints_angles).await
+}
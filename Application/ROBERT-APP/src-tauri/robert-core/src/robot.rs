@@ -0,0 +1,447 @@
+use crate::calibration;
+use crate::cartesian::{self, CartesianAxis, JogFrame};
+use crate::constants::SteppersAngles;
+use crate::events::RobotEvent;
+use crate::jog::{self, JogController, JogTarget};
+use crate::kinematics::{self, CartesianPose, CartesianPosition};
+use crate::parameters::{self, JointLimits, JointParameters, MotionParameters, Parameter};
+use crate::planner::TrajectoryPlan;
+use crate::poller::{self, PollerController};
+use crate::positions::PositionStore;
+use crate::script::Script;
+use crate::state::{AppState, SharedAppState};
+use crate::utils;
+use crate::validation::{self, StartPose, ValidationReport};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+// Capacity of the event channel, slow subscribers skip the oldest events once it is full
+const EVENTS_CAPACITY: usize = 64;
+
+// Handle to the arm, cheap to clone and shared between the app, the CLI and any other frontend
+#[derive(Clone)]
+pub struct Robot {
+    state: SharedAppState,
+    events: broadcast::Sender<RobotEvent>,
+    jog: JogController,
+    poller: PollerController,
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Robot {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+        let mut state = AppState::new();
+        state.events = Some(events.clone());
+
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            events,
+            jog: JogController::default(),
+            poller: PollerController::default(),
+        }
+    }
+
+    // Shared state holding the serial connection, for the lower level functions in `utils`
+    pub fn shared_state(&self) -> SharedAppState {
+        self.state.clone()
+    }
+
+    // Stream of events reported by the robot
+    pub fn subscribe(&self) -> broadcast::Receiver<RobotEvent> {
+        self.events.subscribe()
+    }
+
+    // Calls `callback` for every event reported by the robot, must be called within a tokio runtime
+    pub fn on_event<F>(&self, callback: F) -> JoinHandle<()>
+    where
+        F: Fn(RobotEvent) + Send + 'static,
+    {
+        let mut receiver = self.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => callback(event),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    // Polls the steppers in the background, reporting angles, states and parameters when they
+    // change. Runs until the robot is dropped, spawn it on the runtime. Polling starts once an
+    // interval is set
+    pub fn poll_state(&self) -> impl Future<Output = ()> + Send + 'static {
+        poller::run(self.shared_state(), self.jog.clone(), self.poller.subscribe())
+    }
+
+    // `None` stops polling, see `poller::MIN_POLL_INTERVAL`
+    pub fn set_poll_interval(&self, interval: Option<Duration>) -> Result<(), String> {
+        self.poller.set_interval(interval)
+    }
+
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.poller.interval()
+    }
+
+    pub(crate) fn jog_controller(&self) -> JogController {
+        self.jog.clone()
+    }
+
+    // The firmware can not interrupt a MOVE> or CALIBRATE>, a jogging joint would then run to the end
+    // of its range while STOP> and the heartbeat watchdog go unheard
+    fn check_not_jogging(&self) -> Result<(), String> {
+        match self.jog.is_jogging() {
+            true => Err("A jog is running, stop it first".to_string()),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn emit(&self, event: RobotEvent) {
+        // Sending only fails when nobody is listening, which is fine
+        let _ = self.events.send(event);
+    }
+
+    pub fn ports() -> Vec<String> {
+        utils::get_ports()
+    }
+
+    pub async fn connect(&self, port: &str) -> Result<String, String> {
+        utils::connect_to_port(port.to_string(), self.shared_state()).await
+    }
+
+    pub async fn disconnect(&self) -> Result<String, String> {
+        self.jog.stop().await;
+
+        utils::disconnect_from_active_connection(self.shared_state()).await
+    }
+
+    pub async fn is_connected(&self) -> bool {
+        self.state.read().await.serial_connection.is_some()
+    }
+
+    // Drives joints to absolute angles, given as positive numbers from the joint zero
+    // All joints are synchronized to arrive at the same time
+    pub async fn move_joints(&self, joints_angles: Vec<(i8, f32)>) -> Result<String, String> {
+        self.check_not_jogging()?;
+        let plan = self.plan_move(joints_angles).await?;
+
+        self.emit(RobotEvent::TrajectoryPlanned(plan.clone()));
+
+        let response = utils::execute_trajectory(&plan, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        Ok(response)
+    }
+
+    // Plans a move to absolute angles without executing it, to preview its duration
+    pub async fn plan_move(&self, joints_angles: Vec<(i8, f32)>) -> Result<TrajectoryPlan, String> {
+        utils::plan_steppers_to_angles(joints_angles, self.shared_state()).await
+    }
+
+    // Moves the tool flange along a straight line to `target`, at up to `speed` mm/s, capped at and
+    // defaulting to `MAX_TOOL_SPEED`. Every joint must be calibrated
+    pub async fn move_linear(&self, target: CartesianPose, speed: Option<f32>) -> Result<String, String> {
+        self.check_not_jogging()?;
+        let start_angles = utils::get_joint_positions(self.shared_state()).await?;
+        let (speed_factor, zero_offsets) = {
+            let app_state = self.state.read().await;
+            (app_state.speed_factor(), app_state.zero_offsets)
+        };
+        let path = cartesian::linear_path(&start_angles, &target, &zero_offsets)?;
+        let segment_duration = path.segment_duration(speed)? / speed_factor;
+
+        let response = utils::execute_joint_path(&path.waypoints, segment_duration, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        Ok(response)
+    }
+
+    // Moves the tool flange along the arc through `via` to `end`, at up to `speed` mm/s, capped at
+    // and defaulting to `MAX_TOOL_SPEED`. Every joint must be calibrated
+    pub async fn move_arc(
+        &self,
+        via: CartesianPosition,
+        end: CartesianPose,
+        speed: Option<f32>,
+    ) -> Result<String, String> {
+        self.check_not_jogging()?;
+        let start_angles = utils::get_joint_positions(self.shared_state()).await?;
+        let (speed_factor, zero_offsets) = {
+            let app_state = self.state.read().await;
+            (app_state.speed_factor(), app_state.zero_offsets)
+        };
+        let path = cartesian::arc_path(&start_angles, &via, &end, &zero_offsets)?;
+        let segment_duration = path.segment_duration(speed)? / speed_factor;
+
+        let response = utils::execute_joint_path(&path.waypoints, segment_duration, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        Ok(response)
+    }
+
+    // Jogs the tool by `increment` mm or degrees along or around an axis of the base or tool frame.
+    // Rejected before moving when the jog would cross a joint limit or a singularity
+    pub async fn jog_cartesian(
+        &self,
+        axis: CartesianAxis,
+        frame: JogFrame,
+        increment: f32,
+    ) -> Result<CartesianPose, String> {
+        self.check_not_jogging()?;
+        let start_angles = utils::get_joint_positions(self.shared_state()).await?;
+        let zero_offsets = self.zero_offsets().await;
+        let path = cartesian::jog_path(&start_angles, axis, frame, increment, &zero_offsets)
+            .map_err(|e| format!("Jog rejected. {}", e))?;
+
+        utils::execute_joint_path(&path.waypoints, 0.0, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        self.tool_pose().await
+    }
+
+    // Current pose of the tool flange, every joint must be calibrated
+    pub async fn tool_pose(&self) -> Result<CartesianPose, String> {
+        let angles = utils::get_joint_positions(self.shared_state()).await?;
+
+        Ok(kinematics::forward_kinematics(&angles).to_pose())
+    }
+
+    // Moves a single joint by a number of motor steps
+    pub async fn jog(&self, joint_index: i8, n_steps: i16) -> Result<String, String> {
+        self.check_not_jogging()?;
+        let response = utils::move_step(joint_index, n_steps, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        Ok(response)
+    }
+
+    // Moves a single joint by an angle in degrees, returns the angle reached
+    pub async fn jog_joint(&self, joint_index: i8, delta_degrees: f32) -> Result<f32, String> {
+        self.check_not_jogging()?;
+        let angle = utils::jog_joint(joint_index, delta_degrees, self.shared_state()).await?;
+
+        self.refresh_angles().await?;
+
+        Ok(angle)
+    }
+
+    // Starts moving `target` in `direction` (1 or -1) at `speed` degrees/s, or mm/s for
+    // translations, until `stop_jog` is called, the end of the range is reached or heartbeats
+    // stop arriving for `jog::HEARTBEAT_TIMEOUT`. `frame` is only used by Cartesian axes
+    pub async fn start_jog(
+        &self,
+        target: JogTarget,
+        direction: i8,
+        speed: f32,
+        frame: JogFrame,
+    ) -> Result<(), String> {
+        jog::start(self, target, direction, speed, frame).await
+    }
+
+    // Keeps the running jog alive, returns whether there is one
+    pub fn heartbeat_jog(&self) -> bool {
+        self.jog.heartbeat()
+    }
+
+    // Decelerates the running jog and waits until the arm is at rest
+    pub async fn stop_jog(&self) {
+        self.jog.stop().await
+    }
+
+    pub fn is_jogging(&self) -> bool {
+        self.jog.is_jogging()
+    }
+
+    // Disables every stepper, cutting their power. A running jog is stopped too, but the firmware
+    // can not interrupt a move started with MOVE>. Joints may drift while unpowered, so recalibrate
+    // before relying on their angles again
+    pub async fn emergency_stop(&self) -> Result<String, String> {
+        self.jog.request_stop();
+
+        // Every joint is disabled even if one fails
+        let mut errors = Vec::new();
+        for joint in 1..=6 {
+            if let Err(e) = utils::toggle_stepper(joint, "DISABLED", self.shared_state()).await {
+                errors.push(format!("J{}: {}", joint, e));
+            }
+        }
+
+        self.jog.stop().await;
+
+        if errors.is_empty() {
+            Ok("Emergency stop, every stepper is disabled".to_string())
+        } else {
+            Err(format!("Emergency stop failed for {}", errors.join(", ")))
+        }
+    }
+
+    // Refused while jogging, like every motion
+    pub async fn calibrate(&self, joints_indexes: &[i8]) -> Result<String, String> {
+        self.check_not_jogging()?;
+        utils::calibrate_steppers(joints_indexes, self.shared_state()).await
+    }
+
+    // Degrees from the limit switch to the zero of each joint, angles are measured from the zeros
+    pub async fn set_zero_offsets(&self, offsets: [f32; 6]) -> Result<(), String> {
+        calibration::check_zero_offsets(&offsets)?;
+        self.state.write().await.zero_offsets = offsets;
+
+        // Angles shown until now were measured from the old zeros
+        if self.is_connected().await {
+            self.refresh_angles().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn zero_offsets(&self) -> [f32; 6] {
+        self.state.read().await.zero_offsets
+    }
+
+    // Makes where `joint` is now its zero, once it has been jogged to its zero mark. Returns the
+    // offset, which callers persist
+    pub async fn set_zero_here(&self, joint: i8) -> Result<f32, String> {
+        if !(1..=6).contains(&joint) {
+            return Err(format!("Invalid Joint: {}", joint));
+        }
+
+        // A fresh reading, the offset is only as good as it
+        let steps = utils::get_steppers_steps(self.shared_state()).await?[(joint - 1) as usize]
+            .ok_or_else(|| format!("J{} must be calibrated before setting its zero", joint))?;
+        let offset = utils::steps_to_angle(joint, steps, &[0.0; 6])?;
+
+        if !(0.0..=calibration::MAX_ZERO_OFFSET).contains(&offset) {
+            return Err(format!(
+                "J{} is {:.1}° from its limit switch, a zero offset is between 0° and {}°. Jog it to its zero mark first",
+                joint,
+                offset,
+                calibration::MAX_ZERO_OFFSET
+            ));
+        }
+
+        let mut offsets = self.zero_offsets().await;
+        offsets[(joint - 1) as usize] = offset;
+        self.set_zero_offsets(offsets).await?;
+
+        Ok(offset)
+    }
+
+    // `enabled` is either "ENABLED" or "DISABLED"
+    pub async fn toggle(&self, joint_index: i8, enabled: &str) -> Result<String, String> {
+        utils::toggle_stepper(joint_index, enabled, self.shared_state()).await
+    }
+
+    // Velocity in degrees/s of every joint without an override
+    pub async fn set_velocity(&self, velocity: f32) -> Result<String, String> {
+        utils::set_parameter(Parameter::Velocity, None, Some(velocity), self.shared_state()).await
+    }
+
+    // Acceleration in degrees/s² of every joint without an override
+    pub async fn set_acceleration(&self, acceleration: f32) -> Result<String, String> {
+        utils::set_parameter(Parameter::Acceleration, None, Some(acceleration), self.shared_state()).await
+    }
+
+    // Velocity in degrees/s of a single joint, `None` makes it follow the global velocity again
+    pub async fn set_joint_velocity(&self, joint_index: i8, velocity: Option<f32>) -> Result<String, String> {
+        utils::set_parameter(Parameter::Velocity, Some(joint_index), velocity, self.shared_state()).await
+    }
+
+    // Acceleration in degrees/s² of a single joint, `None` makes it follow the global acceleration again
+    pub async fn set_joint_acceleration(&self, joint_index: i8, acceleration: Option<f32>) -> Result<String, String> {
+        utils::set_parameter(Parameter::Acceleration, Some(joint_index), acceleration, self.shared_state()).await
+    }
+
+    // Velocity and acceleration as configured, with the per joint overrides
+    pub async fn parameters(&self) -> MotionParameters {
+        self.state.read().await.parameters.clone()
+    }
+
+    // Velocity and acceleration each joint runs with, read back from the firmware. They are the
+    // configured ones slowed down by the speed override, to the 0.01 step/s the firmware keeps
+    pub async fn read_parameters(&self) -> Result<Vec<JointParameters>, String> {
+        let steps = utils::get_steppers_parameters(self.shared_state()).await?;
+
+        steps
+            .iter()
+            .enumerate()
+            .map(|(i, (velocity, acceleration))| JointParameters::from_steps(i as i8 + 1, *velocity, *acceleration))
+            .collect()
+    }
+
+    // Runs every motion, scripts and jogs included, at `percent` of the configured speed
+    pub async fn set_speed_override(&self, percent: f32) -> Result<String, String> {
+        utils::set_speed_override(percent, self.shared_state()).await
+    }
+
+    pub async fn speed_override(&self) -> f32 {
+        self.state.read().await.speed_override
+    }
+
+    // Dry run of `script` from `start` with the current parameters and speed override, nothing is sent
+    // to the arm
+    pub async fn validate_script(
+        &self,
+        script: &Script,
+        start: &StartPose,
+        positions: Option<&PositionStore>,
+    ) -> ValidationReport {
+        let app_state = self.state.read().await;
+
+        validation::validate_script(
+            script,
+            start,
+            &app_state.parameters,
+            app_state.speed_override,
+            &app_state.zero_offsets,
+            positions,
+        )
+    }
+
+    // Highest velocity and acceleration each joint accepts
+    pub fn parameter_limits() -> Vec<JointLimits> {
+        parameters::joint_limits()
+    }
+
+    // Returns whether each stepper is enabled
+    pub async fn steppers_state(&self) -> Result<[bool; 6], String> {
+        utils::get_steppers_state(self.shared_state()).await
+    }
+
+    // Reads the current joint angles from the firmware, checking them against the position model,
+    // and reports them to subscribers
+    pub async fn angles(&self) -> Result<[Option<f32>; 6], String> {
+        let angles = utils::get_steppers_angles(self.shared_state()).await?;
+
+        self.emit(RobotEvent::SteppersAngles(SteppersAngles::from(angles)));
+
+        Ok(angles)
+    }
+
+    // Reports the angles after a movement, from the position model unless it is due a reading
+    async fn refresh_angles(&self) -> Result<(), String> {
+        let angles = utils::get_tracked_angles(self.shared_state())
+            .await
+            .map_err(|e| format!("Error retrieving stepper angles: {}", e))?;
+
+        self.emit(RobotEvent::SteppersAngles(SteppersAngles::from(angles)));
+
+        Ok(())
+    }
+}
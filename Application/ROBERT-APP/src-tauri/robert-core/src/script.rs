@@ -0,0 +1,1006 @@
+// Parser of .rob scripts. One statement per line, anything after `//` is a comment:
+//
+//   MOVE>J<n>_<expr>;...         move joints to absolute angles, e.g. MOVE>J1_45;J2_base + 10;
+//   TOGGLE>J<n>_<ENABLED|DISABLED>;...
+//   CALIBRATE>J<n>;...
+//   SETVEL><value>; | SETVEL>J<n>_<value>;...   velocity in degrees/s
+//   SETACC><value>; | SETACC>J<n>_<value>;...   acceleration in degrees/s²
+//   LIN>X_..;Y_..;Z_..;[ROLL_..;PITCH_..;YAW_..;SPEED_..;]
+//   ARC>VX_..;VY_..;VZ_..;X_..;Y_..;Z_..;[ROLL_..;PITCH_..;YAW_..;SPEED_..;]
+//   WAIT <expr>                  pause for that many milliseconds
+//   LET <name> = <expr>          set a variable, names start with a letter or '_'
+//   REPEAT <expr> {              run the lines up to the matching '}' that many times
+//   }
+//   GOTO_POS <name>              move to a stored position
+//   HOME                         move every calibrated joint back to 0°, the joint zero; it does
+//                                not calibrate and ignores the home pose of the `home` command
+//   DEF <name>(<param>, ...)     define a subroutine up to the matching END, outside other blocks
+//   END
+//   CALL <name>(<expr>, ...)     run a subroutine with its parameters set to the values
+//   INCLUDE "<path>"             run another script here and make its subroutines available,
+//                                the path is relative to the including script
+//
+// Expressions are numbers and variables combined with + - * / and parentheses. They are
+// evaluated when the line runs, so variables can change between iterations of a REPEAT.
+// Subroutines see the variables of their caller, those they set are dropped when they return.
+// A subroutine may not call itself, not even through others, as nothing could end the recursion
+use crate::kinematics::{CartesianPose, CartesianPosition};
+use crate::robot::Robot;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Commands sent to the arm as-is, once any expression in them has been evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Move(Vec<(i8, f32)>),
+    Toggle(Vec<(i8, String)>),
+    Calibrate(Vec<i8>),
+    // Degrees/s
+    SetVel(ParameterValues),
+    // Degrees/s²
+    SetAcc(ParameterValues),
+    // Orientation angles left out keep the orientation of the tool when the move starts
+    Linear {
+        end: CartesianPosition,
+        orientation: [Option<f32>; 3],
+        speed: Option<f32>,
+    },
+    Arc {
+        via: CartesianPosition,
+        end: CartesianPosition,
+        orientation: [Option<f32>; 3],
+        speed: Option<f32>,
+    },
+}
+
+// Value of SETVEL> and SETACC>, for every joint without an override or for single joints
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterValues {
+    Global(f32),
+    Joints(Vec<(i8, f32)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f32),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl Expression {
+    pub fn evaluate(&self, variables: &HashMap<String, f32>) -> Result<f32, String> {
+        let value = match self {
+            Expression::Number(value) => *value,
+            Expression::Variable(name) => *variables
+                .get(name)
+                .ok_or_else(|| format!("Unknown variable '{}'", name))?,
+            Expression::Negate(expression) => -expression.evaluate(variables)?,
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(variables)?;
+                let right = right.evaluate(variables)?;
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide if right == 0.0 => return Err("Division by zero".to_string()),
+                    Operator::Divide => left / right,
+                }
+            }
+        };
+
+        if !value.is_finite() {
+            return Err("Expression result is out of range".to_string());
+        }
+
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Instruction(Instruction),
+    // Angles are evaluated when the move runs
+    Move(Vec<(i8, Expression)>),
+    // Milliseconds
+    Wait(Expression),
+    Let(String, Expression),
+    Repeat(Expression, Vec<ParsedStatement>),
+    GotoPos(String),
+    Home,
+    Call(String, Vec<Expression>),
+    // Path as written and the statements of the file, filled in once it is loaded
+    Include(String, Vec<ParsedStatement>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedStatement {
+    pub line: usize,
+    // Included file the statement comes from, `None` for the script itself
+    pub file: Option<Arc<str>>,
+    pub statement: Statement,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subroutine {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<ParsedStatement>,
+    pub line: usize,
+    pub file: Option<Arc<str>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    pub statements: Vec<ParsedStatement>,
+    // Subroutines of the script and every file it includes
+    pub subroutines: HashMap<String, Subroutine>,
+}
+
+// Where a statement is, for error messages
+pub fn location(line: usize, file: Option<&str>) -> String {
+    match file {
+        Some(file) => format!("{}: Line {}", file, line),
+        None => format!("Line {}", line),
+    }
+}
+
+// Duration of a WAIT, expressions can give a negative, infinite or NaN number of milliseconds
+pub fn wait_duration(milliseconds: f32) -> Result<Duration, String> {
+    Duration::try_from_secs_f32(milliseconds / 1000.0)
+        .map_err(|_| format!("WAIT needs a finite, positive duration, got {} ms", milliseconds))
+}
+
+// Numbers as written to scripts, to the thousandth and without float noise like 12.300001
+pub fn format_number(value: f32) -> String {
+    format!("{}", (value * 1000.0).round() / 1000.0 + 0.0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            let value = number.parse::<f32>().map_err(|_| format!("invalid number '{}'", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else {
+            tokens.push(match c {
+                '+' => Token::Operator(Operator::Add),
+                '-' => Token::Operator(Operator::Subtract),
+                '*' => Token::Operator(Operator::Multiply),
+                '/' => Token::Operator(Operator::Divide),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => return Err(format!("unexpected '{}'", c)),
+            });
+            chars.next();
+        }
+    }
+
+    Ok(tokens)
+}
+
+// Recursive descent over the tokens of an expression, * and / bind tighter than + and -
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn binary(
+        &mut self,
+        operators: [Operator; 2],
+        operand: fn(&mut Self) -> Result<Expression, String>,
+    ) -> Result<Expression, String> {
+        let mut expression = operand(self)?;
+
+        while let Some(&Token::Operator(operator)) = self.peek() {
+            if !operators.contains(&operator) {
+                break;
+            }
+            self.position += 1;
+            expression = Expression::Binary(Box::new(expression), operator, Box::new(operand(self)?));
+        }
+
+        Ok(expression)
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        self.binary([Operator::Add, Operator::Subtract], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        self.binary([Operator::Multiply, Operator::Divide], Self::operand)
+    }
+
+    fn operand(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expression::Number(value)),
+            Some(Token::Name(name)) => Ok(Expression::Variable(name)),
+            Some(Token::Operator(Operator::Subtract)) => Ok(Expression::Negate(Box::new(self.operand()?))),
+            Some(Token::Operator(Operator::Add)) => self.operand(),
+            Some(Token::Open) => {
+                let expression = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Token::Close) => Err("unexpected ')'".to_string()),
+            Some(Token::Operator(_)) => Err("expected a value before the operator".to_string()),
+            None => Err("expected a value".to_string()),
+        }
+    }
+}
+
+fn parse_expression(source: &str, line: usize) -> Result<Expression, String> {
+    let source = source.trim();
+    let mut parser = ExpressionParser {
+        tokens: tokenize(source).map_err(|e| format!("Line {}: Invalid expression '{}': {}", line, source, e))?,
+        position: 0,
+    };
+
+    let expression = parser
+        .sum()
+        .and_then(|expression| match parser.peek() {
+            None => Ok(expression),
+            Some(_) => Err("expected an operator".to_string()),
+        })
+        .map_err(|e| format!("Line {}: Invalid expression '{}': {}", line, source, e))?;
+
+    Ok(expression)
+}
+
+fn parse_name(name: &str, line: usize) -> Result<String, String> {
+    let name = name.trim();
+    match tokenize(name).as_deref() {
+        Ok([Token::Name(name)]) => Ok(name.clone()),
+        _ => Err(format!("Line {}: Invalid name '{}'", line, name)),
+    }
+}
+
+// Splits the parameters after '>' into their ';' separated, non empty parts
+fn split_params(params: &str) -> Vec<&str> {
+    params
+        .split(';')
+        .map(|param| param.trim())
+        .filter(|param| !param.is_empty())
+        .collect()
+}
+
+fn parse_joint(joint: &str, line: usize) -> Result<i8, String> {
+    joint
+        .trim()
+        .strip_prefix('J')
+        .and_then(|index| index.parse::<i8>().ok())
+        .ok_or_else(|| format!("Line {}: Invalid joint '{}'", line, joint))
+}
+
+fn parse_value(value: &str, line: usize) -> Result<f32, String> {
+    value
+        .trim()
+        .trim_end_matches(';')
+        .parse::<f32>()
+        .map_err(|_| format!("Line {}: Invalid value '{}'", line, value))
+}
+
+// SETVEL>20; sets every joint, SETVEL>J1_20;J2_10; single joints
+fn parse_parameter_values(params: &str, line: usize) -> Result<ParameterValues, String> {
+    if !params.contains('_') {
+        return Ok(ParameterValues::Global(parse_value(params, line)?));
+    }
+
+    let joints_values = split_params(params)
+        .into_iter()
+        .map(|param| {
+            let (joint, value) = param
+                .split_once('_')
+                .ok_or_else(|| format!("Line {}: Invalid parameter '{}'", line, param))?;
+            Ok((parse_joint(joint, line)?, parse_value(value, line)?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(ParameterValues::Joints(joints_values))
+}
+
+// Parses `KEY_value` parameters into a map, rejecting keys not in `keys`
+fn parse_named_values<'a>(
+    params: &'a str,
+    keys: &[&str],
+    line: usize,
+) -> Result<HashMap<&'a str, f32>, String> {
+    split_params(params)
+        .into_iter()
+        .map(|param| {
+            let (key, value) = param
+                .split_once('_')
+                .ok_or_else(|| format!("Line {}: Invalid parameter '{}'", line, param))?;
+            if !keys.contains(&key) {
+                return Err(format!("Line {}: Unknown parameter '{}'", line, key));
+            }
+            let value = value
+                .parse::<f32>()
+                .map_err(|_| format!("Line {}: Invalid value '{}'", line, value))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn parse_instruction(line: &str, line_number: usize) -> Result<Instruction, String> {
+    if let Some(params) = line.strip_prefix("TOGGLE>") {
+        // TOGGLE>J1_ENABLED;J2_DISABLED;
+        let joints_states = split_params(params)
+            .into_iter()
+            .map(|param| {
+                let (joint, state) = param
+                    .split_once('_')
+                    .ok_or_else(|| format!("Line {}: Invalid toggle parameter '{}'", line_number, param))?;
+                Ok((parse_joint(joint, line_number)?, state.to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Instruction::Toggle(joints_states))
+    } else if let Some(params) = line.strip_prefix("CALIBRATE>") {
+        // CALIBRATE>J1;J2;
+        let joints = split_params(params)
+            .into_iter()
+            .map(|joint| parse_joint(joint, line_number))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Instruction::Calibrate(joints))
+    } else if let Some(value) = line.strip_prefix("SETVEL>") {
+        Ok(Instruction::SetVel(parse_parameter_values(value, line_number)?))
+    } else if let Some(value) = line.strip_prefix("SETACC>") {
+        Ok(Instruction::SetAcc(parse_parameter_values(value, line_number)?))
+    } else if let Some(params) = line.strip_prefix("LIN>") {
+        // LIN>X_270;Y_0;Z_350;PITCH_-45;SPEED_20;
+        let values = parse_named_values(params, &["X", "Y", "Z", "ROLL", "PITCH", "YAW", "SPEED"], line_number)?;
+        let required = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .ok_or_else(|| format!("Line {}: Missing parameter '{}'", line_number, key))
+        };
+
+        Ok(Instruction::Linear {
+            end: CartesianPosition { x: required("X")?, y: required("Y")?, z: required("Z")? },
+            orientation: [values.get("ROLL").copied(), values.get("PITCH").copied(), values.get("YAW").copied()],
+            speed: values.get("SPEED").copied(),
+        })
+    } else if let Some(params) = line.strip_prefix("ARC>") {
+        // ARC>VX_250;VY_40;VZ_380;X_270;Y_0;Z_350;PITCH_-45;SPEED_20;
+        let values = parse_named_values(
+            params,
+            &["VX", "VY", "VZ", "X", "Y", "Z", "ROLL", "PITCH", "YAW", "SPEED"],
+            line_number,
+        )?;
+        let required = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .ok_or_else(|| format!("Line {}: Missing parameter '{}'", line_number, key))
+        };
+
+        Ok(Instruction::Arc {
+            via: CartesianPosition { x: required("VX")?, y: required("VY")?, z: required("VZ")? },
+            end: CartesianPosition { x: required("X")?, y: required("Y")?, z: required("Z")? },
+            orientation: [values.get("ROLL").copied(), values.get("PITCH").copied(), values.get("YAW").copied()],
+            speed: values.get("SPEED").copied(),
+        })
+    } else {
+        Err(format!("Line {}: Invalid command: {}", line_number, line))
+    }
+}
+
+fn parse_line(line: &str, line_number: usize) -> Result<Statement, String> {
+    if let Some(params) = line.strip_prefix("MOVE>") {
+        // MOVE>J1_45;J2_angle * 2;
+        let joints_angles = split_params(params)
+            .into_iter()
+            .map(|param| {
+                let (joint, angle) = param
+                    .split_once('_')
+                    .ok_or_else(|| format!("Line {}: Invalid move parameter '{}'", line_number, param))?;
+                Ok((parse_joint(joint, line_number)?, parse_expression(angle, line_number)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        return Ok(Statement::Move(joints_angles));
+    }
+    if line.contains('>') {
+        return Ok(Statement::Instruction(parse_instruction(line, line_number)?));
+    }
+
+    let (keyword, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let argument = argument.trim();
+    let required = |usage: &str| match argument.is_empty() {
+        true => Err(format!("Line {}: Expected {}", line_number, usage)),
+        false => Ok(argument),
+    };
+
+    match keyword {
+        "WAIT" => Ok(Statement::Wait(parse_expression(required("WAIT <milliseconds>")?, line_number)?)),
+        "LET" => {
+            let (name, value) = required("LET <name> = <value>")?
+                .split_once('=')
+                .ok_or_else(|| format!("Line {}: Expected LET <name> = <value>", line_number))?;
+            Ok(Statement::Let(parse_name(name, line_number)?, parse_expression(value, line_number)?))
+        }
+        "GOTO_POS" => Ok(Statement::GotoPos(required("GOTO_POS <position name>")?.to_string())),
+        "HOME" if argument.is_empty() => Ok(Statement::Home),
+        "HOME" => Err(format!("Line {}: HOME takes no arguments", line_number)),
+        "CALL" => {
+            let (name, args) = parse_signature(required("CALL <name>(<value>, ...)")?, line_number)?;
+            let args = args
+                .into_iter()
+                .map(|arg| parse_expression(arg, line_number))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(Statement::Call(name, args))
+        }
+        "INCLUDE" => {
+            let path = required("INCLUDE \"<path>\"")?
+                .strip_prefix('"')
+                .and_then(|path| path.strip_suffix('"'))
+                .filter(|path| !path.is_empty())
+                .ok_or_else(|| format!("Line {}: Expected INCLUDE \"<path>\"", line_number))?;
+            Ok(Statement::Include(path.to_string(), Vec::new()))
+        }
+        _ => Err(format!("Line {}: Invalid command: {}", line_number, line)),
+    }
+}
+
+// Splits `name(a, b)` into the name and its comma separated arguments
+fn parse_signature(signature: &str, line: usize) -> Result<(String, Vec<&str>), String> {
+    let (name, args) = signature
+        .strip_suffix(')')
+        .and_then(|signature| signature.split_once('('))
+        .ok_or_else(|| format!("Line {}: Expected <name>(...) but got '{}'", line, signature))?;
+    let args = match args.trim() {
+        "" => Vec::new(),
+        args => args.split(',').map(|arg| arg.trim()).collect(),
+    };
+
+    Ok((parse_name(name, line)?, args))
+}
+
+// Removes a trailing `// comment` and surrounding whitespace, `//` within quotes is kept
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut previous = None;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '/' if !in_quotes && previous == Some('/') => return line[..index - 1].trim(),
+            _ => {}
+        }
+        previous = Some(c);
+    }
+
+    line.trim()
+}
+
+enum Block {
+    Repeat(Expression),
+    Def(String, Vec<String>),
+}
+
+// Parses a single file, leaving its INCLUDEs unresolved
+fn parse_file(content: &str, file: Option<Arc<str>>) -> Result<Script, String> {
+    let mut script = Script::default();
+    // Blocks being parsed, with the line that opened them and the statements around them
+    let mut blocks: Vec<(usize, Block, Vec<ParsedStatement>)> = Vec::new();
+    let mut statements = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line == "}" || line == "END" {
+            let (block_line, block, body) = blocks
+                .pop()
+                .ok_or_else(|| format!("Line {}: '{}' does not close any block", line_number, line))?;
+            let block_statements = std::mem::replace(&mut statements, body);
+
+            match (block, line) {
+                (Block::Repeat(count), "}") => statements.push(ParsedStatement {
+                    line: block_line,
+                    file: file.clone(),
+                    statement: Statement::Repeat(count, block_statements),
+                }),
+                (Block::Def(name, params), "END") => {
+                    if let Some(existing) = script.subroutines.get(&name) {
+                        return Err(format!(
+                            "Line {}: Subroutine {} is already defined on line {}",
+                            block_line, name, existing.line
+                        ));
+                    }
+                    script.subroutines.insert(
+                        name.clone(),
+                        Subroutine {
+                            name,
+                            params,
+                            body: block_statements,
+                            line: block_line,
+                            file: file.clone(),
+                        },
+                    );
+                }
+                (Block::Repeat(_), _) => {
+                    return Err(format!("Line {}: REPEAT on line {} is closed with '}}', not END", line_number, block_line))
+                }
+                (Block::Def(..), _) => {
+                    return Err(format!("Line {}: DEF on line {} is closed with END, not '}}'", line_number, block_line))
+                }
+            }
+        } else if let Some(count) = line.strip_prefix("REPEAT").filter(|rest| rest.starts_with(char::is_whitespace)) {
+            let count = count
+                .trim()
+                .strip_suffix('{')
+                .ok_or_else(|| format!("Line {}: Expected REPEAT <count> {{", line_number))?;
+            let count = parse_expression(count, line_number)?;
+            blocks.push((line_number, Block::Repeat(count), std::mem::take(&mut statements)));
+        } else if let Some(signature) = line.strip_prefix("DEF").filter(|rest| rest.starts_with(char::is_whitespace)) {
+            if !blocks.is_empty() {
+                return Err(format!("Line {}: DEF is only allowed outside of other blocks", line_number));
+            }
+            let (name, params) = parse_signature(signature.trim(), line_number)?;
+            let params = params
+                .into_iter()
+                .map(|param| parse_name(param, line_number))
+                .collect::<Result<Vec<_>, String>>()?;
+            blocks.push((line_number, Block::Def(name, params), std::mem::take(&mut statements)));
+        } else {
+            let statement = parse_line(line, line_number)?;
+            if matches!(statement, Statement::Include(..)) && !blocks.is_empty() {
+                return Err(format!("Line {}: INCLUDE is only allowed outside of other blocks", line_number));
+            }
+            statements.push(ParsedStatement {
+                line: line_number,
+                file: file.clone(),
+                statement,
+            });
+        }
+    }
+
+    if let Some((block_line, block, _)) = blocks.last() {
+        return Err(match block {
+            Block::Repeat(_) => format!("Line {}: REPEAT is never closed with '}}'", block_line),
+            Block::Def(name, _) => format!("Line {}: DEF {} is never closed with END", block_line, name),
+        });
+    }
+
+    script.statements = statements;
+
+    Ok(script)
+}
+
+// Loads the files `script` includes, `path` is where the script itself is. `stack` holds the
+// files being loaded, to reject a file that ends up including itself. `merged` holds the files
+// whose subroutines were already taken, a library included along two paths defines them once
+fn resolve_includes(
+    script: &mut Script,
+    path: Option<&Path>,
+    stack: &mut Vec<PathBuf>,
+    merged: &mut HashSet<PathBuf>,
+) -> Result<(), String> {
+    let mut subroutines = Vec::new();
+
+    for parsed in script.statements.iter_mut() {
+        let Statement::Include(name, statements) = &mut parsed.statement else {
+            continue;
+        };
+        let at = location(parsed.line, parsed.file.as_deref());
+
+        let directory = path
+            .and_then(|path| path.parent())
+            .ok_or_else(|| format!("{}: INCLUDE needs the script to be loaded from a file", at))?;
+        let included_path = directory
+            .join(name.as_str())
+            .canonicalize()
+            .map_err(|e| format!("{}: Cannot find {}: {}", at, name, e))?;
+
+        if stack.contains(&included_path) {
+            let cycle = stack
+                .iter()
+                .chain(std::iter::once(&included_path))
+                .skip_while(|file| **file != included_path)
+                .map(|file| file.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(format!("{}: INCLUDE cycle: {}", at, cycle));
+        }
+
+        let content = std::fs::read_to_string(&included_path)
+            .map_err(|e| format!("{}: Failed to read {}: {}", at, name, e))?;
+        // Named relative to the script itself
+        let file: Arc<str> = match parsed.file.as_deref() {
+            Some(parent) => Path::new(parent).with_file_name(name.as_str()).to_string_lossy().into(),
+            None => Arc::from(name.as_str()),
+        };
+        let mut included = parse_file(&content, Some(file.clone())).map_err(|e| format!("{}: {}", file, e))?;
+
+        stack.push(included_path.clone());
+        resolve_includes(&mut included, Some(&included_path), stack, merged)?;
+        stack.pop();
+
+        *statements = included.statements;
+        if merged.insert(included_path) {
+            subroutines.extend(included.subroutines.into_values());
+        }
+    }
+
+    for subroutine in subroutines {
+        if let Some(existing) = script.subroutines.get(&subroutine.name) {
+            return Err(format!(
+                "{}: Subroutine {} is already defined at {}",
+                location(subroutine.line, subroutine.file.as_deref()),
+                subroutine.name,
+                location(existing.line, existing.file.as_deref())
+            ));
+        }
+        script.subroutines.insert(subroutine.name.clone(), subroutine);
+    }
+
+    Ok(())
+}
+
+// CALL statements within `statements`, including those in blocks
+fn calls(statements: &[ParsedStatement]) -> Vec<(&ParsedStatement, &str, usize)> {
+    statements
+        .iter()
+        .flat_map(|parsed| match &parsed.statement {
+            Statement::Call(name, args) => vec![(parsed, name.as_str(), args.len())],
+            Statement::Repeat(_, body) | Statement::Include(_, body) => calls(body),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// Fails on a call of `name` that leads back to a subroutine in `path`
+fn check_recursion<'s>(script: &'s Script, name: &'s str, path: &mut Vec<&'s str>) -> Result<(), String> {
+    let Some(subroutine) = script.subroutines.get(name) else {
+        return Ok(());
+    };
+    path.push(name);
+
+    for (parsed, callee, _) in calls(&subroutine.body) {
+        if path.contains(&callee) {
+            return Err(format!(
+                "{}: Recursive CALL: {} -> {}",
+                location(parsed.line, parsed.file.as_deref()),
+                path.join(" -> "),
+                callee
+            ));
+        }
+        check_recursion(script, callee, path)?;
+    }
+
+    path.pop();
+
+    Ok(())
+}
+
+// Checks every CALL has a subroutine with as many parameters, and that none recurses
+fn check_calls(script: &Script) -> Result<(), String> {
+    let bodies = script.subroutines.values().map(|subroutine| &subroutine.body);
+
+    for statements in std::iter::once(&script.statements).chain(bodies) {
+        for (parsed, name, count) in calls(statements) {
+            let at = location(parsed.line, parsed.file.as_deref());
+            let subroutine = script
+                .subroutines
+                .get(name)
+                .ok_or_else(|| format!("{}: Unknown subroutine {}", at, name))?;
+            if subroutine.params.len() != count {
+                return Err(format!(
+                    "{}: {} takes {} arguments but got {}",
+                    at,
+                    name,
+                    subroutine.params.len(),
+                    count
+                ));
+            }
+        }
+    }
+
+    let mut names: Vec<&str> = script.subroutines.keys().map(|name| name.as_str()).collect();
+    // Report the same cycle on every run
+    names.sort();
+    for name in names {
+        check_recursion(script, name, &mut Vec::new())?;
+    }
+
+    Ok(())
+}
+
+// Parses a script given as text, it cannot INCLUDE other files as there is no path to start from
+pub fn parse_script(content: &str) -> Result<Script, String> {
+    let mut script = parse_file(content, None)?;
+    resolve_includes(&mut script, None, &mut Vec::new(), &mut HashSet::new())?;
+    check_calls(&script)?;
+
+    Ok(script)
+}
+
+// Reads and parses the script at `path`, along with every file it includes
+pub fn load_script(path: &Path) -> Result<Script, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read script {}: {}", path.display(), e))?;
+
+    parse_script_at(&content, path)
+}
+
+// Parses `content` as if it were the script at `path`, which does not have to exist. INCLUDE paths
+// are relative to it
+pub fn parse_script_at(content: &str, path: &Path) -> Result<Script, String> {
+    let directory = path
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .canonicalize()
+        .map_err(|e| format!("Failed to read script {}: {}", path.display(), e))?;
+    let path = directory.join(path.file_name().unwrap_or_default());
+
+    let mut script = parse_file(content, None)?;
+    resolve_includes(&mut script, Some(&path), &mut vec![path.clone()], &mut HashSet::new())?;
+    check_calls(&script)?;
+
+    Ok(script)
+}
+
+// Pose at `end`, orientation angles left out are those of the tool now
+async fn end_pose(robot: &Robot, end: &CartesianPosition, orientation: &[Option<f32>; 3]) -> Result<CartesianPose, String> {
+    let current = robot.tool_pose().await?;
+
+    Ok(CartesianPose {
+        x: end.x,
+        y: end.y,
+        z: end.z,
+        roll: orientation[0].unwrap_or(current.roll),
+        pitch: orientation[1].unwrap_or(current.pitch),
+        yaw: orientation[2].unwrap_or(current.yaw),
+    })
+}
+
+pub async fn execute_instruction(instruction: &Instruction, robot: &Robot) -> Result<String, String> {
+    match instruction {
+        Instruction::Move(joints_angles) => robot.move_joints(joints_angles.clone()).await,
+        Instruction::Toggle(joints_states) => {
+            let mut responses = Vec::new();
+            for (joint, enabled) in joints_states {
+                responses.push(robot.toggle(*joint, enabled).await?);
+            }
+            Ok(responses.join("\n"))
+        }
+        Instruction::Calibrate(joints) => robot.calibrate(joints).await,
+        Instruction::SetVel(ParameterValues::Global(velocity)) => robot.set_velocity(*velocity).await,
+        Instruction::SetAcc(ParameterValues::Global(acceleration)) => robot.set_acceleration(*acceleration).await,
+        Instruction::SetVel(ParameterValues::Joints(joints_velocities)) => {
+            let mut responses = Vec::new();
+            for (joint, velocity) in joints_velocities {
+                responses.push(robot.set_joint_velocity(*joint, Some(*velocity)).await?);
+            }
+            Ok(responses.join("\n"))
+        }
+        Instruction::SetAcc(ParameterValues::Joints(joints_accelerations)) => {
+            let mut responses = Vec::new();
+            for (joint, acceleration) in joints_accelerations {
+                responses.push(robot.set_joint_acceleration(*joint, Some(*acceleration)).await?);
+            }
+            Ok(responses.join("\n"))
+        }
+        Instruction::Linear { end, orientation, speed } => {
+            let end = end_pose(robot, end, orientation).await?;
+            robot.move_linear(end, *speed).await
+        }
+        Instruction::Arc { via, end, orientation, speed } => {
+            let end = end_pose(robot, end, orientation).await?;
+            robot.move_arc(*via, end, *speed).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Value of the expression of `LET x = <expression>`
+    fn evaluate(expression: &str) -> Result<f32, String> {
+        let script = parse_script(&format!("LET x = {}", expression))?;
+        match &script.statements[0].statement {
+            Statement::Let(_, expression) => expression.evaluate(&HashMap::new()),
+            statement => panic!("Parsed as {:?}", statement),
+        }
+    }
+
+    // Directory of its own for a test that writes scripts
+    fn scripts_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("robert-script-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!(evaluate("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(evaluate("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(evaluate("10 - 4 - 3").unwrap(), 3.0);
+        assert_eq!(evaluate("8 / 4 / 2").unwrap(), 1.0);
+        assert_eq!(evaluate("-2 * 3 + 1").unwrap(), -5.0);
+        assert_eq!(evaluate("2 * -(1 + 2)").unwrap(), -6.0);
+        assert_eq!(evaluate("1 / 0").unwrap_err(), "Division by zero");
+    }
+
+    #[test]
+    fn variables_are_evaluated_when_asked() {
+        let script = parse_script("MOVE>J1_base + 10;J2_base * 2;").unwrap();
+        let Statement::Move(joints_angles) = &script.statements[0].statement else {
+            panic!("Parsed as {:?}", script.statements[0].statement);
+        };
+        let variables = HashMap::from([("base".to_string(), 15.0)]);
+
+        let angles: Vec<(i8, f32)> = joints_angles
+            .iter()
+            .map(|(joint, angle)| (*joint, angle.evaluate(&variables).unwrap()))
+            .collect();
+        assert_eq!(angles, vec![(1, 25.0), (2, 30.0)]);
+        assert_eq!(joints_angles[0].1.evaluate(&HashMap::new()).unwrap_err(), "Unknown variable 'base'");
+    }
+
+    #[test]
+    fn blocks_nest_statements() {
+        let script = parse_script("REPEAT 2 {\n  LET a = 1\n  REPEAT a {\n    WAIT 10\n  }\n}\nHOME").unwrap();
+
+        assert_eq!(script.statements.len(), 2);
+        let Statement::Repeat(_, body) = &script.statements[0].statement else {
+            panic!("Parsed as {:?}", script.statements[0].statement);
+        };
+        assert_eq!(body.iter().map(|parsed| parsed.line).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(script.statements[1], ParsedStatement { line: 7, file: None, statement: Statement::Home });
+    }
+
+    #[test]
+    fn errors_name_their_line() {
+        let error = |content: &str| parse_script(content).unwrap_err();
+
+        assert!(error("HOME\nLET 1x = 2").starts_with("Line 2:"));
+        assert_eq!(error("HOME\n\nMOVE>K1_10;"), "Line 3: Invalid joint 'K1'");
+        assert!(error("LET a = (1 + 2").starts_with("Line 1:"));
+        assert_eq!(error("HOME\nREPEAT 2 {\nHOME"), "Line 2: REPEAT is never closed with '}'");
+        assert_eq!(error("DEF f()\nHOME"), "Line 1: DEF f is never closed with END");
+        assert_eq!(error("HOME\nCALL nope()"), "Line 2: Unknown subroutine nope");
+        assert_eq!(error("DEF f(a)\nHOME\nEND\nCALL f(1, 2)"), "Line 4: f takes 1 arguments but got 2");
+    }
+
+    #[test]
+    fn recursion_is_rejected() {
+        assert!(parse_script("DEF f()\nCALL f()\nEND").unwrap_err().contains("Recursive CALL: f -> f"));
+        assert!(parse_script("DEF a()\nCALL b()\nEND\nDEF b()\nREPEAT 2 {\nCALL a()\n}\nEND")
+            .unwrap_err()
+            .contains("Recursive CALL: a -> b -> a"));
+        assert!(parse_script("DEF a()\nCALL b()\nEND\nDEF b()\nHOME\nEND\nCALL a()\nCALL b()").is_ok());
+    }
+
+    #[test]
+    fn includes_are_loaded_relative_to_the_script() {
+        let dir = scripts_dir("includes");
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        write(&dir, "lib/grip.rob", "DEF grip(width)\nMOVE>J6_width;\nEND\nWAIT 5");
+        let main = write(&dir, "main.rob", "INCLUDE \"lib/grip.rob\"\nCALL grip(10)");
+
+        let script = load_script(&main).unwrap();
+        assert!(script.subroutines.contains_key("grip"));
+        let Statement::Include(name, statements) = &script.statements[0].statement else {
+            panic!("Parsed as {:?}", script.statements[0].statement);
+        };
+        assert_eq!(name, "lib/grip.rob");
+        assert_eq!(statements[0].file.as_deref(), Some("lib/grip.rob"));
+
+        // Errors in included files name the file
+        write(&dir, "lib/bad.rob", "HOME\nMOVE>J1_;");
+        let bad = write(&dir, "bad.rob", "INCLUDE \"lib/bad.rob\"");
+        assert!(load_script(&bad).unwrap_err().starts_with("lib/bad.rob: Line 2:"));
+
+        assert_eq!(
+            parse_script("INCLUDE \"lib/grip.rob\"").unwrap_err(),
+            "Line 1: INCLUDE needs the script to be loaded from a file"
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let dir = scripts_dir("cycles");
+        write(&dir, "a.rob", "INCLUDE \"b.rob\"");
+        write(&dir, "b.rob", "INCLUDE \"a.rob\"");
+
+        assert!(load_script(&dir.join("a.rob")).unwrap_err().contains("INCLUDE cycle: a.rob -> b.rob -> a.rob"));
+    }
+
+    #[test]
+    fn files_included_twice_define_their_subroutines_once() {
+        let dir = scripts_dir("diamond");
+        write(&dir, "common.rob", "DEF open()\nWAIT 1\nEND");
+        write(&dir, "left.rob", "INCLUDE \"common.rob\"\nDEF left()\nCALL open()\nEND");
+        write(&dir, "right.rob", "INCLUDE \"common.rob\"\nDEF right()\nCALL open()\nEND");
+        let main = write(&dir, "main.rob", "INCLUDE \"left.rob\"\nINCLUDE \"right.rob\"\nCALL left()\nCALL right()");
+
+        let script = load_script(&main).unwrap();
+        let mut names: Vec<&String> = script.subroutines.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["left", "open", "right"]);
+
+        // Two files defining the same name still clash
+        write(&dir, "other.rob", "DEF open()\nHOME\nEND");
+        let clash = write(&dir, "clash.rob", "INCLUDE \"common.rob\"\nINCLUDE \"other.rob\"");
+        assert!(load_script(&clash).unwrap_err().contains("Subroutine open is already defined"));
+    }
+
+    #[test]
+    fn wait_durations_must_be_finite_and_positive() {
+        assert_eq!(wait_duration(250.0).unwrap(), Duration::from_millis(250));
+        for milliseconds in [-1.0, f32::NAN, f32::INFINITY, 1e30] {
+            assert!(wait_duration(milliseconds).is_err(), "{} ms", milliseconds);
+        }
+    }
+
+    #[test]
+    fn numbers_are_written_without_float_noise() {
+        assert_eq!(format_number(12.300001), "12.3");
+        assert_eq!(format_number(0.0004), "0");
+        assert_eq!(format_number(-0.0001), "0");
+        assert_eq!(format_number(-45.1236), "-45.124");
+        assert_eq!(format_number(90.0), "90");
+    }
+}
@@ -0,0 +1,319 @@
+use crate::constants::{self, ResponseCodes};
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+// Velocity (steps/s) and acceleration (steps/s²) set by initializeSteppers()
+const FIRMWARE_DEFAULT_PARAMETER: f32 = 200.0;
+
+// Emulates the SerialController firmware so the backend can be exercised without an arm.
+// Moves complete instantly and limit switches are hit as soon as a calibrated joint reaches 0.
+pub struct SimulatedController {
+    steps: [i64; 6],
+    calibrated: [bool; 6],
+    enabled: [bool; 6],
+    // Of each stepper, a global SETVEL> or SETACC> sets them all
+    velocities: [f32; 6],
+    accelerations: [f32; 6],
+}
+
+impl Default for SimulatedController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedController {
+    pub fn new() -> Self {
+        Self {
+            steps: [0; 6],
+            calibrated: [false; 6],
+            enabled: [true; 6],
+            velocities: [FIRMWARE_DEFAULT_PARAMETER; 6],
+            accelerations: [FIRMWARE_DEFAULT_PARAMETER; 6],
+        }
+    }
+
+    // Processes a single command (without the trailing '~') and returns the response line
+    pub fn process(&mut self, command: &str) -> String {
+        let Some((code, action)) = command.split_once('>') else {
+            return "C001".to_string();
+        };
+
+        match code {
+            "MOVE" => self.process_move(action, "[INFO];MOVING_STEPS:"),
+            // Jogs complete instantly too, so there is never anything left to stop
+            "JOG" => self.process_move(action, "[INFO];JOGGING_STEPS:"),
+            "STOP" => "[INFO];STOPPING".to_string(),
+            "CHECK" => ResponseCodes::CONNECTED_RESPONSE.to_string(),
+            "SETVEL" if action.contains('_') => {
+                Self::set_parameters(&mut self.velocities, action);
+                format!("Velocity set to: {}", action.replace(';', " "))
+            }
+            "SETACC" if action.contains('_') => {
+                Self::set_parameters(&mut self.accelerations, action);
+                format!("Acceleration Set to: {}", action.replace(';', " "))
+            }
+            // Like atoi(), the firmware takes whole steps/s for every stepper
+            "SETVEL" => {
+                let velocity = action.trim_end_matches(';').parse::<i32>().unwrap_or(0);
+                self.velocities = [velocity as f32; 6];
+                format!("Velocity set to: {}", velocity)
+            }
+            "SETACC" => {
+                let acceleration = action.trim_end_matches(';').parse::<i32>().unwrap_or(0);
+                self.accelerations = [acceleration as f32; 6];
+                format!("Acceleration Set to: {}", acceleration)
+            }
+            "TOGGLE" => self.process_toggle(action),
+            "CALIBRATE" => self.process_calibrate(action),
+            "STATE" => {
+                let states: String = self
+                    .enabled
+                    .iter()
+                    .enumerate()
+                    .map(|(i, enabled)| format!("J{}_{};", i + 1, if *enabled { "ENABLED" } else { "DISABLED" }))
+                    .collect();
+                format!("{}{}", ResponseCodes::STATE_RESPONSE, states)
+            }
+            "STEPS" => {
+                let steps: String = (0..6)
+                    .map(|i| {
+                        if self.calibrated[i] {
+                            format!("J{}_{};", i + 1, self.steps[i])
+                        } else {
+                            format!("J{}_UNKNOWN;", i + 1)
+                        }
+                    })
+                    .collect();
+                format!("{}{}", ResponseCodes::STEPS_RESPONSE, steps)
+            }
+            "PARAMS" => {
+                let parameters: String = (0..6)
+                    .map(|i| format!("J{}_{:.2}_{:.2};", i + 1, self.velocities[i], self.accelerations[i]))
+                    .collect();
+                format!("{}{}", ResponseCodes::PARAMS_RESPONSE, parameters)
+            }
+            "CALSTATE" => {
+                let calibration: String = self
+                    .calibrated
+                    .iter()
+                    .enumerate()
+                    .map(|(i, calibrated)| format!("J{}_{};", i + 1, *calibrated as u8))
+                    .collect();
+                format!("{}{}", ResponseCodes::CALIBRATION_RESPONSE, calibration)
+            }
+            _ => "C002".to_string(),
+        }
+    }
+
+    // Sets the steppers of `J<n>_<value>;` pairs, skipping malformed ones like the firmware
+    fn set_parameters(values: &mut [f32; 6], action: &str) {
+        for part in action.split(';').filter(|part| !part.is_empty()) {
+            if let Some((index, value)) = part
+                .split_once('_')
+                .and_then(|(joint, value)| Some((Self::stepper_index(joint)?, value.parse::<f32>().ok()?)))
+            {
+                values[index] = value;
+            }
+        }
+    }
+
+    // Parses a `J<n>` prefix into a 0-based stepper index
+    fn stepper_index(joint: &str) -> Option<usize> {
+        joint
+            .strip_prefix('J')
+            .and_then(|index| index.parse::<usize>().ok())
+            .filter(|index| (1..=6).contains(index))
+            .map(|index| index - 1)
+    }
+
+    fn process_move(&mut self, action: &str, response_prefix: &str) -> String {
+        if !action.contains(';') {
+            return "C001".to_string();
+        }
+
+        let mut response = String::from(response_prefix);
+
+        for part in action.split(';').filter(|part| !part.is_empty()) {
+            let Some((joint, steps)) = part.split_once('_') else {
+                continue;
+            };
+            let (Some(index), Ok(steps)) = (Self::stepper_index(joint), steps.parse::<i64>()) else {
+                continue;
+            };
+            if steps == 0 {
+                continue;
+            }
+
+            response.push_str(&format!("J{}_{} ", index + 1, steps));
+
+            let positive_to_limit = constants::STEPPER_POSITIVE_TO_LIMIT[&((index + 1) as u8)];
+            let target = self.steps[index] + steps;
+            let towards_limit = (positive_to_limit && steps > 0) || (!positive_to_limit && steps < 0);
+
+            // The limit switch sits at position 0 once the joint has been calibrated
+            let crosses_limit = self.calibrated[index]
+                && towards_limit
+                && (if positive_to_limit { target >= 0 } else { target <= 0 });
+
+            self.steps[index] = if crosses_limit { 0 } else { target };
+        }
+
+        response
+    }
+
+    fn process_toggle(&mut self, action: &str) -> String {
+        if !action.contains(';') {
+            return "C001".to_string();
+        }
+
+        let mut response = Vec::new();
+
+        for part in action.split(';').filter(|part| !part.is_empty()) {
+            let (joint, state) = part.split_once('_').unwrap_or((part, ""));
+            let Some(index) = Self::stepper_index(joint) else {
+                return "I001".to_string();
+            };
+
+            match state {
+                "ENABLED" => self.enabled[index] = true,
+                "DISABLED" => self.enabled[index] = false,
+                _ => return "I002".to_string(),
+            }
+
+            response.push(format!("Stepper: J{} {}", index + 1, state));
+        }
+
+        response.join("\n")
+    }
+
+    fn process_calibrate(&mut self, action: &str) -> String {
+        if !action.contains(';') {
+            return "C001".to_string();
+        }
+
+        let mut failed_joints = String::new();
+
+        for joint in action.split(';').filter(|joint| !joint.is_empty()) {
+            match Self::stepper_index(joint) {
+                Some(index) => {
+                    self.steps[index] = 0;
+                    self.calibrated[index] = true;
+                }
+                None => failed_joints.push_str(&format!("{};", joint)),
+            }
+        }
+
+        if failed_joints.is_empty() {
+            format!("{}OK", ResponseCodes::CALIBRATION_RESPONSE)
+        } else {
+            format!("{}{}", ResponseCodes::CALIBRATION_RESPONSE, failed_joints)
+        }
+    }
+}
+
+// Starts a simulated controller on a background task and returns the host end of its connection
+pub fn spawn_simulator() -> DuplexStream {
+    let (host, mut device) = duplex(4096);
+
+    tokio::spawn(async move {
+        let mut controller = SimulatedController::new();
+        let mut pending = String::new();
+        let mut buffer = [0; 1024];
+
+        loop {
+            let bytes_read = match device.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(bytes_read) => bytes_read,
+            };
+
+            pending.push_str(&String::from_utf8_lossy(&buffer[..bytes_read]));
+
+            // Commands are terminated by '~', like Serial.readStringUntil('~') on the firmware
+            while let Some(end) = pending.find('~') {
+                let command: String = pending.drain(..=end).collect();
+                let response = controller.process(&command[..command.len() - 1]);
+
+                if device.write_all(format!("{}\n", response).as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    host
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CommandCodes;
+    use crate::parameters::JointParameters;
+    use crate::robot::Robot;
+    use crate::utils;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    // Sends `command` like the host does and returns the response line
+    async fn send(stream: &mut BufReader<DuplexStream>, command: &str) -> String {
+        stream.get_mut().write_all(format!("{}~", command).as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_line(&mut response).await.unwrap();
+        response.trim_end().to_string()
+    }
+
+    #[tokio::test]
+    async fn moves_are_reported_by_steps() {
+        let mut stream = BufReader::new(spawn_simulator());
+
+        assert_eq!(send(&mut stream, CommandCodes::CHECK).await, ResponseCodes::CONNECTED_RESPONSE);
+        assert_eq!(send(&mut stream, "STEPS>").await, "[STEPS];J1_UNKNOWN;J2_UNKNOWN;J3_UNKNOWN;J4_UNKNOWN;J5_UNKNOWN;J6_UNKNOWN;");
+        assert_eq!(send(&mut stream, "CALIBRATE>J1;J3;").await, "[CALIBRATION];OK");
+
+        // J1 moves away from its switch with positive steps, J3 with negative ones
+        assert_eq!(send(&mut stream, "MOVE>J1_250;J3_-40;").await, "[INFO];MOVING_STEPS:J1_250 J3_-40");
+        assert_eq!(send(&mut stream, "MOVE>J1_-50;").await, "[INFO];MOVING_STEPS:J1_-50");
+        assert_eq!(send(&mut stream, CommandCodes::STEPS).await, "[STEPS];J1_200;J2_UNKNOWN;J3_-40;J4_UNKNOWN;J5_UNKNOWN;J6_UNKNOWN;");
+
+        // Moving past the limit switch stops on it
+        send(&mut stream, "MOVE>J1_-500;J3_500;").await;
+        assert_eq!(send(&mut stream, CommandCodes::STEPS).await, "[STEPS];J1_0;J2_UNKNOWN;J3_0;J4_UNKNOWN;J5_UNKNOWN;J6_UNKNOWN;");
+
+        assert_eq!(send(&mut stream, "CALSTATE>").await, "[CALIBRATION];J1_1;J2_0;J3_1;J4_0;J5_0;J6_0;");
+        assert_eq!(send(&mut stream, "NOPE").await, "C001");
+    }
+
+    #[tokio::test]
+    async fn robot_moves_on_the_simulator() {
+        let robot = Robot::new();
+        robot.connect(constants::SIMULATOR_PORT).await.unwrap();
+        robot.calibrate(&[1, 2]).await.unwrap();
+
+        robot.move_joints(vec![(1, 90.0), (2, 30.0)]).await.unwrap();
+
+        let angles = utils::to_positive_angles(robot.angles().await.unwrap());
+        assert!((angles[0].unwrap() - 90.0).abs() < 1.0, "J1 at {:?}", angles[0]);
+        assert!((angles[1].unwrap() - 30.0).abs() < 1.0, "J2 at {:?}", angles[1]);
+        assert_eq!(angles[2], None);
+    }
+
+    #[tokio::test]
+    async fn parameters_read_back_as_sent() {
+        let robot = Robot::new();
+        robot.connect(constants::SIMULATOR_PORT).await.unwrap();
+        robot.set_joint_velocity(2, Some(12.5)).await.unwrap();
+        robot.set_speed_override(50.0).await.unwrap();
+
+        let parameters = robot.read_parameters().await.unwrap();
+        let parameters_set = robot.parameters().await.scaled(0.5);
+
+        for joint in &parameters {
+            // Sent with two decimals in steps, which is what comes back
+            let (velocity, acceleration) = parameters_set.steps(joint.joint).unwrap();
+            let sent = |steps: f32| format!("{:.2}", steps).parse::<f32>().unwrap();
+            let expected = JointParameters::from_steps(joint.joint, sent(velocity), sent(acceleration)).unwrap();
+            assert_eq!(*joint, expected);
+        }
+        assert!((parameters[1].velocity - 6.25).abs() < 0.01, "{:?}", parameters[1]);
+        assert!((parameters[0].velocity - constants::DEFAULT_VELOCITY / 2.0).abs() < 0.01, "{:?}", parameters[0]);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::events::RobotEvent;
+use crate::parameters::{self, MotionParameters};
+use crate::tracking::PositionModel;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::Mutex;
+use tokio::sync::RwLock;
+
+// Anything the controller can be talked to through: a serial port or the simulator
+pub trait SerialTransport: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> SerialTransport for T {}
+
+// Shared type for serial connection
+pub type SharedSerialConnection = Arc<Mutex<Box<dyn SerialTransport>>>;
+
+// Define your application state
+pub struct AppState {
+    pub serial_connection: Option<SharedSerialConnection>,
+    // Velocity and acceleration of the joints as configured
+    pub parameters: MotionParameters,
+    // Percentage of the configured speed every motion runs at
+    pub speed_override: f32,
+    // Degrees from the limit switch to the kinematic zero of each joint, see `calibration`
+    pub zero_offsets: [f32; 6],
+    // Where the steppers are, following the commanded moves
+    pub positions: PositionModel,
+    // When the last move sent is expected to end, the firmware reads nothing else until then
+    pub motion_end: Option<Instant>,
+    // Robot events, for the lower level functions in `utils`
+    pub events: Option<broadcast::Sender<RobotEvent>>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self {
+            serial_connection: None,
+            parameters: MotionParameters::default(),
+            speed_override: parameters::MAX_SPEED_OVERRIDE,
+            zero_offsets: [0.0; 6],
+            positions: PositionModel::default(),
+            motion_end: None,
+            events: None,
+        }
+    }
+
+    // Fraction of the configured speed motions run at, from the speed override
+    pub fn speed_factor(&self) -> f32 {
+        self.speed_override / 100.0
+    }
+
+    // Parameters motions are planned with and the steppers are set to when idle, slowed down by
+    // the speed override
+    pub fn motion_parameters(&self) -> MotionParameters {
+        self.parameters.scaled(self.speed_factor())
+    }
+
+    pub fn emit(&self, event: RobotEvent) {
+        if let Some(events) = &self.events {
+            // Sending only fails when nobody is listening, which is fine
+            let _ = events.send(event);
+        }
+    }
+
+    pub fn set_connection(&mut self, connection: SharedSerialConnection) {
+        self.serial_connection = Some(connection);
+    }
+}
+
+// Shared state type for your application
+pub type SharedAppState = Arc<RwLock<AppState>>;
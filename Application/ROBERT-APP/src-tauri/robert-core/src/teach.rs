@@ -0,0 +1,195 @@
+// Teach mode. Operators jog the arm and record a waypoint whenever it is where they want it, the
+// recording is then exported as a .rob script that drives through the same waypoints
+use crate::parameters::{MotionParameters, Parameter};
+use crate::robot::Robot;
+use crate::script::format_number;
+use crate::utils;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Waypoint {
+    // Positive angles from the joint zero when recorded, `None` for joints that were not calibrated.
+    // Changing the zero offsets afterwards shifts where the waypoint is
+    pub angles: [Option<f32>; 6],
+    // Velocity and acceleration set when the waypoint was recorded, the move to it runs with them
+    pub parameters: MotionParameters,
+    // Milliseconds to wait once the waypoint is reached
+    pub dwell: Option<f32>,
+    pub label: Option<String>,
+}
+
+// Recorded waypoints, in the order they are driven through
+#[derive(Default)]
+pub struct Recording {
+    waypoints: Vec<Waypoint>,
+}
+
+// Writes SETVEL> or SETACC> when the parameter changed since the previous waypoint
+struct ParameterWriter {
+    parameter: Parameter,
+    previous: Option<(f32, [f32; 6])>,
+    // Joints that had an override at some point, they keep it until set again
+    overridden: [bool; 6],
+}
+
+impl ParameterWriter {
+    fn new(parameter: Parameter) -> Self {
+        Self {
+            parameter,
+            previous: None,
+            overridden: [false; 6],
+        }
+    }
+
+    fn write(&mut self, parameters: &MotionParameters, lines: &mut Vec<String>) {
+        let (keyword, global) = match self.parameter {
+            Parameter::Velocity => ("SETVEL", parameters.velocity),
+            Parameter::Acceleration => ("SETACC", parameters.acceleration),
+        };
+        let values: [f32; 6] = std::array::from_fn(|i| {
+            let (velocity, acceleration) = parameters.joint(i as i8 + 1).unwrap_or((parameters.velocity, parameters.acceleration));
+            match self.parameter {
+                Parameter::Velocity => velocity,
+                Parameter::Acceleration => acceleration,
+            }
+        });
+
+        if self.previous == Some((global, values)) {
+            return;
+        }
+        self.previous = Some((global, values));
+
+        lines.push(format!("{}>{};", keyword, format_number(global)));
+        for (overridden, value) in self.overridden.iter_mut().zip(values) {
+            *overridden |= value != global;
+        }
+
+        let joints: String = values
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.overridden[*i])
+            .map(|(i, value)| format!("J{}_{};", i + 1, format_number(*value)))
+            .collect();
+        if !joints.is_empty() {
+            lines.push(format!("{}>{}", keyword, joints));
+        }
+    }
+}
+
+fn check_dwell(dwell: Option<f32>) -> Result<(), String> {
+    match dwell {
+        Some(dwell) if !dwell.is_finite() || dwell < 0.0 => Err(format!("Dwell must be at least 0 ms, got {}", dwell)),
+        _ => Ok(()),
+    }
+}
+
+impl Recording {
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn add(&mut self, waypoint: Waypoint) -> Result<(), String> {
+        check_dwell(waypoint.dwell)?;
+
+        if waypoint.angles.iter().all(|angle| angle.is_none()) {
+            return Err("No joint has a known angle, calibrate the arm first".to_string());
+        }
+
+        self.waypoints.push(waypoint);
+
+        Ok(())
+    }
+
+    fn waypoint_mut(&mut self, index: usize) -> Result<&mut Waypoint, String> {
+        let count = self.waypoints.len();
+        self.waypoints
+            .get_mut(index)
+            .ok_or_else(|| format!("Waypoint {} does not exist, there are {}", index + 1, count))
+    }
+
+    pub fn set_dwell(&mut self, index: usize, dwell: Option<f32>) -> Result<(), String> {
+        check_dwell(dwell)?;
+        self.waypoint_mut(index)?.dwell = dwell;
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Waypoint, String> {
+        self.waypoint_mut(index)?;
+
+        Ok(self.waypoints.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+    }
+
+    // Script driving through the waypoints. Velocity and acceleration are only set when they changed
+    // since the previous waypoint
+    pub fn to_script(&self) -> String {
+        let mut lines = vec![
+            format!("// Recorded in teach mode, {} waypoint(s)", self.waypoints.len()),
+            "// The joints have to be calibrated before running it".to_string(),
+        ];
+        let mut velocity = ParameterWriter::new(Parameter::Velocity);
+        let mut acceleration = ParameterWriter::new(Parameter::Acceleration);
+
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            lines.push(String::new());
+            lines.push(match &waypoint.label {
+                Some(label) => format!("// Waypoint {}: {}", i + 1, label.replace(['\r', '\n'], " ")),
+                None => format!("// Waypoint {}", i + 1),
+            });
+
+            velocity.write(&waypoint.parameters, &mut lines);
+            acceleration.write(&waypoint.parameters, &mut lines);
+
+            let targets: String = waypoint
+                .angles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, angle)| angle.map(|angle| format!("J{}_{};", i + 1, format_number(angle))))
+                .collect();
+            lines.push(format!("MOVE>{}", targets));
+
+            if let Some(dwell) = waypoint.dwell {
+                lines.push(format!("WAIT {}", format_number(dwell)));
+            }
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        if self.waypoints.is_empty() {
+            return Err("Nothing has been recorded".to_string());
+        }
+
+        utils::write_atomic(path, &self.to_script())
+    }
+}
+
+pub type SharedRecording = Arc<Mutex<Recording>>;
+
+// Records where the arm is now, with the velocity and acceleration it moves with
+pub async fn record_waypoint(
+    robot: &Robot,
+    recording: &SharedRecording,
+    dwell: Option<f32>,
+    label: Option<String>,
+) -> Result<Waypoint, String> {
+    let waypoint = Waypoint {
+        angles: utils::to_positive_angles(robot.angles().await?),
+        parameters: robot.parameters().await,
+        dwell,
+        label: label.map(|label| label.trim().to_string()).filter(|label| !label.is_empty()),
+    };
+    recording.lock().await.add(waypoint.clone())?;
+
+    Ok(waypoint)
+}
@@ -0,0 +1,122 @@
+// Host-side model of the stepper positions, so moves do not have to ask the firmware where the
+// joints are first. It follows every commanded move and is checked against each STEPS> reading
+use crate::constants;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// The model stands in for a reading for this long, then the firmware is asked again
+pub const MAX_MODEL_AGE: Duration = Duration::from_secs(10);
+// Difference in steps between the model and a reading tolerated before reporting drift
+const DRIFT_TOLERANCE: i32 = 1;
+
+// A joint the firmware reports somewhere else than the model expected
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionDrift {
+    pub joint: i8,
+    // Absolute steps, `None` when unknown
+    pub expected: Option<i32>,
+    pub reported: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PositionModel {
+    // Absolute steps as the firmware counts them, `None` for joints that are not calibrated
+    steps: [Option<i32>; 6],
+    // Last time the model matched a reading, `None` while it can not be trusted
+    last_reading: Option<Instant>,
+    // Set while a jog moves the steppers without the model following them
+    free_running: bool,
+}
+
+impl PositionModel {
+    pub fn steps(&self) -> [Option<f32>; 6] {
+        self.steps.map(|steps| steps.map(|steps| steps as f32))
+    }
+
+    // Whether the model can be used instead of asking the firmware
+    pub fn is_fresh(&self) -> bool {
+        !self.free_running && self.last_reading.is_some_and(|reading| reading.elapsed() <= MAX_MODEL_AGE)
+    }
+
+    // Follows a relative move of `steps`. Like the firmware, a calibrated joint moving towards its
+    // limit switch stops at 0
+    pub fn apply_move(&mut self, joint: i8, steps: i32) {
+        if !(1..=6).contains(&joint) {
+            return;
+        }
+        let Some(position) = self.steps[(joint - 1) as usize].as_mut() else {
+            return;
+        };
+
+        let positive_to_limit = constants::STEPPER_POSITIVE_TO_LIMIT
+            .get(&(joint as u8))
+            .copied()
+            .unwrap_or(false);
+        let target = *position + steps;
+        let crosses_limit = if positive_to_limit {
+            steps > 0 && target >= 0
+        } else {
+            steps < 0 && target <= 0
+        };
+
+        *position = if crosses_limit { 0 } else { target };
+    }
+
+    // Calibrated joints sit on their limit switch, at 0
+    pub fn set_calibrated(&mut self, joint: i8) {
+        if (1..=6).contains(&joint) {
+            self.steps[(joint - 1) as usize] = Some(0);
+        }
+    }
+
+    // Makes the next reading replace the model without being checked against it
+    pub fn invalidate(&mut self) {
+        self.last_reading = None;
+    }
+
+    pub fn is_free_running(&self) -> bool {
+        self.free_running
+    }
+
+    pub fn set_free_running(&mut self, free_running: bool) {
+        self.free_running = free_running;
+        self.invalidate();
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Adopts a reading from the firmware, returns the joints where it disagrees with the model
+    pub fn reconcile(&mut self, reading: [Option<f32>; 6]) -> Vec<PositionDrift> {
+        let reading = reading.map(|steps| steps.map(|steps| steps.round() as i32));
+        let mut drift = Vec::new();
+
+        if self.last_reading.is_some() && !self.free_running {
+            for (i, (expected, reported)) in self.steps.iter().zip(reading.iter()).enumerate() {
+                let agrees = match (expected, reported) {
+                    (Some(expected), Some(reported)) => (expected - reported).abs() <= DRIFT_TOLERANCE,
+                    // Joints get calibrated by the firmware when a move hits their limit switch
+                    (None, _) => true,
+                    (Some(_), None) => false,
+                };
+
+                if !agrees {
+                    drift.push(PositionDrift {
+                        joint: (i + 1) as i8,
+                        expected: *expected,
+                        reported: *reported,
+                    });
+                }
+            }
+        }
+
+        self.steps = reading;
+        if !self.free_running {
+            self.last_reading = Some(Instant::now());
+        }
+
+        drift
+    }
+}
@@ -0,0 +1,942 @@
+use crate::calibration;
+use crate::constants;
+use crate::events::RobotEvent;
+use crate::parameters::{self, MotionParameters, Parameter};
+use crate::planner::{self, TrajectoryPlan};
+use crate::simulator;
+use crate::state::{SerialTransport, SharedAppState};
+use serialport::available_ports;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::{timeout, Duration},
+};
+use tokio_serial::{DataBits, Parity, SerialPortBuilderExt, StopBits};
+
+pub async fn send_and_receive_from_shared_state(
+    data: &str,
+    state: SharedAppState,
+    opt_timeout: Option<Duration>, // Optional timeout
+) -> Result<String, String> {
+    let timeout_duration = opt_timeout.unwrap_or(Duration::from_secs(3)); // Default to 3 seconds if None
+
+    // Acquire the lock on the shared state
+    let app_state = state.write().await;
+
+    // The firmware only reads the command once the running move is over
+    let timeout_duration = timeout_duration
+        + app_state
+            .motion_end
+            .map(|end| end.saturating_duration_since(Instant::now()))
+            .unwrap_or_default();
+
+    // Check if a serial connection exists
+    let connection = match app_state.serial_connection.as_ref() {
+        Some(conn) => conn.clone(),
+        None => return Err("No serial connection available".to_string()),
+    };
+
+    // Concatenate '~' to the data
+    let data_to_send = format!("{}~", data);
+
+    eprintln!("###DEBUG### - Sending data: {}", data_to_send);
+
+    // Correctly handle the lock on the serial connection
+    let mut port = connection.lock().await;
+
+    // Write the data to the port
+    port.write_all(data_to_send.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to serial port: {}", e))?;
+    port.flush()
+        .await
+        .map_err(|e| format!("Failed to flush serial port: {}", e))?;
+
+    eprintln!("###DEBUG### - Waiting for response...");
+
+    let mut response = Vec::new();
+    let mut buffer = [0; 1024]; // Buffer to read data in chunks
+
+    let read_result = timeout(timeout_duration, async {
+        loop {
+            match port.read(&mut buffer).await {
+                Ok(bytes_read) => {
+                    response.extend_from_slice(&buffer[..bytes_read]);
+
+                    // Check if we have received a complete response
+                    if response.ends_with(b"\n") || response.ends_with(b"~") {
+                        break Ok(());
+                    }
+                }
+                Err(e) => {
+                    break Err(format!("Error reading from serial port: {}", e));
+                }
+            }
+        }
+    })
+    .await;
+
+    match read_result {
+        Ok(Ok(())) => {
+            let response_string = String::from_utf8_lossy(&response).to_string();
+            eprintln!("###DEBUG### - Response obtained: {}", response_string);
+            Ok(response_string)
+        }
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err("Timeout while waiting for response".to_string()),
+    }
+}
+
+pub fn get_ports() -> Vec<String> {
+    let mut ports_list = Vec::new();
+
+    // Attempt to get the list of available serial ports
+    if let Ok(ports) = available_ports() {
+        for port in ports {
+            // Add all detected port names, regardless of type
+            ports_list.push(port.port_name);
+        }
+    } else {
+        eprintln!("Failed to list ports.");
+    }
+
+    ports_list
+}
+
+// Writes `content` to a temporary file next to `path` and renames it over `path`, so a crash never
+// leaves a truncated file behind. Missing parent directories are created
+pub fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    std::fs::write(&temporary_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    std::fs::rename(&temporary_path, path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Opens the serial port, or starts the simulated controller when the simulator port is requested
+fn open_transport(
+    port: &str,
+    baud_rate: u32,
+    timeout_duration: Duration,
+) -> Result<Box<dyn SerialTransport>, String> {
+    if port == constants::SIMULATOR_PORT {
+        return Ok(Box::new(simulator::spawn_simulator()));
+    }
+
+    tokio_serial::new(port, baud_rate)
+        .timeout(timeout_duration)
+        .data_bits(DataBits::Eight)
+        .parity(Parity::None)
+        .stop_bits(StopBits::One)
+        .open_native_async()
+        .map(|serial_connection| Box::new(serial_connection) as Box<dyn SerialTransport>)
+        .map_err(|e| e.to_string())
+}
+
+pub async fn connect_to_port(port: String, state: SharedAppState) -> Result<String, String> {
+    let baud_rate = 115200;
+    let timeout_duration = Duration::from_secs(3);
+    let max_retries = 3;
+
+    for attempt in 1..=max_retries {
+        {
+            // Lock the state and clear any existing connection before trying again
+            let mut app_state = state.write().await;
+            if app_state.serial_connection.is_some() {
+                eprintln!(
+                    "###DEBUG### - Attempt {}/{}: Closing existing connection before reconnecting.",
+                    attempt, max_retries
+                );
+                app_state.serial_connection = None;
+            }
+        }
+
+        eprintln!(
+            "###DEBUG### - Attempt {}/{}: Connecting to port: {}",
+            attempt, max_retries, port
+        );
+
+        match open_transport(&port, baud_rate, timeout_duration) {
+            Ok(serial_connection) => {
+                let shared_connection = Arc::new(Mutex::new(serial_connection));
+
+                {
+                    let mut app_state = state.write().await;
+                    app_state.set_connection(shared_connection.clone());
+                }
+
+                match send_and_receive_from_shared_state(
+                    crate::constants::CommandCodes::CHECK,
+                    state.clone(),
+                    None,
+                )
+                .await
+                {
+                    Ok(response) => {
+                        if response.trim() == crate::constants::ResponseCodes::CONNECTED_RESPONSE {
+                            // The arm starts with the firmware defaults, give it the parameters used for planning
+                            let parameters = state.read().await.motion_parameters();
+                            if let Err(e) = apply_parameters(&parameters, state.clone(), None).await {
+                                eprintln!("###DEBUG### - Failed to set parameters: {}", e);
+                            }
+
+                            // Start the position model from what the firmware reports
+                            state.write().await.positions.reset();
+                            if let Err(e) = get_steppers_steps(state.clone()).await {
+                                eprintln!("###DEBUG### - Failed to read steppers steps: {}", e);
+                            }
+
+                            return Ok(format!("Successfully connected to port: {}.", port));
+                        } else {
+                            eprintln!(
+                                "###DEBUG### - Attempt {}/{}: Unexpected response: {}",
+                                attempt, max_retries, response
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "###DEBUG### - Attempt {}/{}: Failed to verify connection: {}",
+                            attempt, max_retries, e
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "###DEBUG### - Attempt {}/{}: Failed to open serial port: {}",
+                    attempt, max_retries, e
+                );
+            }
+        }
+
+        // Wait before retrying
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Err(format!(
+        "Failed to connect to port: {} after {} attempts.",
+        port, max_retries
+    ))
+}
+
+pub async fn disconnect_from_active_connection(state: SharedAppState) -> Result<String, String> {
+    // Lock the shared app state
+    let mut app_state = state.write().await;
+
+    if app_state.serial_connection.is_some() {
+        eprintln!("###DEBUG### - Disconnecting from serial port.");
+
+        // Explicitly drop the connection
+        app_state.serial_connection = None;
+        app_state.positions.reset();
+        app_state.motion_end = None;
+
+        // Give the OS time to release the port
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        eprintln!("###DEBUG### - Serial port disconnected.");
+        Ok("Successfully disconnected from the port.".to_string())
+    } else {
+        Err("No active serial connection.".to_string())
+    }
+}
+
+// Sets the velocity (degrees/s) or acceleration (degrees/s²) of every joint without an override,
+// or of `joint` alone. `None` removes the override of `joint`
+pub async fn set_parameter(
+    parameter: Parameter,
+    joint: Option<i8>,
+    value: Option<f32>,
+    state: SharedAppState,
+) -> Result<String, String> {
+    let (mut parameters, speed_factor) = {
+        let app_state = state.read().await;
+        (app_state.parameters.clone(), app_state.speed_factor())
+    };
+    parameters.set(parameter, joint, value)?;
+
+    apply_parameters(&parameters.scaled(speed_factor), state.clone(), None).await?;
+    state.write().await.parameters = parameters;
+
+    Ok(format!("Successfully set the {}.", parameter.name().to_lowercase()))
+}
+
+// Runs every motion at `percent` of the configured speed, from `MIN_SPEED_OVERRIDE` to
+// `MAX_SPEED_OVERRIDE`. The steppers are given the slowed down parameters at once
+pub async fn set_speed_override(percent: f32, state: SharedAppState) -> Result<String, String> {
+    if !(parameters::MIN_SPEED_OVERRIDE..=parameters::MAX_SPEED_OVERRIDE).contains(&percent) {
+        return Err(format!(
+            "Speed override must be between {}% and {}%",
+            parameters::MIN_SPEED_OVERRIDE,
+            parameters::MAX_SPEED_OVERRIDE
+        ));
+    }
+
+    let parameters = state.read().await.parameters.scaled(percent / 100.0);
+    apply_parameters(&parameters, state.clone(), None).await?;
+
+    let mut app_state = state.write().await;
+    app_state.speed_override = percent;
+    app_state.emit(RobotEvent::SpeedOverride(percent));
+
+    Ok(format!("Speed override set to {}%.", percent))
+}
+
+// Sets the velocity and acceleration of every stepper, converted to steps/s and steps/s²
+pub async fn apply_parameters(
+    parameters: &MotionParameters,
+    state: SharedAppState,
+    opt_timeout: Option<Duration>,
+) -> Result<(), String> {
+    let mut joints_velocity = String::new();
+    let mut joints_acceleration = String::new();
+
+    for joint in 1..=6 {
+        let (velocity, acceleration) = parameters.steps(joint)?;
+        joints_velocity += &format!("J{}_{:.2};", joint, velocity);
+        joints_acceleration += &format!("J{}_{:.2};", joint, acceleration);
+    }
+
+    send_and_receive_from_shared_state(
+        &format!("{}{}", constants::CommandCodes::SETVEL, joints_velocity),
+        state.clone(),
+        opt_timeout,
+    )
+    .await
+    .map_err(|e| format!("Error setting velocity: {}", e))?;
+    send_and_receive_from_shared_state(
+        &format!("{}{}", constants::CommandCodes::SETACC, joints_acceleration),
+        state,
+        None,
+    )
+    .await
+    .map_err(|e| format!("Error setting acceleration: {}", e))?;
+
+    Ok(())
+}
+
+// Velocity and acceleration of every stepper as the firmware holds them, in steps/s and steps/s².
+// They are read back with the two decimals they are sent with, so they match what was set
+pub async fn get_steppers_parameters(state: SharedAppState) -> Result<[(f32, f32); 6], String> {
+    let response = send_and_receive_from_shared_state(constants::CommandCodes::PARAMS, state, None).await?;
+
+    parse_steppers_parameters(&response)
+}
+
+// Expected response format: "[PARAMS];J1_1200.00_800.00;...;J6_1200.00_800.00;"
+fn parse_steppers_parameters(response: &str) -> Result<[(f32, f32); 6], String> {
+    let parameters_str = response
+        .trim()
+        .strip_prefix(constants::ResponseCodes::PARAMS_RESPONSE)
+        .ok_or_else(|| format!("Invalid parameters response: {}", response.trim()))?;
+
+    let mut parameters = [None; 6];
+
+    for part in parameters_str.split(';').map(str::trim).filter(|part| !part.is_empty()) {
+        let mut fields = part.split('_');
+        let (Some(joint), Some(velocity), Some(acceleration), None) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+            return Err(format!("Invalid parameters of a stepper: {}", part));
+        };
+
+        let index = joint
+            .strip_prefix('J')
+            .and_then(|joint| joint.parse::<usize>().ok())
+            .filter(|joint| (1..=6).contains(joint))
+            .ok_or_else(|| format!("Invalid parameters of a stepper: {}", part))?
+            - 1;
+        let (Ok(velocity), Ok(acceleration)) = (velocity.parse::<f32>(), acceleration.parse::<f32>()) else {
+            return Err(format!("Invalid parameters of a stepper: {}", part));
+        };
+
+        parameters[index] = Some((velocity, acceleration));
+    }
+
+    let mut steppers_parameters = [(0.0, 0.0); 6];
+    for (i, parameters) in parameters.iter().enumerate() {
+        steppers_parameters[i] = parameters.ok_or_else(|| format!("The parameters of J{} are missing", i + 1))?;
+    }
+
+    Ok(steppers_parameters)
+}
+
+pub async fn move_step(
+    joint_index: i8,
+    mut n_steps: i16,
+    state: SharedAppState,
+) -> Result<String, String> {
+    if joint_index <= 0 || joint_index as usize > constants::STEPPER_POSITIVE_TO_LIMIT.len() {
+        return Err("Invalid joint index".to_string());
+    }
+
+    // Convert `joint_index` to `u8` for HashMap lookup
+    let joint_index_u8 = joint_index as u8;
+
+    if constants::STEPPER_POSITIVE_TO_LIMIT[&joint_index_u8] {
+        n_steps = -n_steps;
+    }
+
+    let move_step_command = format!(
+        "{}J{}_{};",
+        constants::CommandCodes::MOVE,
+        joint_index,
+        n_steps
+    );
+
+    // Send movement command
+    match send_and_receive_from_shared_state(&move_step_command, state.clone(), None).await {
+        Ok(response) => {
+            let mut app_state = state.write().await;
+            let duration = planner::plan_trajectory(&[(joint_index, n_steps as i32)], &app_state.motion_parameters())
+                .map(|plan| plan.duration)
+                .unwrap_or(0.0);
+
+            app_state.positions.apply_move(joint_index, n_steps as i32);
+            app_state.motion_end = Some(Instant::now() + Duration::from_secs_f32(duration));
+
+            Ok(format!(
+                "Successfully sent move_step command. Response: {}",
+                response
+            ))
+        }
+        Err(e) => Err(format!("Error: {}", e)),
+    }
+}
+
+pub async fn toggle_stepper(
+    joint_index: i8,
+    enabled: &str,
+    state: SharedAppState,
+) -> Result<String, String> {
+    // Arduino command format: TOGGLE>JOINT_STATE;
+    let toggle_command = format!(
+        "{}J{}_{};",
+        constants::CommandCodes::TOGGLE,
+        joint_index,
+        enabled
+    );
+
+    // Send the command using the shared connection
+    match send_and_receive_from_shared_state(&toggle_command, state, None).await {
+        Ok(response) => Ok(format!(
+            "Successfully sent toggle_step command. Response: {}",
+            response
+        )),
+        Err(e) => Err(format!("Error: {}", e)),
+    }
+}
+
+pub async fn calibrate_steppers(
+    joints_indexes: &[i8],
+    state: SharedAppState,
+) -> Result<String, String> {
+    if let Some(joint) = joints_indexes.iter().find(|joint| !(1..=6).contains(*joint)) {
+        return Err(format!("Invalid Joint: {}", joint));
+    }
+
+    let joint_commands: Vec<String> = joints_indexes
+        .iter()
+        .map(|&index| format!("J{};", index))
+        .collect();
+
+    // Join all joint commands with no separator, and prepend the CALIBRATE> part
+    let calibrate_command = format!(
+        "{}{}",
+        constants::CommandCodes::CALIBRATE,
+        joint_commands.join("")
+    );
+
+    // Send the command using the shared connection
+    //Use a high timeout duration for calibration
+    match send_and_receive_from_shared_state(&calibrate_command, state.clone(), Some(Duration::from_secs(35))).await {
+        Ok(response) => {
+            let mut app_state = state.write().await;
+
+            // Joints that failed are listed instead of OK, the next reading tells where they are
+            if response.trim_end().ends_with("OK") {
+                for joint in joints_indexes {
+                    app_state.positions.set_calibrated(*joint);
+                }
+            } else {
+                app_state.positions.invalidate();
+            }
+
+            // The firmware resets calibrated steppers to its own defaults, give them ours back
+            let parameters = app_state.motion_parameters();
+            drop(app_state);
+            apply_parameters(&parameters, state.clone(), None)
+                .await
+                .map_err(|e| format!("Calibrated, but failed to restore the parameters: {}", e))?;
+
+            Ok(format!(
+                "Successfully sent calibrate command. Response: {}",
+                response
+            ))
+        }
+        Err(e) => Err(format!("Error: {}", e)),
+    }
+}
+
+//Sends state command to arduino and returns an array of bools representing the state of the steppers
+pub async fn get_steppers_state(state: SharedAppState) -> Result<[bool; 6], String> {
+    let data = constants::CommandCodes::STATE;
+    let response = send_and_receive_from_shared_state(data, state, None).await?;
+
+    // Parse the response
+    let state_str = response
+        .trim_start_matches(constants::ResponseCodes::STATE_RESPONSE)
+        .trim_end_matches("~");
+
+    // Split the response into parts
+    let parts: Vec<&str> = state_str.split(';').collect();
+
+    let mut stepper_states = [false; 6];
+
+    for part in parts.iter() {
+        // Strip any trailing newline or extra spaces from each part
+        let part = part.trim();
+
+        if let Some(index) = part
+            .strip_prefix("J")
+            .and_then(|s| s.chars().next())
+            .and_then(|c| c.to_digit(10))
+        {
+            let idx = (index as usize).saturating_sub(1);
+            if idx < 6 {
+                stepper_states[idx] = part.ends_with("ENABLED");
+            }
+        }
+    }
+
+    Ok(stepper_states)
+}
+
+//Sends state command to arduino and returns an array of steps representing the steps of the steppers
+pub async fn get_steppers_steps(state: SharedAppState) -> Result<[Option<f32>; 6], String> {
+    let data = constants::CommandCodes::STEPS;
+    let response = send_and_receive_from_shared_state(data, state.clone(), Some(Duration::from_secs(8))).await?;
+
+    // Parse the response
+    let state_str = response
+        .trim_start_matches(constants::ResponseCodes::STEPS_RESPONSE)
+        .trim_end_matches("~");
+
+    // Split the response into parts
+    let parts: Vec<&str> = state_str.split(';').collect();
+
+    let mut stepper_steps = [None; 6];
+
+    for part in parts.iter() {
+        // Strip any trailing newline or extra spaces from each part
+        let part = part.trim();
+
+        if let Some(index) = part
+            .strip_prefix("J")
+            .and_then(|s| s.chars().next())
+            .and_then(|c| c.to_digit(10))
+        {
+            let idx = (index as usize).saturating_sub(1);
+            if idx < 6 {
+                if part.ends_with("UNKNOWN") {
+                    stepper_steps[idx] = None;
+                } else if let Some(steps) = part
+                    .strip_prefix(&format!("J{}_", index))
+                    .and_then(|s| s.parse::<f32>().ok())
+                {
+                    stepper_steps[idx] = Some(steps);
+                }
+            }
+        }
+    }
+
+    // Every reading is checked against the position model, which then takes it over
+    let mut app_state = state.write().await;
+    let drift = app_state.positions.reconcile(stepper_steps);
+    if !drift.is_empty() {
+        eprintln!("###DEBUG### - Position drift: {:?}", drift);
+        app_state.emit(RobotEvent::PositionDrift(drift));
+    }
+
+    Ok(stepper_steps)
+}
+
+pub async fn get_steppers_angles(state: SharedAppState) -> Result<[Option<f32>; 6], String> {
+    let steps = get_steppers_steps(state.clone()).await?;
+    let zero_offsets = state.read().await.zero_offsets;
+
+    Ok(steps_to_angles(steps, &zero_offsets))
+}
+
+// Steps of the steppers from the position model, asking the firmware when the model is not fresh
+pub async fn get_tracked_steps(state: SharedAppState) -> Result<[Option<f32>; 6], String> {
+    {
+        let app_state = state.read().await;
+        if app_state.positions.is_fresh() {
+            return Ok(app_state.positions.steps());
+        }
+    }
+
+    get_steppers_steps(state).await
+}
+
+pub async fn get_tracked_angles(state: SharedAppState) -> Result<[Option<f32>; 6], String> {
+    let steps = get_tracked_steps(state.clone()).await?;
+    let zero_offsets = state.read().await.zero_offsets;
+
+    Ok(steps_to_angles(steps, &zero_offsets))
+}
+
+// Converts steps reported by the firmware into the angles it would report, measured from the zero
+// of each joint instead of its limit switch. Unknown steps stay unknown
+pub fn steps_to_angles(steps: [Option<f32>; 6], zero_offsets: &[f32; 6]) -> [Option<f32>; 6] {
+    let mut angles = [None; 6];
+
+    for (i, step) in steps.iter().enumerate() {
+        if let Some(steps) = step {
+            if let (Some(reduction_ratio), Some(degrees_per_step)) = (
+                constants::get_reduction_ratio((i + 1) as u8),
+                constants::get_degrees_per_step((i + 1) as u8),
+            ) {
+                // Offsets are positive angles, like the ones `drive_steppers_to_angles` takes
+                let zero_offset = match constants::STEPPER_POSITIVE_TO_LIMIT.get(&((i + 1) as u8)).copied().unwrap_or(false) {
+                    true => -zero_offsets[i],
+                    false => zero_offsets[i],
+                };
+                angles[i] = Some((*steps / reduction_ratio) * degrees_per_step - zero_offset);
+            }
+        }
+    }
+
+    angles
+}
+
+// Converts angles reported by the firmware into the positive angles taken by `drive_steppers_to_angles`
+pub fn to_positive_angles(angles: [Option<f32>; 6]) -> [Option<f32>; 6] {
+    let mut positive_angles = angles;
+
+    for (i, angle) in positive_angles.iter_mut().enumerate() {
+        if constants::STEPPER_POSITIVE_TO_LIMIT.get(&((i + 1) as u8)).copied().unwrap_or(false) {
+            *angle = angle.map(|angle| -angle);
+        }
+    }
+
+    positive_angles
+}
+
+//Assumes all joint angles are provided as positive numbers
+pub async fn plan_steppers_to_angles(
+    joints_angles: Vec<(i8, f32)>,
+    state: SharedAppState,
+) -> Result<TrajectoryPlan, String> {
+    // Get the current angles of the steppers
+    let current_angles = get_tracked_angles(state.clone()).await?;
+    let zero_offsets = state.read().await.zero_offsets;
+
+    let mut joints_steps = Vec::new();
+
+    // Compute the steps of each stepper taking into account current and max angles
+    for (joint_id, target_angle) in joints_angles {
+        if !(1..=6).contains(&joint_id) {
+            return Err(format!("Invalid Joint: {}", joint_id));
+        }
+
+        let joint_index = (joint_id - 1) as usize; // Convert joint ID to array index (1-based to 0-based)
+
+        // Check if the target angle exceeds joint limits
+        calibration::check_joint_angle(joint_id, target_angle, &zero_offsets)?;
+
+        // Adjust angle based on the joint's positive limit switch
+        let target_angle = if constants::STEPPER_POSITIVE_TO_LIMIT.get(&(joint_id as u8)).copied().unwrap_or(false) {
+            -target_angle
+        } else {
+            target_angle
+        };
+
+        // Ensure we have a known current angle
+        let current_angle = match current_angles[joint_index] {
+            Some(angle) => angle,
+            None => return Err(format!("Current angle for J{} is unknown", joint_id)),
+        };
+
+        // Convert angle difference to steps
+        if let (Some(reduction_ratio), Some(degrees_per_step)) = (
+            constants::get_reduction_ratio(joint_id as u8),
+            constants::get_degrees_per_step(joint_id as u8),
+        ) {
+            let steps =
+                ((target_angle - current_angle) * (1.0 / degrees_per_step) * reduction_ratio).round() as i32;
+            joints_steps.push((joint_id, steps));
+        } else {
+            return Err(format!("Invalid Joint: {}", joint_id));
+        }
+    }
+
+    let parameters = state.read().await.motion_parameters();
+
+    planner::plan_trajectory(&joints_steps, &parameters)
+}
+
+// Sends a planned move, see `execute_trajectories`
+pub async fn execute_trajectory(plan: &TrajectoryPlan, state: SharedAppState) -> Result<String, String> {
+    let responses = execute_trajectories(std::slice::from_ref(plan), state).await?;
+
+    if responses.is_empty() {
+        return Ok("No joint needs to move.".to_string());
+    }
+
+    Ok(format!(
+        "Successfully sent move command. Response: {}",
+        responses.concat()
+    ))
+}
+
+// Sends planned moves one after the other, giving each stepper its own velocity and acceleration
+// for every move and restoring the configured parameters once done. Returns the response to every move
+pub async fn execute_trajectories(plans: &[TrajectoryPlan], state: SharedAppState) -> Result<Vec<String>, String> {
+    let plans: Vec<&TrajectoryPlan> = plans.iter().filter(|plan| !plan.joints.is_empty()).collect();
+
+    if plans.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let parameters = state.read().await.motion_parameters();
+    let synchronized = plans.iter().any(|plan| !plan.uses_parameters(&parameters));
+
+    // The firmware only reads the next command once the current move is over, so commands sent
+    // after a move allow for its duration
+    let mut previous_duration = Duration::ZERO;
+    let mut responses = Vec::new();
+
+    for plan in plans {
+        if synchronized {
+            let joints_velocity: String = plan
+                .joints
+                .iter()
+                .map(|profile| format!("J{}_{:.2};", profile.joint, profile.velocity))
+                .collect();
+            let joints_acceleration: String = plan
+                .joints
+                .iter()
+                .map(|profile| format!("J{}_{:.2};", profile.joint, profile.acceleration))
+                .collect();
+
+            send_and_receive_from_shared_state(
+                &format!("{}{}", constants::CommandCodes::SETVEL, joints_velocity),
+                state.clone(),
+                Some(previous_duration + Duration::from_secs(5)),
+            )
+            .await
+            .map_err(|e| format!("Error: {}", e))?;
+            send_and_receive_from_shared_state(
+                &format!("{}{}", constants::CommandCodes::SETACC, joints_acceleration),
+                state.clone(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Error: {}", e))?;
+
+            previous_duration = Duration::ZERO;
+        }
+
+        let move_command: String = plan
+            .joints
+            .iter()
+            .map(|profile| format!("J{}_{};", profile.joint, profile.steps))
+            .collect();
+        let move_command = format!("{}{}", constants::CommandCodes::MOVE, move_command);
+
+        // Send the command using the shared connection
+        let response = send_and_receive_from_shared_state(
+            &move_command,
+            state.clone(),
+            Some(previous_duration + Duration::from_secs(20)),
+        )
+        .await
+        .map_err(|e| format!("Error: {}", e))?;
+
+        {
+            let mut app_state = state.write().await;
+            for profile in &plan.joints {
+                app_state.positions.apply_move(profile.joint, profile.steps);
+            }
+            app_state.motion_end = Some(Instant::now() + Duration::from_secs_f32(plan.duration));
+        }
+
+        responses.push(response);
+        previous_duration = Duration::from_secs_f32(plan.duration);
+    }
+
+    if synchronized {
+        apply_parameters(&parameters, state, Some(previous_duration + Duration::from_secs(5)))
+            .await
+            .map_err(|e| format!("Error restoring parameters: {}", e))?;
+    }
+
+    Ok(responses)
+}
+
+// Steps of a positive degree, negative for joints counting down away from their limit switch
+fn steps_per_positive_degree(joint_id: i8) -> Result<f32, String> {
+    let (Some(reduction_ratio), Some(degrees_per_step)) = (
+        constants::get_reduction_ratio(joint_id as u8),
+        constants::get_degrees_per_step(joint_id as u8),
+    ) else {
+        return Err(format!("Invalid Joint: {}", joint_id));
+    };
+
+    let steps = reduction_ratio / degrees_per_step;
+    Ok(match constants::STEPPER_POSITIVE_TO_LIMIT.get(&(joint_id as u8)).copied().unwrap_or(false) {
+        true => -steps,
+        false => steps,
+    })
+}
+
+// Converts a positive angle from the zero of the joint into the absolute step count reported by the firmware
+pub fn angle_to_steps(joint_id: i8, angle: f32, zero_offsets: &[f32; 6]) -> Result<f32, String> {
+    let steps = steps_per_positive_degree(joint_id)?;
+
+    Ok((angle + zero_offsets[(joint_id - 1) as usize]) * steps)
+}
+
+// Converts an absolute step count reported by the firmware into a positive angle from the zero of the joint
+pub fn steps_to_angle(joint_id: i8, steps: f32, zero_offsets: &[f32; 6]) -> Result<f32, String> {
+    let one_degree_steps = steps_per_positive_degree(joint_id)?;
+
+    Ok(steps / one_degree_steps - zero_offsets[(joint_id - 1) as usize])
+}
+
+// Moves a joint by `delta_degrees` from its current angle, within its range. Returns the angle
+// reached, which is rounded to whole motor steps
+pub async fn jog_joint(joint_id: i8, delta_degrees: f32, state: SharedAppState) -> Result<f32, String> {
+    if !(1..=6).contains(&joint_id) {
+        return Err(format!("Invalid Joint: {}", joint_id));
+    }
+
+    let current_angles = to_positive_angles(get_tracked_angles(state.clone()).await?);
+    let current_angle = current_angles[(joint_id - 1) as usize]
+        .ok_or_else(|| format!("Current angle for J{} is unknown", joint_id))?;
+
+    let target_angle = current_angle + delta_degrees;
+    let (parameters, zero_offsets) = {
+        let app_state = state.read().await;
+        (app_state.motion_parameters(), app_state.zero_offsets)
+    };
+    calibration::check_joint_angle(joint_id, target_angle, &zero_offsets)?;
+
+    let current_steps = angle_to_steps(joint_id, current_angle, &zero_offsets)?.round() as i32;
+    let target_steps = angle_to_steps(joint_id, target_angle, &zero_offsets)?.round() as i32;
+
+    let plan = planner::plan_trajectory(&[(joint_id, target_steps - current_steps)], &parameters)?;
+    execute_trajectory(&plan, state).await?;
+
+    steps_to_angle(joint_id, target_steps as f32, &zero_offsets)
+}
+
+// Positive angles of all joints, for Cartesian moves which need the whole arm calibrated
+pub async fn get_joint_positions(state: SharedAppState) -> Result<[f32; 6], String> {
+    let angles = to_positive_angles(get_tracked_angles(state).await?);
+    let mut positions = [0.0; 6];
+
+    for (i, angle) in angles.iter().enumerate() {
+        positions[i] = angle.ok_or_else(|| format!("J{} must be calibrated for Cartesian moves", i + 1))?;
+    }
+
+    Ok(positions)
+}
+
+// Streams a path of joint angles as consecutive moves, each one synchronized by the planner and
+// lasting at least `min_segment_duration` seconds
+pub async fn execute_joint_path(
+    waypoints: &[[f32; 6]],
+    min_segment_duration: f32,
+    state: SharedAppState,
+) -> Result<String, String> {
+    let current_steps = get_tracked_steps(state.clone()).await?;
+    let mut previous_steps = [0; 6];
+
+    for (i, steps) in current_steps.iter().enumerate() {
+        previous_steps[i] = steps.ok_or_else(|| format!("Current angle for J{} is unknown", i + 1))?.round() as i32;
+    }
+
+    let (parameters, zero_offsets) = {
+        let app_state = state.read().await;
+        (app_state.motion_parameters(), app_state.zero_offsets)
+    };
+
+    // Targets are rounded in absolute steps so rounding errors do not pile up along the path
+    let mut plans = Vec::new();
+    for waypoint in waypoints {
+        let mut joints_steps = Vec::new();
+
+        for (i, angle) in waypoint.iter().enumerate() {
+            let joint_id = (i + 1) as i8;
+            let target_steps = angle_to_steps(joint_id, *angle, &zero_offsets)?.round() as i32;
+
+            joints_steps.push((joint_id, target_steps - previous_steps[i]));
+            previous_steps[i] = target_steps;
+        }
+
+        let mut plan = planner::plan_trajectory(&joints_steps, &parameters)?;
+        plan.stretch(min_segment_duration);
+        plans.push(plan);
+    }
+
+    let responses = execute_trajectories(&plans, state).await?;
+
+    Ok(format!(
+        "Successfully sent {} move segments.",
+        responses.len()
+    ))
+}
+
+pub async fn drive_steppers_to_angles(
+    joints_angles: Vec<(i8, f32)>,
+    state: SharedAppState,
+) -> Result<String, String> {
+    let plan = plan_steppers_to_angles(joints_angles, state.clone()).await?;
+
+    execute_trajectory(&plan, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_are_read_back_per_stepper() {
+        let response = "[PARAMS];J1_1200.50_800.00;J2_10.25_5.00;J3_1.00_2.00;J4_3.00_4.00;J5_5.00_6.00;J6_7.00_8.00;\r\n";
+        let parameters = parse_steppers_parameters(response).unwrap();
+        assert_eq!(parameters[0], (1200.5, 800.0));
+        assert_eq!(parameters[1], (10.25, 5.0));
+        assert_eq!(parameters[5], (7.0, 8.0));
+
+        assert_eq!(
+            parse_steppers_parameters("[PARAMS];J1_1_2;").unwrap_err(),
+            "The parameters of J2 are missing"
+        );
+        assert!(parse_steppers_parameters("[PARAMS];VEL_200;ACC_200;").is_err());
+        assert!(parse_steppers_parameters("[STEPS];J1_0;").is_err());
+    }
+
+    #[test]
+    fn files_are_replaced_in_one_go() {
+        let dir = std::env::temp_dir().join(format!("robert-utils-{}", std::process::id()));
+        let path = dir.join("data").join("positions.json");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join("data").join("positions.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
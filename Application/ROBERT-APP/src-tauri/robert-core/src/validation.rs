@@ -0,0 +1,471 @@
+// Dry run of a script. Joint positions are simulated from a starting pose through every move,
+// and moves are planned with the same planner as the real ones to estimate how long the script
+// takes. Nothing is sent to the arm
+use crate::calibration;
+use crate::constants;
+use crate::parameters::{MotionParameters, Parameter};
+use crate::planner;
+use crate::positions::PositionStore;
+use crate::robot::Robot;
+use crate::script::{self, Expression, Instruction, ParameterValues, ParsedStatement, Script, Statement};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Statements simulated before giving up, so a huge REPEAT cannot hang the validation
+const MAX_SIMULATED_STATEMENTS: usize = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    // The line fails or does something it should not when run
+    Error,
+    // The line runs but likely not as intended, or could not be checked
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub line: usize,
+    // Included file the line is in, `None` for the script itself
+    pub file: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// Pose the simulation starts from, angles as positive numbers from the joint zero and `None` for
+// joints that are not calibrated
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartPose {
+    pub angles: [Option<f32>; 6],
+    pub enabled: [bool; 6],
+}
+
+impl Default for StartPose {
+    // The arm right after calibrating every joint
+    fn default() -> Self {
+        Self {
+            angles: [Some(0.0); 6],
+            enabled: [true; 6],
+        }
+    }
+}
+
+impl StartPose {
+    // Pose the arm is in now
+    pub async fn current(robot: &Robot) -> Result<Self, String> {
+        Ok(Self {
+            angles: utils::to_positive_angles(robot.angles().await?),
+            enabled: robot.steppers_state().await?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    // In the order the lines first run
+    pub diagnostics: Vec<Diagnostic>,
+    // Seconds spent moving and waiting. Calibrations, arcs and the time to send commands are left out
+    pub estimated_duration: f32,
+    // Angles the arm ends at, `None` where they cannot be known
+    pub final_angles: [Option<f32>; 6],
+}
+
+// Index of J1 to J6, scripts can name any joint
+fn joint_index(joint: i8) -> Option<usize> {
+    (1..=6).contains(&joint).then(|| (joint - 1) as usize)
+}
+
+#[derive(Clone, Copy)]
+struct SimulatedJoint {
+    calibrated: bool,
+    enabled: bool,
+    // `None` when calibrated but the angle cannot be simulated, e.g. after an ARC
+    angle: Option<f32>,
+}
+
+struct Simulation<'a> {
+    script: &'a Script,
+    positions: Option<&'a PositionStore>,
+    parameters: MotionParameters,
+    speed_factor: f32,
+    zero_offsets: [f32; 6],
+    joints: [SimulatedJoint; 6],
+    variables: HashMap<String, f32>,
+    duration: f32,
+    diagnostics: Vec<Diagnostic>,
+    statements: usize,
+}
+
+impl Simulation<'_> {
+    fn report(&mut self, parsed: &ParsedStatement, severity: Severity, message: String) {
+        let diagnostic = Diagnostic {
+            line: parsed.line,
+            file: parsed.file.as_deref().map(str::to_string),
+            severity,
+            message,
+        };
+
+        // Lines in REPEATs and subroutines run more than once, report them once
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn evaluate(&mut self, parsed: &ParsedStatement, expression: &Expression) -> Option<f32> {
+        match expression.evaluate(&self.variables) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report(parsed, Severity::Error, e);
+                None
+            }
+        }
+    }
+
+    // Simulates `statements`, `false` once the statement limit is reached
+    fn run(&mut self, statements: &[ParsedStatement]) -> bool {
+        for parsed in statements {
+            self.statements += 1;
+            if self.statements > MAX_SIMULATED_STATEMENTS {
+                self.report(
+                    parsed,
+                    Severity::Warning,
+                    format!("Simulation stopped after {} statements, the rest is not checked", MAX_SIMULATED_STATEMENTS),
+                );
+                return false;
+            }
+
+            if !self.statement(parsed) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn statement(&mut self, parsed: &ParsedStatement) -> bool {
+        match &parsed.statement {
+            Statement::Move(joints_angles) => {
+                let targets: Option<Vec<(i8, f32)>> = joints_angles
+                    .iter()
+                    .map(|(joint, angle)| Some((*joint, self.evaluate(parsed, angle)?)))
+                    .collect();
+                if let Some(targets) = targets {
+                    self.simulate_move(parsed, &targets);
+                }
+            }
+            Statement::Instruction(instruction) => self.instruction(parsed, instruction),
+            Statement::Wait(milliseconds) => {
+                if let Some(milliseconds) = self.evaluate(parsed, milliseconds) {
+                    match script::wait_duration(milliseconds) {
+                        Ok(duration) => self.duration += duration.as_secs_f32(),
+                        Err(e) => self.report(parsed, Severity::Error, e),
+                    }
+                }
+            }
+            Statement::Let(name, value) => {
+                if let Some(value) = self.evaluate(parsed, value) {
+                    self.variables.insert(name.clone(), value);
+                }
+            }
+            Statement::Repeat(count, body) => {
+                let Some(count) = self.evaluate(parsed, count) else {
+                    return true;
+                };
+                if count < 0.0 || count.fract() != 0.0 {
+                    self.report(
+                        parsed,
+                        Severity::Error,
+                        format!("REPEAT count must be a whole number of at least 0, got {}", count),
+                    );
+                    return true;
+                }
+                for _ in 0..count as u32 {
+                    if !self.run(body) {
+                        return false;
+                    }
+                }
+            }
+            Statement::GotoPos(name) => match self.positions.map(|positions| positions.get(name)) {
+                Some(Ok(position)) => {
+                    let targets = position.joints_angles();
+                    self.simulate_move(parsed, &targets);
+                }
+                Some(Err(e)) => self.report(parsed, Severity::Error, e),
+                None => {
+                    self.report(parsed, Severity::Warning, format!("Position '{}' is not checked, no stored positions are available", name));
+                    self.forget_angles();
+                }
+            },
+            Statement::Home => {
+                let targets: Vec<(i8, f32)> = (1..=6)
+                    .filter(|joint| self.joints[(*joint - 1) as usize].calibrated)
+                    .map(|joint| (joint, 0.0))
+                    .collect();
+                if targets.is_empty() {
+                    self.report(parsed, Severity::Error, "HOME moves calibrated joints to 0° and none is calibrated".to_string());
+                } else {
+                    self.simulate_move(parsed, &targets);
+                }
+            }
+            Statement::Call(name, args) => {
+                let Some(subroutine) = self.script.subroutines.get(name) else {
+                    self.report(parsed, Severity::Error, format!("Unknown subroutine {}", name));
+                    return true;
+                };
+                let Some(args) = args.iter().map(|arg| self.evaluate(parsed, arg)).collect::<Option<Vec<_>>>() else {
+                    return true;
+                };
+
+                let mut variables = self.variables.clone();
+                variables.extend(subroutine.params.iter().cloned().zip(args));
+                let caller_variables = std::mem::replace(&mut self.variables, variables);
+                let completed = self.run(&subroutine.body);
+                self.variables = caller_variables;
+
+                return completed;
+            }
+            Statement::Include(_, statements) => return self.run(statements),
+        }
+
+        true
+    }
+
+    fn instruction(&mut self, parsed: &ParsedStatement, instruction: &Instruction) {
+        match instruction {
+            Instruction::Move(targets) => self.simulate_move(parsed, targets),
+            Instruction::Toggle(joints_states) => {
+                for (joint, state) in joints_states {
+                    match joint_index(*joint) {
+                        Some(index) => self.joints[index].enabled = state == "ENABLED",
+                        None => self.report(parsed, Severity::Error, format!("Invalid Joint: {}", joint)),
+                    }
+                }
+            }
+            Instruction::Calibrate(joints) => {
+                for joint in joints {
+                    match joint_index(*joint) {
+                        // Calibration leaves the joint on its switch, its offset below its zero
+                        Some(index) => {
+                            self.joints[index].calibrated = true;
+                            self.joints[index].angle = Some(-self.zero_offsets[index]);
+                        }
+                        None => self.report(parsed, Severity::Error, format!("Invalid Joint: {}", joint)),
+                    }
+                }
+            }
+            Instruction::SetVel(values) => self.set_parameter(parsed, Parameter::Velocity, values),
+            Instruction::SetAcc(values) => self.set_parameter(parsed, Parameter::Acceleration, values),
+            Instruction::Linear { .. } | Instruction::Arc { .. } => {
+                let keyword = if matches!(instruction, Instruction::Arc { .. }) { "ARC" } else { "LIN" };
+                self.report(
+                    parsed,
+                    Severity::Warning,
+                    format!("{} is not simulated, the joint angles after it are unknown", keyword),
+                );
+                self.forget_angles();
+            }
+        }
+    }
+
+    fn set_parameter(&mut self, parsed: &ParsedStatement, parameter: Parameter, values: &ParameterValues) {
+        let result = match values {
+            ParameterValues::Global(value) => self.parameters.set(parameter, None, Some(*value)),
+            ParameterValues::Joints(joints_values) => joints_values
+                .iter()
+                .try_for_each(|(joint, value)| self.parameters.set(parameter, Some(*joint), Some(*value))),
+        };
+
+        if let Err(e) = result {
+            self.report(parsed, Severity::Error, e);
+        }
+    }
+
+    // Calibrated joints keep being calibrated, only where they are is lost
+    fn forget_angles(&mut self) {
+        for joint in self.joints.iter_mut() {
+            joint.angle = None;
+        }
+    }
+
+    fn simulate_move(&mut self, parsed: &ParsedStatement, targets: &[(i8, f32)]) {
+        let mut joints_steps = Vec::new();
+        let mut all_known = true;
+        let mut failed = false;
+
+        for &(joint, target) in targets {
+            let (Some(simulated), Some(steps_per_degree)) = (
+                joint_index(joint).map(|index| self.joints[index]),
+                constants::get_steps_per_degree(joint as u8),
+            ) else {
+                self.report(parsed, Severity::Error, format!("Invalid Joint: {}", joint));
+                failed = true;
+                continue;
+            };
+
+            if let Err(e) = calibration::check_joint_angle(joint, target, &self.zero_offsets) {
+                self.report(parsed, Severity::Error, e);
+                failed = true;
+                continue;
+            }
+            if !simulated.calibrated {
+                self.report(parsed, Severity::Error, format!("J{} is not calibrated", joint));
+                failed = true;
+                continue;
+            }
+            if !simulated.enabled {
+                self.report(parsed, Severity::Error, format!("J{} is disabled", joint));
+            }
+
+            match simulated.angle {
+                Some(angle) => joints_steps.push((joint, ((target - angle) * steps_per_degree).round() as i32)),
+                None => all_known = false,
+            }
+            self.joints[(joint - 1) as usize].angle = Some(target);
+        }
+
+        if failed || !all_known {
+            return;
+        }
+
+        if joints_steps.iter().all(|(_, steps)| *steps == 0) {
+            self.report(parsed, Severity::Warning, "Move does not change any joint".to_string());
+            return;
+        }
+
+        match planner::plan_trajectory(&joints_steps, &self.parameters.scaled(self.speed_factor)) {
+            Ok(plan) => self.duration += plan.duration,
+            Err(e) => self.report(parsed, Severity::Error, e),
+        }
+    }
+}
+
+// Simulates `script` from `start` with the given parameters and speed override, in percent. Joint
+// ranges are checked from the zeros set by `zero_offsets`. GOTO_POS is only checked when
+// `positions` are given
+pub fn validate_script(
+    script: &Script,
+    start: &StartPose,
+    parameters: &MotionParameters,
+    speed_override: f32,
+    zero_offsets: &[f32; 6],
+    positions: Option<&PositionStore>,
+) -> ValidationReport {
+    let mut joints = [SimulatedJoint {
+        calibrated: false,
+        enabled: false,
+        angle: None,
+    }; 6];
+    for (i, joint) in joints.iter_mut().enumerate() {
+        joint.calibrated = start.angles[i].is_some();
+        joint.enabled = start.enabled[i];
+        joint.angle = start.angles[i];
+    }
+
+    let mut simulation = Simulation {
+        script,
+        positions,
+        parameters: parameters.clone(),
+        speed_factor: speed_override / 100.0,
+        zero_offsets: *zero_offsets,
+        joints,
+        variables: HashMap::new(),
+        duration: 0.0,
+        diagnostics: Vec::new(),
+        statements: 0,
+    };
+    simulation.run(&script.statements);
+
+    ValidationReport {
+        diagnostics: simulation.diagnostics,
+        estimated_duration: simulation.duration,
+        final_angles: simulation.joints.map(|joint| joint.angle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(content: &str, start: &StartPose, zero_offsets: &[f32; 6]) -> ValidationReport {
+        let script = script::parse_script(content).unwrap();
+        validate_script(&script, start, &MotionParameters::default(), 100.0, zero_offsets, None)
+    }
+
+    fn messages(report: &ValidationReport) -> Vec<(usize, Severity, &str)> {
+        report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.severity, diagnostic.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn targets_out_of_range_are_errors() {
+        let report = validate("MOVE>J2_120;\nMOVE>J5_-10;", &StartPose::default(), &[0.0; 6]);
+        assert_eq!(
+            messages(&report),
+            [
+                (1, Severity::Error, "J2 would reach 120.0°, outside its range of 0° to 100°"),
+                (2, Severity::Error, "J5 would reach -10.0°, outside its range of 0° to 45°")
+            ]
+        );
+
+        // Below the zero is reachable down to the switch
+        let report = validate("MOVE>J5_-10;", &StartPose::default(), &[0.0, 0.0, 0.0, 0.0, 15.0, 0.0]);
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.final_angles[4], Some(-10.0));
+    }
+
+    #[test]
+    fn uncalibrated_joints_cannot_move() {
+        let start = StartPose {
+            angles: [Some(0.0), None, Some(0.0), Some(0.0), Some(0.0), Some(0.0)],
+            enabled: [true; 6],
+        };
+
+        let report = validate("MOVE>J2_10;", &start, &[0.0; 6]);
+        assert_eq!(messages(&report), [(1, Severity::Error, "J2 is not calibrated")]);
+        assert_eq!(report.final_angles[1], None);
+
+        let report = validate("CALIBRATE>J2;\nMOVE>J2_10;", &start, &[0.0; 6]);
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.final_angles[1], Some(10.0));
+    }
+
+    #[test]
+    fn disabled_joints_are_reported() {
+        let report = validate("TOGGLE>J3_DISABLED;\nMOVE>J3_10;\nTOGGLE>J3_ENABLED;\nMOVE>J3_20;", &StartPose::default(), &[0.0; 6]);
+
+        assert_eq!(messages(&report), [(2, Severity::Error, "J3 is disabled")]);
+        assert_eq!(report.final_angles[2], Some(20.0));
+    }
+
+    #[test]
+    fn moves_that_change_nothing_are_warnings() {
+        let report = validate("MOVE>J1_0;\nMOVE>J1_10;\nMOVE>J1_10;J2_0;", &StartPose::default(), &[0.0; 6]);
+
+        assert_eq!(
+            messages(&report),
+            [
+                (1, Severity::Warning, "Move does not change any joint"),
+                (3, Severity::Warning, "Move does not change any joint")
+            ]
+        );
+        assert!(report.estimated_duration > 0.0);
+    }
+
+    #[test]
+    fn repeated_lines_are_reported_once() {
+        let report = validate("REPEAT 3 {\nMOVE>J2_120;\nWAIT 100\n}", &StartPose::default(), &[0.0; 6]);
+
+        assert_eq!(
+            messages(&report),
+            [(2, Severity::Error, "J2 would reach 120.0°, outside its range of 0° to 100°")]
+        );
+        assert!((report.estimated_duration - 0.3).abs() < 1e-4);
+    }
+}
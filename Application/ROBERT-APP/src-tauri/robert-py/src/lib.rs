@@ -0,0 +1,375 @@
+// Python bindings over robert-core, going through the same `Robot` calls as the Tauri commands
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use robert_core::calibration;
+use robert_core::cartesian::{CartesianAxis, JogFrame};
+use robert_core::history::{self, RunHistory};
+use robert_core::homing::{self, HomingConfig};
+use robert_core::interpreter::Interpreter;
+use robert_core::jog::JogTarget;
+use robert_core::kinematics::CartesianPose;
+use robert_core::positions::PositionStore;
+use robert_core::validation::{Severity, StartPose};
+use robert_core::{constants, script, Robot};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+
+create_exception!(robert, RobotError, PyException);
+
+#[pyclass(name = "Robot")]
+struct PyRobot {
+    robot: Robot,
+    runtime: Runtime,
+}
+
+impl PyRobot {
+    // Runs a robot call to completion, releasing the GIL while waiting on the arm
+    fn block_on<T, F>(&self, py: Python<'_>, future: F) -> PyResult<T>
+    where
+        T: Send,
+        F: Future<Output = Result<T, String>> + Send,
+    {
+        py.allow_threads(|| self.runtime.block_on(future))
+            .map_err(RobotError::new_err)
+    }
+}
+
+#[pymethods]
+impl PyRobot {
+    #[new]
+    fn new() -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| RobotError::new_err(format!("Failed to start runtime: {}", e)))?;
+
+        Ok(Self {
+            robot: Robot::new(),
+            runtime,
+        })
+    }
+
+    /// List available serial ports
+    #[staticmethod]
+    fn ports() -> Vec<String> {
+        Robot::ports()
+    }
+
+    /// Connect to the arm on `port`, use `robert.SIMULATOR_PORT` for the simulated controller
+    fn connect(&self, py: Python<'_>, port: &str) -> PyResult<String> {
+        self.block_on(py, self.robot.connect(port))
+    }
+
+    fn disconnect(&self, py: Python<'_>) -> PyResult<String> {
+        self.block_on(py, self.robot.disconnect())
+    }
+
+    fn is_connected(&self, py: Python<'_>) -> bool {
+        py.allow_threads(|| self.runtime.block_on(self.robot.is_connected()))
+    }
+
+    /// Drive joints to absolute angles, e.g. `robot.move_joints({1: 45.0, 2: 10.0})`
+    fn move_joints(&self, py: Python<'_>, joints: BTreeMap<i8, f32>) -> PyResult<String> {
+        self.block_on(py, self.robot.move_joints(joints.into_iter().collect()))
+    }
+
+    /// Move the tool along a straight line to a pose in mm and degrees, at up to `speed` mm/s,
+    /// capped at and defaulting to 100 mm/s. Every joint must be calibrated
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (x, y, z, roll, pitch, yaw, speed = None))]
+    fn move_linear(
+        &self,
+        py: Python<'_>,
+        x: f32,
+        y: f32,
+        z: f32,
+        roll: f32,
+        pitch: f32,
+        yaw: f32,
+        speed: Option<f32>,
+    ) -> PyResult<String> {
+        let target = CartesianPose { x, y, z, roll, pitch, yaw };
+        self.block_on(py, self.robot.move_linear(target, speed))
+    }
+
+    /// Jog the tool by `increment` mm or degrees along or around `axis` ("x" to "z", "roll", "pitch"
+    /// or "yaw") of the "base" or "tool" frame. Returns the new tool pose
+    #[pyo3(signature = (axis, increment, frame = "base"))]
+    fn jog_cartesian(
+        &self,
+        py: Python<'_>,
+        axis: &str,
+        increment: f32,
+        frame: &str,
+    ) -> PyResult<(f32, f32, f32, f32, f32, f32)> {
+        let axis = axis.parse::<CartesianAxis>().map_err(RobotError::new_err)?;
+        let frame = frame.parse::<JogFrame>().map_err(RobotError::new_err)?;
+        let pose = self.block_on(py, self.robot.jog_cartesian(axis, frame, increment))?;
+        Ok((pose.x, pose.y, pose.z, pose.roll, pose.pitch, pose.yaw))
+    }
+
+    /// Returns the tool pose as `(x, y, z, roll, pitch, yaw)`
+    fn tool_pose(&self, py: Python<'_>) -> PyResult<(f32, f32, f32, f32, f32, f32)> {
+        let pose = self.block_on(py, self.robot.tool_pose())?;
+        Ok((pose.x, pose.y, pose.z, pose.roll, pose.pitch, pose.yaw))
+    }
+
+    /// Move a joint by a number of motor steps
+    fn jog(&self, py: Python<'_>, joint: i8, steps: i16) -> PyResult<String> {
+        self.block_on(py, self.robot.jog(joint, steps))
+    }
+
+    /// Move a joint by an angle in degrees, returns the angle reached
+    fn jog_joint(&self, py: Python<'_>, joint: i8, degrees: f32) -> PyResult<f32> {
+        self.block_on(py, self.robot.jog_joint(joint, degrees))
+    }
+
+    /// Start a continuous jog of `target` ("J1" to "J6" or an axis) in `direction` (1 or -1) at
+    /// `speed` degrees/s, or mm/s along x, y and z. Call `heartbeat_jog` at least every 0.5 s to
+    /// keep it moving and `stop_jog` to stop it
+    #[pyo3(signature = (target, direction, speed, frame = "base"))]
+    fn start_jog(&self, py: Python<'_>, target: &str, direction: i8, speed: f32, frame: &str) -> PyResult<()> {
+        let target = target.parse::<JogTarget>().map_err(RobotError::new_err)?;
+        let frame = frame.parse::<JogFrame>().map_err(RobotError::new_err)?;
+        self.block_on(py, self.robot.start_jog(target, direction, speed, frame))
+    }
+
+    /// Keep the running jog alive, returns whether there is one
+    fn heartbeat_jog(&self) -> bool {
+        self.robot.heartbeat_jog()
+    }
+
+    /// Stop the running jog, returns once the arm is at rest
+    fn stop_jog(&self, py: Python<'_>) -> PyResult<()> {
+        self.block_on(py, async {
+            self.robot.stop_jog().await;
+            Ok(())
+        })
+    }
+
+    /// Disable every stepper, stopping a running jog
+    fn emergency_stop(&self, py: Python<'_>) -> PyResult<String> {
+        self.block_on(py, self.robot.emergency_stop())
+    }
+
+    fn calibrate(&self, py: Python<'_>, joints: Vec<i8>) -> PyResult<String> {
+        self.block_on(py, self.robot.calibrate(&joints))
+    }
+
+    /// Calibrate `joints`, every joint when `None`, in the order from the homing `config` file and
+    /// end in the home pose `pose` or the configured one
+    #[pyo3(signature = (joints = None, pose = None, config = None))]
+    fn home(
+        &self,
+        py: Python<'_>,
+        joints: Option<Vec<i8>>,
+        pose: Option<String>,
+        config: Option<PathBuf>,
+    ) -> PyResult<String> {
+        let config = config
+            .map(|path| HomingConfig::load(&path))
+            .transpose()
+            .map_err(RobotError::new_err)?
+            .unwrap_or_default();
+
+        self.block_on(py, homing::home(&self.robot, &config, &joints.unwrap_or_default(), pose.as_deref()))
+    }
+
+    /// Degrees from the limit switch to the zero of each joint, angles are measured from the zeros
+    fn zero_offsets(&self, py: Python<'_>) -> PyResult<Vec<f32>> {
+        Ok(self.block_on(py, async { Ok(self.robot.zero_offsets().await) })?.to_vec())
+    }
+
+    /// Set the zero offsets of the six joints, or load them from the file at `path`
+    #[pyo3(signature = (offsets = None, path = None))]
+    fn set_zero_offsets(&self, py: Python<'_>, offsets: Option<[f32; 6]>, path: Option<PathBuf>) -> PyResult<()> {
+        let offsets = match (offsets, path) {
+            (Some(offsets), None) => offsets,
+            (None, Some(path)) => calibration::load_zero_offsets(&path).map_err(RobotError::new_err)?,
+            _ => return Err(RobotError::new_err("Pass either offsets or path")),
+        };
+
+        self.block_on(py, self.robot.set_zero_offsets(offsets))
+    }
+
+    /// Make where `joint` is now its zero, once jogged to its zero mark. Returns the offset and
+    /// saves all of them to `path` when given
+    #[pyo3(signature = (joint, path = None))]
+    fn set_zero_here(&self, py: Python<'_>, joint: i8, path: Option<PathBuf>) -> PyResult<f32> {
+        let offset = self.block_on(py, self.robot.set_zero_here(joint))?;
+
+        if let Some(path) = path {
+            let offsets = self.block_on(py, async { Ok(self.robot.zero_offsets().await) })?;
+            calibration::save_zero_offsets(&path, &offsets).map_err(RobotError::new_err)?;
+        }
+
+        Ok(offset)
+    }
+
+    fn toggle(&self, py: Python<'_>, joint: i8, enabled: bool) -> PyResult<String> {
+        let enabled = if enabled { "ENABLED" } else { "DISABLED" };
+        self.block_on(py, self.robot.toggle(joint, enabled))
+    }
+
+    /// Velocity in degrees/s of every joint without an override
+    fn set_velocity(&self, py: Python<'_>, velocity: f32) -> PyResult<String> {
+        self.block_on(py, self.robot.set_velocity(velocity))
+    }
+
+    /// Acceleration in degrees/s² of every joint without an override
+    fn set_acceleration(&self, py: Python<'_>, acceleration: f32) -> PyResult<String> {
+        self.block_on(py, self.robot.set_acceleration(acceleration))
+    }
+
+    /// `None` makes the joint follow the global velocity again
+    #[pyo3(signature = (joint, velocity = None))]
+    fn set_joint_velocity(&self, py: Python<'_>, joint: i8, velocity: Option<f32>) -> PyResult<String> {
+        self.block_on(py, self.robot.set_joint_velocity(joint, velocity))
+    }
+
+    /// `None` makes the joint follow the global acceleration again
+    #[pyo3(signature = (joint, acceleration = None))]
+    fn set_joint_acceleration(&self, py: Python<'_>, joint: i8, acceleration: Option<f32>) -> PyResult<String> {
+        self.block_on(py, self.robot.set_joint_acceleration(joint, acceleration))
+    }
+
+    /// Runs every motion at `percent` of the configured speed, from 1 to 100
+    fn set_speed_override(&self, py: Python<'_>, percent: f32) -> PyResult<String> {
+        self.block_on(py, self.robot.set_speed_override(percent))
+    }
+
+    fn speed_override(&self, py: Python<'_>) -> PyResult<f32> {
+        self.block_on(py, async { Ok(self.robot.speed_override().await) })
+    }
+
+    /// Returns the configured global `(velocity, acceleration)`
+    fn parameters(&self, py: Python<'_>) -> PyResult<(f32, f32)> {
+        let parameters = self.block_on(py, async { Ok(self.robot.parameters().await) })?;
+        Ok((parameters.velocity, parameters.acceleration))
+    }
+
+    /// Returns the `(velocity, acceleration)` a joint runs with, its overrides included
+    fn joint_parameters(&self, py: Python<'_>, joint: i8) -> PyResult<(f32, f32)> {
+        let parameters = self.block_on(py, async { Ok(self.robot.parameters().await) })?;
+        parameters.joint(joint).map_err(RobotError::new_err)
+    }
+
+    /// Reads back the `(velocity, acceleration)` each joint runs with from the firmware, speed
+    /// override included
+    fn read_parameters(&self, py: Python<'_>) -> PyResult<Vec<(f32, f32)>> {
+        let parameters = self.block_on(py, self.robot.read_parameters())?;
+        Ok(parameters.iter().map(|joint| (joint.velocity, joint.acceleration)).collect())
+    }
+
+    /// Returns whether each stepper is enabled
+    fn steppers_state(&self, py: Python<'_>) -> PyResult<Vec<bool>> {
+        Ok(self.block_on(py, self.robot.steppers_state())?.to_vec())
+    }
+
+    /// Returns the joint angles in degrees, `None` for joints that are not calibrated
+    fn angles(&self, py: Python<'_>) -> PyResult<Vec<Option<f32>>> {
+        Ok(self.block_on(py, self.robot.angles())?.to_vec())
+    }
+
+    /// Run a .rob script, returns the `(line, response)` of every executed instruction. GOTO_POS
+    /// reads from the `positions` file. With a `history` file the run is recorded in it with `note`
+    #[pyo3(signature = (path, positions = None, history = None, note = None))]
+    fn run_script(
+        &self,
+        py: Python<'_>,
+        path: &str,
+        positions: Option<PathBuf>,
+        history: Option<PathBuf>,
+        note: Option<String>,
+    ) -> PyResult<Vec<(usize, String)>> {
+        let script = script::load_script(Path::new(path)).map_err(RobotError::new_err)?;
+        let positions = positions
+            .map(|path| PositionStore::load(path).map(|store| Arc::new(Mutex::new(store))))
+            .transpose()
+            .map_err(RobotError::new_err)?;
+        let history = history
+            .map(|path| RunHistory::load(path).map(|history| Arc::new(Mutex::new(history))))
+            .transpose()
+            .map_err(RobotError::new_err)?;
+
+        self.block_on(py, async {
+            let mut responses = Vec::new();
+            let mut interpreter = Interpreter::new(self.robot.clone(), positions);
+            let on_response = |line, response| responses.push((line, response));
+
+            match history {
+                Some(history) => {
+                    let content =
+                        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                    let name = Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+                    history::run_recorded(&mut interpreter, &script, &name, &content, note, &history, on_response)
+                        .await?
+                }
+                None => interpreter.run(&script, on_response).await?,
+            }
+            Ok(responses)
+        })
+    }
+
+    /// Check a .rob script without moving the arm, starting from where the arm is or, when not
+    /// connected, from every joint calibrated at 0°. Returns the `(line, file, severity, message)`
+    /// of every problem and the estimated duration in seconds
+    #[pyo3(signature = (path, positions = None))]
+    #[allow(clippy::type_complexity)]
+    fn validate_script(
+        &self,
+        py: Python<'_>,
+        path: &str,
+        positions: Option<PathBuf>,
+    ) -> PyResult<(Vec<(usize, Option<String>, String, String)>, f32)> {
+        let script = script::load_script(Path::new(path)).map_err(RobotError::new_err)?;
+        let positions = positions.map(PositionStore::load).transpose().map_err(RobotError::new_err)?;
+
+        self.block_on(py, async {
+            let start = match self.robot.is_connected().await {
+                true => StartPose::current(&self.robot).await?,
+                false => StartPose::default(),
+            };
+            let report = self.robot.validate_script(&script, &start, positions.as_ref()).await;
+            let diagnostics = report
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| {
+                    let severity = match diagnostic.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    };
+                    (diagnostic.line, diagnostic.file, severity.to_string(), diagnostic.message)
+                })
+                .collect();
+            Ok((diagnostics, report.estimated_duration))
+        })
+    }
+
+    /// Call `callback(name, payload)` for every event reported by the robot, payload is a JSON string
+    fn on_event(&self, callback: PyObject) {
+        let _guard = self.runtime.enter();
+
+        self.robot.on_event(move |event| {
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            Python::with_gil(|py| {
+                if let Err(e) = callback.call1(py, (event.name(), payload)) {
+                    e.print(py);
+                }
+            });
+        });
+    }
+}
+
+#[pymodule]
+fn robert(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRobot>()?;
+    m.add("RobotError", m.py().get_type::<RobotError>())?;
+    m.add("SIMULATOR_PORT", constants::SIMULATOR_PORT)?;
+    Ok(())
+}
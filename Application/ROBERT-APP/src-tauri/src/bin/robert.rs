@@ -0,0 +1,805 @@
+// Command line interface to drive the arm without the desktop app
+use clap::{Parser, Subcommand};
+use robert_core::calibration;
+use robert_core::cartesian::{CartesianAxis, JogFrame};
+use robert_core::constants::SteppersAngles;
+use robert_core::gamepad::{self, GamepadMapping};
+use robert_core::gcode::{self, GcodeConfig, GcodeImport};
+use robert_core::history::{self, ExportFormat, RunHistory};
+use robert_core::homing::{self, HomingConfig, HomingProgress, HomingStage};
+use robert_core::interpreter::Interpreter;
+use robert_core::jog::JogTarget;
+use robert_core::kinematics::CartesianPose;
+use robert_core::parameters::MotionParameters;
+use robert_core::positions::PositionStore;
+use robert_core::validation::{Diagnostic, Severity, StartPose};
+use robert_core::{script, utils, Robot, RobotEvent};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "robert", version, about = "Control the R.O.B.E.R.T arm from the command line")]
+struct Cli {
+    /// Serial port the arm is connected to
+    #[arg(short, long, global = true, env = "ROBERT_PORT")]
+    port: Option<String>,
+
+    /// File with the zero offsets of the joints, angles are measured from the zeros in it
+    #[arg(long, global = true, env = "ROBERT_ZERO_OFFSETS")]
+    zero_offsets: Option<PathBuf>,
+
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List available serial ports
+    Ports,
+    /// Check the connection with the arm
+    Connect,
+    /// Drive joints to absolute angles, e.g. `move J1=45 J2=10`
+    Move {
+        #[arg(required = true, value_parser = parse_joint_angle)]
+        joints: Vec<(i8, f32)>,
+    },
+    /// Move the tool along a straight line, e.g. `movel z=150 pitch=90`. Axes left out keep their
+    /// current value
+    #[command(allow_negative_numbers = true)]
+    Movel {
+        #[arg(required = true, value_parser = parse_pose_axis)]
+        axes: Vec<(CartesianAxis, f32)>,
+        /// Tool speed in mm/s, capped at and defaulting to the tool speed limit
+        #[arg(long)]
+        speed: Option<f32>,
+    },
+    /// Jog the tool along or around an axis, in mm or degrees, e.g. `jog-cartesian z -10 --frame tool`
+    #[command(allow_negative_numbers = true)]
+    JogCartesian {
+        axis: CartesianAxis,
+        increment: f32,
+        /// Frame the axis belongs to, base or tool
+        #[arg(long, default_value = "base")]
+        frame: JogFrame,
+    },
+    /// Report the pose of the tool
+    Pose,
+    /// Move a joint by a number of steps, e.g. `jog J3 -200`
+    #[command(allow_negative_numbers = true)]
+    Jog {
+        #[arg(value_parser = parse_joint)]
+        joint: i8,
+        steps: i16,
+    },
+    /// Move a joint by an angle in degrees, e.g. `jog-joint J3 -5`
+    #[command(allow_negative_numbers = true)]
+    JogJoint {
+        #[arg(value_parser = parse_joint)]
+        joint: i8,
+        degrees: f32,
+    },
+    /// Jog continuously until Ctrl-C or `--seconds` pass, e.g. `hold J3 -10 --seconds 2`. Speed is
+    /// in degrees/s, mm/s along x, y and z, and negative to jog backwards
+    #[command(allow_negative_numbers = true)]
+    Hold {
+        /// Joint (J1 to J6) or axis (x, y, z, roll, pitch, yaw)
+        target: JogTarget,
+        speed: f32,
+        #[arg(long)]
+        seconds: Option<f32>,
+        /// Frame the axis belongs to, base or tool
+        #[arg(long, default_value = "base")]
+        frame: JogFrame,
+    },
+    /// Drive the arm with a game controller until Ctrl-C or the device is unplugged, e.g.
+    /// `gamepad gamepad.json --device /dev/input/event5`
+    Gamepad {
+        /// JSON mapping of axes and buttons to actions
+        mapping: PathBuf,
+        /// Event device, defaults to the one in the mapping
+        #[arg(long)]
+        device: Option<String>,
+        /// Positions file the save-position button writes to
+        #[arg(long)]
+        positions: Option<PathBuf>,
+    },
+    /// Disable every stepper
+    EmergencyStop,
+    /// Calibrate joints against their limit switches, e.g. `calibrate J1 J2`
+    Calibrate {
+        #[arg(required = true, value_parser = parse_joint)]
+        joints: Vec<i8>,
+    },
+    /// Calibrate joints in the configured order and move them to a home pose, e.g. `home J1 J2 J3`.
+    /// Every joint is homed when none is given
+    Home {
+        #[arg(value_parser = parse_joint)]
+        joints: Vec<i8>,
+        /// JSON file with the calibration order, offsets and home poses
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Home pose to end in instead of the configured one
+        #[arg(long)]
+        pose: Option<String>,
+    },
+    /// Make where a joint is now its zero, once jogged to its zero mark, and save the offset to the
+    /// `--zero-offsets` file, e.g. `set-zero J2`
+    SetZero {
+        #[arg(value_parser = parse_joint)]
+        joint: i8,
+    },
+    /// Enable or disable a joint, e.g. `toggle J1 ENABLED`
+    Toggle {
+        #[arg(value_parser = parse_joint)]
+        joint: i8,
+        #[arg(value_parser = ["ENABLED", "DISABLED"], ignore_case = true)]
+        enabled: String,
+    },
+    /// Report which steppers are enabled
+    State,
+    /// Report the current joint angles
+    Angles {
+        /// Keep reporting angles until interrupted
+        #[arg(short, long)]
+        watch: bool,
+        /// Interval between readings in milliseconds when watching
+        #[arg(long, default_value_t = 1000)]
+        interval: u64,
+    },
+    /// Report the configured velocity and acceleration
+    Params {
+        /// Read back what each joint runs with from the firmware instead, speed override included
+        #[arg(long)]
+        firmware: bool,
+    },
+    /// Set the velocity in degrees/s of every joint, or of a single one
+    #[command(allow_negative_numbers = true)]
+    Velocity {
+        /// Left out with --joint, the joint follows the global velocity again
+        velocity: Option<f32>,
+        #[arg(long, value_parser = parse_joint)]
+        joint: Option<i8>,
+    },
+    /// Set the acceleration in degrees/s² of every joint, or of a single one
+    #[command(allow_negative_numbers = true)]
+    Acceleration {
+        /// Left out with --joint, the joint follows the global acceleration again
+        acceleration: Option<f32>,
+        #[arg(long, value_parser = parse_joint)]
+        joint: Option<i8>,
+    },
+    /// Run a .rob script
+    Run {
+        file: PathBuf,
+        /// Percentage of the configured speed to run at, from 1 to 100
+        #[arg(long)]
+        speed: Option<f32>,
+        /// Positions file GOTO_POS reads from
+        #[arg(long)]
+        positions: Option<PathBuf>,
+        /// History file to record the run in
+        #[arg(long)]
+        history: Option<PathBuf>,
+        /// Note recorded with the run, e.g. the batch or who ran it
+        #[arg(long, requires = "history")]
+        note: Option<String>,
+    },
+    /// List the runs recorded in a history file, or export them to `--output`
+    History {
+        file: PathBuf,
+        /// Where to export the runs instead of listing them
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Export format, json or csv
+        #[arg(long, default_value = "json")]
+        format: ExportFormat,
+    },
+    /// Check a .rob script without moving the arm. Starts from where the arm is when a port is
+    /// given, otherwise from every joint calibrated at 0° unless set with `--start J1=45`
+    Validate {
+        file: PathBuf,
+        #[arg(long, value_parser = parse_joint_angle)]
+        start: Vec<(i8, f32)>,
+        /// Positions file GOTO_POS reads from
+        #[arg(long)]
+        positions: Option<PathBuf>,
+    },
+    /// Import G-code into a .rob script and run it, or write it to `--output` without connecting
+    Gcode {
+        file: PathBuf,
+        /// JSON file with the work object frame, tool orientation and M-code lines
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Where to write the script instead of running it
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Percentage of the configured speed to run at, from 1 to 100
+        #[arg(long, conflicts_with = "output")]
+        speed: Option<f32>,
+        /// History file to record the run in
+        #[arg(long, conflicts_with = "output")]
+        history: Option<PathBuf>,
+        /// Note recorded with the run, e.g. the batch or who ran it
+        #[arg(long, requires = "history")]
+        note: Option<String>,
+    },
+    /// Bridge the arm to an MQTT broker until interrupted
+    #[cfg(feature = "mqtt")]
+    Mqtt {
+        /// Broker host
+        #[arg(long, default_value = "localhost")]
+        host: String,
+        /// Broker port
+        #[arg(long, default_value_t = 1883)]
+        mqtt_port: u16,
+        /// Identifier of the arm in topic names, robert/<id>/...
+        #[arg(long, default_value = "robert")]
+        id: String,
+        /// Interval between state publications in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        interval: u64,
+        /// History file to record the scripts run through the bridge in
+        #[arg(long)]
+        history: Option<PathBuf>,
+    },
+}
+
+// Accepts joints as `J1` or `1`
+fn parse_joint(joint: &str) -> Result<i8, String> {
+    let index = joint
+        .strip_prefix(['J', 'j'])
+        .unwrap_or(joint)
+        .parse::<i8>()
+        .map_err(|_| format!("Invalid joint '{}'", joint))?;
+
+    if (1..=6).contains(&index) {
+        Ok(index)
+    } else {
+        Err(format!("Invalid joint '{}', expected J1 to J6", joint))
+    }
+}
+
+// Accepts joint angles as `J1=45`
+fn parse_joint_angle(joint_angle: &str) -> Result<(i8, f32), String> {
+    let (joint, angle) = joint_angle
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid joint angle '{}', expected J<n>=<angle>", joint_angle))?;
+    let angle = angle
+        .parse::<f32>()
+        .map_err(|_| format!("Invalid angle '{}'", angle))?;
+
+    Ok((parse_joint(joint)?, angle))
+}
+
+// Accepts pose axes as `x=120`, in mm or degrees
+fn parse_pose_axis(axis_value: &str) -> Result<(CartesianAxis, f32), String> {
+    let (axis, value) = axis_value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid pose axis '{}', expected <axis>=<value>", axis_value))?;
+    let axis = axis.parse::<CartesianAxis>()?;
+
+    let value = value
+        .parse::<f32>()
+        .map_err(|_| format!("Invalid value '{}'", value))?;
+
+    Ok((axis, value))
+}
+
+fn format_pose(pose: &CartesianPose) -> String {
+    format!(
+        "X: {:.2}  Y: {:.2}  Z: {:.2}  Roll: {:.2}  Pitch: {:.2}  Yaw: {:.2}",
+        pose.x, pose.y, pose.z, pose.roll, pose.pitch, pose.yaw
+    )
+}
+
+fn print_output<T: Serialize>(json: bool, value: &T, text: impl FnOnce() -> String) {
+    if json {
+        println!("{}", serde_json::to_string(value).unwrap());
+    } else {
+        println!("{}", text());
+    }
+}
+
+fn print_message(json: bool, message: &str) {
+    print_output(json, &json!({ "message": message.trim() }), || message.trim().to_string());
+}
+
+fn print_homing_progress(json: bool, progress: &HomingProgress) {
+    let joints = progress.joints.iter().map(|joint| format!("J{}", joint)).collect::<Vec<_>>().join(", ");
+
+    print_output(json, progress, || match progress.stage {
+        HomingStage::Calibrating => format!("[{}/{}] Calibrating {}", progress.step + 1, progress.steps, joints),
+        HomingStage::Offsetting => format!("[{}/{}] Moving {} to their offsets", progress.step + 1, progress.steps, joints),
+        HomingStage::MovingToPose => format!(
+            "[{}/{}] Moving to home pose '{}'",
+            progress.step + 1,
+            progress.steps,
+            progress.pose.as_deref().unwrap_or_default()
+        ),
+        HomingStage::Done => "Homing done".to_string(),
+    });
+}
+
+fn format_angles(angles: &[Option<f32>; 6]) -> String {
+    angles
+        .iter()
+        .enumerate()
+        .map(|(i, angle)| match angle {
+            Some(angle) => format!("J{}: {:.2}", i + 1, angle),
+            None => format!("J{}: UNKNOWN", i + 1),
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn format_parameters(parameters: &MotionParameters) -> String {
+    let mut lines = vec![format!(
+        "Velocity: {}°/s  Acceleration: {}°/s²",
+        parameters.velocity, parameters.acceleration
+    )];
+
+    for (i, overrides) in parameters.joints.iter().enumerate() {
+        let velocity = overrides.velocity.map(|velocity| format!("  Velocity: {}°/s", velocity));
+        let acceleration = overrides
+            .acceleration
+            .map(|acceleration| format!("  Acceleration: {}°/s²", acceleration));
+
+        if velocity.is_some() || acceleration.is_some() {
+            lines.push(format!(
+                "J{}:{}{}",
+                i + 1,
+                velocity.unwrap_or_default(),
+                acceleration.unwrap_or_default()
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+async fn connect(port: Option<String>, robot: &Robot) -> Result<String, String> {
+    let port = port.ok_or("No serial port given, use --port or ROBERT_PORT")?;
+    robot.connect(&port).await
+}
+
+fn format_diagnostics(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            format!(
+                "{} {}: {}",
+                match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                script::location(diagnostic.line, diagnostic.file.as_deref()),
+                diagnostic.message
+            )
+        })
+        .collect()
+}
+
+// Imports the G-code in `file` and prints what it found, failing on unsupported codes
+fn import_gcode(file: &Path, config: Option<&Path>, json: bool) -> Result<GcodeImport, String> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| format!("Failed to read G-code {}: {}", file.display(), e))?;
+    let config = config.map(GcodeConfig::load).transpose()?.unwrap_or_default();
+
+    let import = gcode::import_gcode(&content, &config);
+    if !import.diagnostics.is_empty() {
+        print_output(json, &import.diagnostics, || format_diagnostics(&import.diagnostics).join("\n"));
+    }
+
+    match import.has_errors() {
+        true => Err("The G-code has unsupported codes".to_string()),
+        false => Ok(import),
+    }
+}
+
+async fn validate(
+    robot: &Robot,
+    port: Option<String>,
+    file: &Path,
+    start: Vec<(i8, f32)>,
+    positions: Option<PathBuf>,
+    json: bool,
+) -> Result<(), String> {
+    let script = script::load_script(file)?;
+    let positions = positions.map(PositionStore::load).transpose()?;
+
+    let mut start_pose = match port {
+        Some(port) => {
+            robot.connect(&port).await?;
+            StartPose::current(robot).await?
+        }
+        None => StartPose::default(),
+    };
+    for (joint, angle) in start {
+        start_pose.angles[(joint - 1) as usize] = Some(angle);
+    }
+
+    let report = robot.validate_script(&script, &start_pose, positions.as_ref()).await;
+
+    print_output(json, &report, || {
+        let mut lines = format_diagnostics(&report.diagnostics);
+        lines.push(format!("Estimated duration: {:.1} s", report.estimated_duration));
+        lines.join("\n")
+    });
+
+    match report.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+        true => Err("The script has errors".to_string()),
+        false => Ok(()),
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let json = cli.json;
+    let robot = Robot::new();
+
+    if let Some(path) = &cli.zero_offsets {
+        robot.set_zero_offsets(calibration::load_zero_offsets(path)?).await?;
+    }
+
+    if let Command::Ports = cli.command {
+        let ports = Robot::ports();
+        print_output(json, &ports, || ports.join("\n"));
+        return Ok(());
+    }
+
+    if let Command::Validate { file, start, positions } = cli.command {
+        return validate(&robot, cli.port, &file, start, positions, json).await;
+    }
+
+    if let Command::History { file, output, format } = &cli.command {
+        return list_history(file, output.as_deref(), *format, json);
+    }
+
+    if let Command::Gcode { file, config, output: Some(output), .. } = &cli.command {
+        let import = import_gcode(file, config.as_deref(), json)?;
+        return utils::write_atomic(output, &import.script);
+    }
+
+    let connect_response = connect(cli.port, &robot).await?;
+
+    match cli.command {
+        Command::Ports | Command::Validate { .. } | Command::History { .. } | Command::Gcode { output: Some(_), .. } => {
+            unreachable!()
+        }
+        Command::Connect => print_message(json, &connect_response),
+        Command::Move { joints } => {
+            let response = robot.move_joints(joints).await?;
+            print_message(json, &response);
+        }
+        Command::Movel { axes, speed } => {
+            let mut target = robot.tool_pose().await?;
+
+            for (axis, value) in axes {
+                match axis {
+                    CartesianAxis::X => target.x = value,
+                    CartesianAxis::Y => target.y = value,
+                    CartesianAxis::Z => target.z = value,
+                    CartesianAxis::Roll => target.roll = value,
+                    CartesianAxis::Pitch => target.pitch = value,
+                    CartesianAxis::Yaw => target.yaw = value,
+                }
+            }
+
+            let response = robot.move_linear(target, speed).await?;
+            print_message(json, &response);
+        }
+        Command::JogCartesian { axis, increment, frame } => {
+            let pose = robot.jog_cartesian(axis, frame, increment).await?;
+            print_output(json, &pose, || format_pose(&pose));
+        }
+        Command::Pose => {
+            let pose = robot.tool_pose().await?;
+            print_output(json, &pose, || format_pose(&pose));
+        }
+        Command::Jog { joint, steps } => {
+            let response = robot.jog(joint, steps).await?;
+            print_message(json, &response);
+        }
+        Command::JogJoint { joint, degrees } => {
+            let angle = robot.jog_joint(joint, degrees).await?;
+            print_output(json, &json!({ "joint": joint, "angle": angle }), || format!("J{}: {:.2}", joint, angle));
+        }
+        Command::Hold { target, speed, seconds, frame } => {
+            let mut events = robot.subscribe();
+            let direction = if speed < 0.0 { -1 } else { 1 };
+            robot.start_jog(target, direction, speed.abs(), frame).await?;
+
+            // Heartbeats are sent well within the timeout, the jog stops if this process hangs
+            let started = tokio::time::Instant::now();
+            let mut heartbeat = tokio::time::interval(Duration::from_millis(150));
+            loop {
+                tokio::select! {
+                    _ = heartbeat.tick() => {
+                        let expired = seconds.is_some_and(|seconds| started.elapsed().as_secs_f32() >= seconds);
+                        if !robot.heartbeat_jog() || expired {
+                            break;
+                        }
+                    }
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+            robot.stop_jog().await;
+
+            let mut report = None;
+            loop {
+                match events.try_recv() {
+                    Ok(RobotEvent::JogStopped(stopped)) => report = Some(stopped),
+                    Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                    Err(_) => break,
+                }
+            }
+
+            if let Some(report) = report {
+                print_output(json, &report, || match &report.message {
+                    Some(message) => format!("Jog stopped ({:?}): {}", report.reason, message),
+                    None => format!("Jog stopped ({:?})", report.reason),
+                });
+            }
+        }
+        Command::Gamepad { mapping, device, positions } => {
+            let mapping = GamepadMapping::load(&mapping)?;
+            let positions = positions
+                .map(|path| PositionStore::load(path).map(|store| Arc::new(Mutex::new(store))))
+                .transpose()?;
+
+            tokio::select! {
+                result = gamepad::run_gamepad(robot.clone(), mapping, device, positions) => result?,
+                _ = tokio::signal::ctrl_c() => robot.stop_jog().await,
+            }
+        }
+        Command::EmergencyStop => {
+            let response = robot.emergency_stop().await?;
+            print_message(json, &response);
+        }
+        Command::Calibrate { joints } => {
+            let response = robot.calibrate(&joints).await?;
+            print_message(json, &response);
+        }
+        Command::Home { joints, config, pose } => {
+            let config = config.map(|path| HomingConfig::load(&path)).transpose()?.unwrap_or_default();
+            let mut events = robot.subscribe();
+
+            let home = homing::home(&robot, &config, &joints, pose.as_deref());
+            tokio::pin!(home);
+            let response = loop {
+                tokio::select! {
+                    result = &mut home => break result?,
+                    Ok(event) = events.recv() => {
+                        if let RobotEvent::HomingProgress(progress) = event {
+                            print_homing_progress(json, &progress);
+                        }
+                    }
+                }
+            };
+            while let Ok(event) = events.try_recv() {
+                if let RobotEvent::HomingProgress(progress) = event {
+                    print_homing_progress(json, &progress);
+                }
+            }
+
+            print_message(json, &response);
+        }
+        Command::SetZero { joint } => {
+            let path = cli
+                .zero_offsets
+                .as_deref()
+                .ok_or("Pass --zero-offsets with the file to save the offset to")?;
+            let offset = robot.set_zero_here(joint).await?;
+            calibration::save_zero_offsets(path, &robot.zero_offsets().await)?;
+
+            print_output(json, &json!({ "joint": joint, "offset": offset }), || {
+                format!("J{} zero set {:.2}° from its limit switch", joint, offset)
+            });
+        }
+        Command::Toggle { joint, enabled } => {
+            let response = robot.toggle(joint, &enabled.to_uppercase()).await?;
+            print_message(json, &response);
+        }
+        Command::State => {
+            let steppers_state = robot.steppers_state().await?;
+            print_output(json, &steppers_state, || {
+                steppers_state
+                    .iter()
+                    .enumerate()
+                    .map(|(i, enabled)| format!("J{}: {}", i + 1, if *enabled { "ENABLED" } else { "DISABLED" }))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            });
+        }
+        Command::Angles { watch, interval } => loop {
+            let angles = robot.angles().await?;
+            print_output(json, &SteppersAngles::from(angles), || format_angles(&angles));
+
+            if !watch {
+                break;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(interval)) => {}
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        },
+        Command::Params { firmware: false } => {
+            let parameters = robot.parameters().await;
+            print_output(json, &parameters, || format_parameters(&parameters));
+        }
+        Command::Params { firmware: true } => {
+            let parameters = robot.read_parameters().await?;
+            print_output(json, &parameters, || {
+                parameters
+                    .iter()
+                    .map(|joint| format!("J{}:  Velocity: {:.2}°/s  Acceleration: {:.2}°/s²", joint.joint, joint.velocity, joint.acceleration))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Velocity { velocity, joint } => {
+            let response = match (joint, velocity) {
+                (Some(joint), velocity) => robot.set_joint_velocity(joint, velocity).await?,
+                (None, Some(velocity)) => robot.set_velocity(velocity).await?,
+                (None, None) => return Err("A velocity is needed without --joint".to_string()),
+            };
+            print_message(json, &response);
+        }
+        Command::Acceleration { acceleration, joint } => {
+            let response = match (joint, acceleration) {
+                (Some(joint), acceleration) => robot.set_joint_acceleration(joint, acceleration).await?,
+                (None, Some(acceleration)) => robot.set_acceleration(acceleration).await?,
+                (None, None) => return Err("An acceleration is needed without --joint".to_string()),
+            };
+            print_message(json, &response);
+        }
+        Command::Run { file, speed, positions, history, note } => {
+            let script = script::load_script(&file)?;
+            let positions = positions
+                .map(|path| PositionStore::load(path).map(|store| Arc::new(Mutex::new(store))))
+                .transpose()?;
+            let history = history
+                .map(|path| RunHistory::load(path).map(|history| Arc::new(Mutex::new(history))))
+                .transpose()?;
+
+            if let Some(speed) = speed {
+                robot.set_speed_override(speed).await?;
+            }
+
+            let mut interpreter = Interpreter::new(robot.clone(), positions);
+            let print_response = |line: usize, response: String| {
+                print_output(
+                    json,
+                    &json!({ "line": line, "response": response.trim() }),
+                    || format!("[{}] {}", line, response.trim()),
+                );
+            };
+
+            match history {
+                Some(history) => {
+                    let content = std::fs::read_to_string(&file)
+                        .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+                    let name = file.file_name().map_or(file.to_string_lossy(), |name| name.to_string_lossy());
+                    history::run_recorded(&mut interpreter, &script, &name, &content, note, &history, print_response)
+                        .await?
+                }
+                None => interpreter.run(&script, print_response).await?,
+            }
+        }
+        Command::Gcode { file, config, output: None, speed, history, note } => {
+            let import = import_gcode(&file, config.as_deref(), json)?;
+            // INCLUDEs in the configured header are relative to the G-code
+            let script = script::parse_script_at(&import.script, &file)?;
+            let history = history
+                .map(|path| RunHistory::load(path).map(|history| Arc::new(Mutex::new(history))))
+                .transpose()?;
+
+            if let Some(speed) = speed {
+                robot.set_speed_override(speed).await?;
+            }
+
+            let mut interpreter = Interpreter::new(robot.clone(), None);
+            let print_response = |line: usize, response: String| {
+                print_output(
+                    json,
+                    &json!({ "line": line, "response": response.trim() }),
+                    || format!("[{}] {}", line, response.trim()),
+                );
+            };
+
+            match history {
+                // Recorded under the G-code file, hashed as the script it was converted to
+                Some(history) => {
+                    let name = file.file_name().map_or(file.to_string_lossy(), |name| name.to_string_lossy());
+                    history::run_recorded(&mut interpreter, &script, &name, &import.script, note, &history, print_response)
+                        .await?
+                }
+                None => interpreter.run(&script, print_response).await?,
+            }
+        }
+        #[cfg(feature = "mqtt")]
+        Command::Mqtt { host, mqtt_port, id, interval, history } => {
+            let history = history
+                .map(|path| RunHistory::load(path).map(|history| Arc::new(Mutex::new(history))))
+                .transpose()?;
+            let config = robert_core::mqtt::MqttConfig {
+                host,
+                port: mqtt_port,
+                robot_id: id,
+                state_interval: Duration::from_millis(interval),
+                history,
+            };
+
+            tokio::select! {
+                result = robert_core::mqtt::run_bridge(robot, config) => result?,
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_history(file: &Path, output: Option<&Path>, format: ExportFormat, json: bool) -> Result<(), String> {
+    if !file.exists() {
+        return Err(format!("History file {} does not exist", file.display()));
+    }
+    let history = RunHistory::load(file.to_path_buf())?;
+
+    if let Some(output) = output {
+        return history.export(output, format);
+    }
+
+    print_output(json, &history.list(), || {
+        history
+            .list()
+            .iter()
+            .map(|run| {
+                let mut line = format!(
+                    "#{} {} {} in {:.1} s",
+                    run.id,
+                    run.script,
+                    run.result.as_str(),
+                    run.ended_at.saturating_sub(run.started_at) as f32 / 1000.0
+                );
+                if let Some(stopped_at) = run.stopped_at {
+                    line.push_str(&format!(", stopped at line {}", stopped_at));
+                }
+                if let Some(note) = &run.note {
+                    line.push_str(&format!(" ({})", note));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                println!("{}", json!({ "error": e }));
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
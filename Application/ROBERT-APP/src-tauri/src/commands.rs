@@ -1,265 +1,652 @@
-use crate::constants;
-use crate::state::SharedAppState;
-use crate::utils::{self, send_and_receive_from_shared_state};
-use serialport::available_ports;
-use tauri::{AppHandle, State};
-use tokio::time::Duration;
+use robert_core::calibration;
+use robert_core::cartesian::{CartesianAxis, JogFrame};
+use robert_core::debugger::{self, DebugCommand, DebugHandle};
+use robert_core::gamepad::{self, GamepadMapping};
+use robert_core::gcode::{self, GcodeConfig, GcodeImport};
+use robert_core::history::{self, ExportFormat, ScriptRun, SharedRunHistory};
+use robert_core::homing::{self, HomingConfig};
+use robert_core::interpreter::Interpreter;
+use robert_core::jog::JogTarget;
+use robert_core::kinematics::{CartesianPose, CartesianPosition};
+use robert_core::parameters::{JointLimits, JointParameters, MotionParameters};
+use robert_core::planner::TrajectoryPlan;
+use robert_core::positions::{self, SharedPositionStore, StoredPosition};
+use robert_core::teach::{self, SharedRecording, Waypoint};
+use robert_core::validation::{StartPose, ValidationReport};
+use robert_core::script::{self, Script};
+use robert_core::Robot;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::State;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+// Gamepad teleoperation running in the background, if any
+pub type GamepadTask = Mutex<Option<JoinHandle<Result<(), String>>>>;
+
+// Handle of the script being debugged, if any
+pub type ScriptDebugger = Mutex<Option<DebugHandle>>;
+
+// File the zero offsets are saved to
+pub struct ZeroOffsetsPath(pub PathBuf);
 
 #[tauri::command]
 pub async fn connect_to_port<'a>(
     port: String,
-    state: State<'a, SharedAppState>,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-   utils::connect_to_port(port, state).await
+    robot.connect(&port).await
 }
 
 #[tauri::command]
 pub async fn disconnect_from_active_connection<'a>(
-    state: State<'a, SharedAppState>, 
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-    // Lock the shared app state
-    let mut app_state = state.write().await;
-
-    if app_state.serial_connection.is_some() {
-        println!("###DEBUG### - Disconnecting from serial port.");
-
-        // Explicitly drop the connection
-        app_state.serial_connection = None;
-        
-        // Give the OS time to release the port
-        tokio::time::sleep(Duration::from_millis(200)).await;
-        
-        println!("###DEBUG### - Serial port disconnected.");
-        Ok("Successfully disconnected from the port.".to_string())
-    } else {
-        Err("No active serial connection.".to_string())
-    }
+    robot.disconnect().await
 }
 
-
+//Acceleration in degrees/s² of every joint without an override
 #[tauri::command]
 pub async fn set_acceleration<'a>(
-    acceleration: i8,
-    state: State<'a, SharedAppState>,
+    acceleration: f32,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-    // Convert to i16 to prevent overflow
-    let scaled_acceleration = (acceleration as i16) * constants::PARAMETERS_MULTIPLIER as i16
-    ;
-
-    let set_acc_command = format!("{}{}", constants::CommandCodes::SETACC, scaled_acceleration);
-
-    match send_and_receive_from_shared_state(&set_acc_command, state.inner().clone(), None).await {
-        Ok(response) => Ok(format!(
-            "Successfully sent set_acc command. Response: {}",
-            response
-        )),
-        Err(e) => Err(format!("Error: {}", e)),
-    }
+    robot.set_acceleration(acceleration).await
 }
 
+//Velocity in degrees/s of every joint without an override
 #[tauri::command]
 pub async fn set_velocity<'a>(
-    velocity: i8,
-    state: State<'a, SharedAppState>,
+    velocity: f32,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-    // Convert to i16 to prevent overflow
-    let scaled_velocity = (velocity as i16) * constants::PARAMETERS_MULTIPLIER as i16;
+    robot.set_velocity(velocity).await
+}
 
-    let set_vel_command = format!("{}{}", constants::CommandCodes::SETVEL, scaled_velocity);
+//Overrides the velocity of a joint, no velocity makes it follow the global one again
+#[tauri::command]
+pub async fn set_joint_velocity<'a>(
+    joint_index: i8,
+    velocity: Option<f32>,
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.set_joint_velocity(joint_index, velocity).await
+}
 
-    match send_and_receive_from_shared_state(&set_vel_command, state.inner().clone(), None).await {
-        Ok(response) => Ok(format!(
-            "Successfully sent set_vel command. Response: {}",
-            response
-        )),
-        Err(e) => Err(format!("Error: {}", e)),
-    }
+//Overrides the acceleration of a joint, no acceleration makes it follow the global one again
+#[tauri::command]
+pub async fn set_joint_acceleration<'a>(
+    joint_index: i8,
+    acceleration: Option<f32>,
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.set_joint_acceleration(joint_index, acceleration).await
 }
 
 #[tauri::command]
 pub async fn move_step<'a>(
-    app: AppHandle,
-    joint_index: i8, 
-    mut n_steps: i16, 
-    state: State<'a, SharedAppState>,
+    joint_index: i8,
+    n_steps: i16,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-    if joint_index <= 0 || joint_index as usize >= constants::STEPPER_POSITIVE_TO_LIMIT.len() {
-        return Err("Invalid joint index".to_string());
-    }
-
-    // Convert `joint_index` to `usize` for array indexing
-    let joint_index_usize = joint_index as u8;
-
-    if constants::STEPPER_POSITIVE_TO_LIMIT[&joint_index_usize] {
-        n_steps = -n_steps;
-    }
-
-    let move_step_command = format!(
-        "{}J{}_{};",
-        constants::CommandCodes::MOVE,
-        joint_index,
-        n_steps
-    );
-
-    // Send movement command
-    let response = send_and_receive_from_shared_state(&move_step_command, state.inner().clone(), None).await;
-
-    // If the command is successful, get updated stepper angles
-    match response {
-        Ok(resp) => {
-            // Call `get_steppers_angles` to retrieve updated angles
-            if let Err(e) = utils::get_steppers_angles(&app, state.inner().clone()).await {
-                return Err(format!("Error retrieving stepper angles: {}", e));
-            }
-
-            Ok(format!(
-                "Successfully sent move_step command. Response: {}",
-                resp
-            ))
-        }
-        Err(e) => Err(format!("Error: {}", e)),
-    }
+    robot.jog(joint_index, n_steps).await
 }
 
-
-
+//Moves a joint by an angle instead of raw steps, returns the angle reached
+#[tauri::command]
+pub async fn jog_joint<'a>(
+    joint_index: i8,
+    delta_degrees: f32,
+    robot: State<'a, Robot>,
+) -> Result<f32, String> {
+    robot.jog_joint(joint_index, delta_degrees).await
+}
 
 #[tauri::command]
 pub async fn toggle_stepper<'a>(
     joint_index: i8,
     enabled: &str,
-    state: State<'a, SharedAppState>,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-
-    // Arduino command format: TOGGLE>JOINT_STATE;
-    let toggle_command = format!(
-        "{}J{}_{};",
-        constants::CommandCodes::TOGGLE,
-        joint_index,
-        enabled
-    );
-
-    // Send the command using the shared connection
-    match send_and_receive_from_shared_state(&toggle_command, state.inner().clone(), None).await {
-        Ok(response) => Ok(format!(
-            "Successfully sent toggle_step command. Response: {}",
-            response
-        )),
-        Err(e) => Err(format!("Error: {}", e)),
-    }
+    robot.toggle(joint_index, enabled).await
 }
 
 #[tauri::command]
 pub async fn calibrate_steppers<'a>(
     joints_indexes: Vec<i8>,
-    state: State<'a, SharedAppState>,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
-    let joint_commands: Vec<String> = joints_indexes
-        .iter()
-        .map(|&index| format!("J{};", index))
-        .collect();
-
-    // Join all joint commands with no separator, and prepend the CALIBRATE> part
-    let calibrate_command = format!(
-        "{}{}",
-        constants::CommandCodes::CALIBRATE,
-        joint_commands.join("")
-    );
-
-    // Send the command using the shared connection
-    //Use a high timeout duration for calibration
-    match send_and_receive_from_shared_state(&calibrate_command, state.inner().clone(), Some(Duration::from_secs(35))).await {
-        Ok(response) => Ok(format!(
-            "Successfully sent calibrate command. Response: {}",
-            response
-        )),
-        Err(e) => Err(format!("Error: {}", e)),
-    }
+    robot.calibrate(&joints_indexes).await
+}
+
+// Calibrates `joints`, every joint when left out, in the order from homing.json in the app data dir
+// and ends in the home pose `pose` or the configured one. Stages are reported as `report-homing-progress`
+#[tauri::command]
+pub async fn home<'a>(
+    joints: Option<Vec<i8>>,
+    pose: Option<String>,
+    robot: State<'a, Robot>,
+    config: State<'a, HomingConfig>,
+) -> Result<String, String> {
+    homing::home(&robot, &config, &joints.unwrap_or_default(), pose.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_zero_offsets<'a>(
+    robot: State<'a, Robot>,
+) -> Result<[f32; 6], String> {
+    Ok(robot.zero_offsets().await)
 }
 
-//Command assumes all joint angles are provided as positive numbers 
+// Replaces the zero offsets, e.g. to clear one, and saves them
+#[tauri::command]
+pub async fn set_zero_offsets<'a>(
+    offsets: [f32; 6],
+    robot: State<'a, Robot>,
+    path: State<'a, ZeroOffsetsPath>,
+) -> Result<(), String> {
+    robot.set_zero_offsets(offsets).await?;
+    calibration::save_zero_offsets(&path.0, &offsets)
+}
+
+// Makes where `joint` is now its zero and saves the offset, which is returned
+#[tauri::command]
+pub async fn set_zero_here<'a>(
+    joint: i8,
+    robot: State<'a, Robot>,
+    path: State<'a, ZeroOffsetsPath>,
+) -> Result<f32, String> {
+    let offset = robot.set_zero_here(joint).await?;
+    calibration::save_zero_offsets(&path.0, &robot.zero_offsets().await)?;
+
+    Ok(offset)
+}
+
+// Names of the configured home poses
+#[tauri::command]
+pub fn list_home_poses(config: State<'_, HomingConfig>) -> Vec<String> {
+    config.poses.keys().cloned().collect()
+}
+
+//Command assumes all joint angles are provided as positive numbers
 #[tauri::command]
 pub async fn drive_steppers_to_angles<'a>(
-    app: AppHandle,
-    joints_angles: Vec<(i8, f32)>, 
-    state: State<'a, SharedAppState>,
+    joints_angles: Vec<(i8, f32)>,
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.move_joints(joints_angles).await
+}
+
+//Plans a move like drive_steppers_to_angles without executing it
+#[tauri::command]
+pub async fn plan_steppers_to_angles<'a>(
+    joints_angles: Vec<(i8, f32)>,
+    robot: State<'a, Robot>,
+) -> Result<TrajectoryPlan, String> {
+    robot.plan_move(joints_angles).await
+}
+
+//Moves the tool along a straight line, needs every joint calibrated. `speed` in mm/s
+#[tauri::command]
+pub async fn move_linear<'a>(
+    pose: CartesianPose,
+    speed: Option<f32>,
+    robot: State<'a, Robot>,
 ) -> Result<String, String> {
+    robot.move_linear(pose, speed).await
+}
 
-    // Adjust angles based on the joint's positive limit switch
-    let adjusted_angles: Vec<(i8, f32)> = joints_angles
-        .into_iter()
-        .map(|(joint_index, angle)| {
-            let joint_index_u8 = joint_index as u8; // Convert to u8 for HashMap lookup
-        
-            if constants::STEPPER_POSITIVE_TO_LIMIT.get(&joint_index_u8).copied().unwrap_or(false) {
-                (joint_index, -angle) // Negate if true
-            } else {
-                (joint_index, angle) // Keep as is
-            }
-        })
-        .collect();
+//Moves the tool along the arc through `via` to `end`, `speed` in mm/s
+#[tauri::command]
+pub async fn move_arc<'a>(
+    via: CartesianPosition,
+    end: CartesianPose,
+    speed: Option<f32>,
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.move_arc(via, end, speed).await
+}
 
-    utils::drive_steppers_to_angles(&app,adjusted_angles, state.inner().clone()).await
+//Jogs the tool by `increment` mm or degrees, returns the new tool pose
+#[tauri::command]
+pub async fn jog_cartesian<'a>(
+    axis: CartesianAxis,
+    frame: JogFrame,
+    increment: f32,
+    robot: State<'a, Robot>,
+) -> Result<CartesianPose, String> {
+    robot.jog_cartesian(axis, frame, increment).await
 }
 
+//Starts a continuous jog, kept alive by heartbeat_jog until stop_jog is called
 #[tauri::command]
-pub async fn get_parameters<'a>(
-    state: State<'a, SharedAppState>,
-) -> Result<[u8; 2], String> {
-    // Send the command using the shared connection
-    match send_and_receive_from_shared_state(constants::CommandCodes::PARAMS, state.inner().clone(), None).await {
-        Ok(response) => {
-            // Expected response format: "[PARAMS];VEL_20;ACC_40;"
-            if response.starts_with(constants::ResponseCodes::PARAMS_RESPONSE) {
-                let parts: Vec<&str> = response.split(';').collect();
-                let mut vel: u8 = 0;
-                let mut acc: u8 = 0;
-                
-                for part in parts {
-                    if part.starts_with("VEL_") {
-                        vel = (part[4..].parse::<f32>().unwrap_or(0.0) / constants::PARAMETERS_MULTIPLIER as f32) as u8;
-                    } else if part.starts_with("ACC_") {
-                        acc = (part[4..].parse::<f32>().unwrap_or(0.0) / constants::PARAMETERS_MULTIPLIER as f32) as u8;
-                    }
-                }
-
-                Ok([vel, acc])
-            } else {
-                Err("Invalid response format".to_string())
-            }
-        }
-        Err(e) => Err(format!("Error: {}", e)),
+pub async fn start_jog<'a>(
+    target: JogTarget,
+    direction: i8,
+    speed: f32,
+    frame: Option<JogFrame>,
+    robot: State<'a, Robot>,
+) -> Result<(), String> {
+    robot
+        .start_jog(target, direction, speed, frame.unwrap_or(JogFrame::Base))
+        .await
+}
+
+#[tauri::command]
+pub fn heartbeat_jog(robot: State<'_, Robot>) -> bool {
+    robot.heartbeat_jog()
+}
+
+#[tauri::command]
+pub async fn stop_jog<'a>(
+    robot: State<'a, Robot>,
+) -> Result<(), String> {
+    robot.stop_jog().await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn emergency_stop<'a>(
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.emergency_stop().await
+}
+
+//Starts driving the arm with the game controller described by the mapping file
+#[tauri::command]
+pub async fn start_gamepad<'a>(
+    mapping_path: PathBuf,
+    device: Option<String>,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+    task: State<'a, GamepadTask>,
+) -> Result<(), String> {
+    let mut task = task.lock().await;
+
+    if task.as_ref().is_some_and(|task| !task.is_finished()) {
+        return Err("The gamepad is already running".to_string());
+    }
+
+    let mapping = GamepadMapping::load(&mapping_path)?;
+    *task = Some(gamepad::spawn_gamepad(
+        robot.inner().clone(),
+        mapping,
+        device,
+        Some(store.inner().clone()),
+    ));
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_gamepad<'a>(
+    robot: State<'a, Robot>,
+    task: State<'a, GamepadTask>,
+) -> Result<(), String> {
+    if let Some(task) = task.lock().await.take() {
+        task.abort();
     }
+
+    robot.stop_jog().await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_tool_pose<'a>(
+    robot: State<'a, Robot>,
+) -> Result<CartesianPose, String> {
+    robot.tool_pose().await
+}
+
+//Runs every motion at a percentage of the configured speed
+#[tauri::command]
+pub async fn set_speed_override<'a>(
+    percent: f32,
+    robot: State<'a, Robot>,
+) -> Result<String, String> {
+    robot.set_speed_override(percent).await
+}
+
+#[tauri::command]
+pub async fn get_speed_override<'a>(
+    robot: State<'a, Robot>,
+) -> Result<f32, String> {
+    Ok(robot.speed_override().await)
+}
+
+#[tauri::command]
+pub async fn get_parameters<'a>(
+    robot: State<'a, Robot>,
+) -> Result<MotionParameters, String> {
+    Ok(robot.parameters().await)
+}
+
+// What each joint runs with, read back from the firmware
+#[tauri::command]
+pub async fn read_parameters<'a>(
+    robot: State<'a, Robot>,
+) -> Result<Vec<JointParameters>, String> {
+    robot.read_parameters().await
+}
+
+#[tauri::command]
+pub fn get_parameter_limits() -> Vec<JointLimits> {
+    Robot::parameter_limits()
+}
+
+//Sets the interval of the background state polling in ms, 0 stops it
+#[tauri::command]
+pub fn set_poll_interval(interval_ms: u64, robot: State<'_, Robot>) -> Result<(), String> {
+    let interval = (interval_ms > 0).then(|| Duration::from_millis(interval_ms));
+
+    robot.set_poll_interval(interval)
+}
+
+#[tauri::command]
+pub fn get_poll_interval(robot: State<'_, Robot>) -> u64 {
+    robot.poll_interval().map_or(0, |interval| interval.as_millis() as u64)
 }
 
 #[tauri::command]
 pub async fn check_steppers_state<'a>(
-    state: State<'a, SharedAppState>,
+    robot: State<'a, Robot>,
 ) -> Result<[bool; 6], String> {
-    return utils::get_steppers_state(state.inner().clone()).await;
+    robot.steppers_state().await
 }
 
 #[tauri::command]
 pub async fn get_steppers_angles<'a>(
-    app: AppHandle,
-    state: State<'a, SharedAppState>,
+    robot: State<'a, Robot>,
 ) -> Result<[Option<f32>; 6], String> {
-    return utils::get_steppers_angles(&app,state.inner().clone()).await;
+    robot.angles().await
 }
 
 #[tauri::command]
 pub fn get_ports() -> Vec<String> {
-    let mut ports_list = Vec::new();
+    Robot::ports()
+}
+
+#[tauri::command]
+pub async fn save_position<'a>(
+    name: String,
+    notes: Option<String>,
+    tool: Option<String>,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+) -> Result<StoredPosition, String> {
+    positions::save_current_position(&robot, &store, &name, notes, tool).await
+}
+
+#[tauri::command]
+pub async fn list_positions<'a>(
+    store: State<'a, SharedPositionStore>,
+) -> Result<Vec<StoredPosition>, String> {
+    Ok(store.lock().await.list().to_vec())
+}
+
+#[tauri::command]
+pub async fn rename_position<'a>(
+    name: String,
+    new_name: String,
+    store: State<'a, SharedPositionStore>,
+) -> Result<(), String> {
+    store.lock().await.rename(&name, &new_name)
+}
+
+#[tauri::command]
+pub async fn delete_position<'a>(
+    name: String,
+    store: State<'a, SharedPositionStore>,
+) -> Result<(), String> {
+    store.lock().await.delete(&name)
+}
+
+#[tauri::command]
+pub async fn go_to_position<'a>(
+    name: String,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+) -> Result<String, String> {
+    positions::go_to_position(&robot, &store, &name).await
+}
+
+#[tauri::command]
+pub async fn import_positions<'a>(
+    path: PathBuf,
+    store: State<'a, SharedPositionStore>,
+) -> Result<usize, String> {
+    store.lock().await.import(&path)
+}
+
+#[tauri::command]
+pub async fn export_positions<'a>(
+    path: PathBuf,
+    store: State<'a, SharedPositionStore>,
+) -> Result<(), String> {
+    store.lock().await.export(&path)
+}
+
+// Records where the arm is now as the next teach mode waypoint, waiting `dwell` ms once reached
+#[tauri::command]
+pub async fn record_waypoint<'a>(
+    dwell: Option<f32>,
+    label: Option<String>,
+    robot: State<'a, Robot>,
+    recording: State<'a, SharedRecording>,
+) -> Result<Waypoint, String> {
+    teach::record_waypoint(&robot, &recording, dwell, label).await
+}
+
+#[tauri::command]
+pub async fn list_waypoints<'a>(
+    recording: State<'a, SharedRecording>,
+) -> Result<Vec<Waypoint>, String> {
+    Ok(recording.lock().await.waypoints().to_vec())
+}
+
+#[tauri::command]
+pub async fn set_waypoint_dwell<'a>(
+    index: usize,
+    dwell: Option<f32>,
+    recording: State<'a, SharedRecording>,
+) -> Result<(), String> {
+    recording.lock().await.set_dwell(index, dwell)
+}
+
+#[tauri::command]
+pub async fn delete_waypoint<'a>(
+    index: usize,
+    recording: State<'a, SharedRecording>,
+) -> Result<(), String> {
+    recording.lock().await.remove(index).map(|_| ())
+}
+
+#[tauri::command]
+pub async fn clear_recording<'a>(
+    recording: State<'a, SharedRecording>,
+) -> Result<(), String> {
+    recording.lock().await.clear();
+    Ok(())
+}
+
+// The recording as a .rob script, to play back or save
+#[tauri::command]
+pub async fn recording_script<'a>(
+    recording: State<'a, SharedRecording>,
+) -> Result<String, String> {
+    Ok(recording.lock().await.to_script())
+}
+
+#[tauri::command]
+pub async fn export_recording<'a>(
+    path: PathBuf,
+    recording: State<'a, SharedRecording>,
+) -> Result<(), String> {
+    recording.lock().await.export(&path)
+}
+
+// Scripts opened from a file are parsed at their `path`, so they can INCLUDE the files next to them
+fn parse_script(content: &str, path: Option<&Path>) -> Result<Script, String> {
+    match path {
+        Some(path) => script::parse_script_at(content, path),
+        None => script::parse_script(content),
+    }
+}
+
+// Reads a script picked in the file dialog, the webview only gets its path
+#[tauri::command]
+pub fn read_script(path: PathBuf) -> Result<String, String> {
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+// Parses a script without running it, so errors show up before the arm moves
+#[tauri::command]
+pub fn check_script(content: String, path: Option<PathBuf>) -> Result<(), String> {
+    parse_script(&content, path.as_deref()).map(|_| ())
+}
+
+// Name runs of scripts that were never saved are recorded under
+const UNNAMED_SCRIPT: &str = "untitled.rob";
+
+// Runs a whole script, progress is reported as `report-script-progress`. The run is recorded in the
+// history under `name` with the operator's `note`
+#[tauri::command]
+pub async fn run_script<'a>(
+    content: String,
+    path: Option<PathBuf>,
+    name: Option<String>,
+    note: Option<String>,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+    runs: State<'a, SharedRunHistory>,
+) -> Result<(), String> {
+    let script = parse_script(&content, path.as_deref())?;
+    let mut interpreter = Interpreter::new(robot.inner().clone(), Some(store.inner().clone()));
+
+    history::run_recorded(
+        &mut interpreter,
+        &script,
+        name.as_deref().unwrap_or(UNNAMED_SCRIPT),
+        &content,
+        note,
+        &runs,
+        |line, response| {
+            eprintln!("###DEBUG### - Script line {}: {}", line, response.trim());
+        },
+    )
+    .await
+}
+
+// Converts G-code into a .rob script, with the configuration from gcode.json in the app data dir
+#[tauri::command]
+pub fn import_gcode(
+    content: String,
+    config: State<'_, GcodeConfig>,
+) -> GcodeImport {
+    gcode::import_gcode(&content, &config)
+}
 
-    // Attempt to get the list of available serial ports
-    if let Ok(ports) = available_ports() {
-        for port in ports {
-            // Add all detected port names, regardless of type
-            ports_list.push(port.port_name);
+// Runs a script under the debugger, pausing at `breakpoints` and before the first line with
+// `pause_at_start`. Returns once the script ends or is stopped, the run is recorded like `run_script`
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn debug_script<'a>(
+    content: String,
+    path: Option<PathBuf>,
+    breakpoints: Vec<usize>,
+    pause_at_start: bool,
+    name: Option<String>,
+    note: Option<String>,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+    runs: State<'a, SharedRunHistory>,
+    debugged: State<'a, ScriptDebugger>,
+) -> Result<(), String> {
+    let script = parse_script(&content, path.as_deref())?;
+    let (handle, debugger) = debugger::debugger(breakpoints, pause_at_start);
+    {
+        let mut debugged = debugged.lock().await;
+        if debugged.is_some() {
+            return Err("A script is already being debugged".to_string());
         }
-    } else {
-        println!("Failed to list ports.");
+        *debugged = Some(handle);
     }
 
-    ports_list
+    let mut interpreter = Interpreter::new(robot.inner().clone(), Some(store.inner().clone())).with_debugger(debugger);
+    let result = history::run_recorded(
+        &mut interpreter,
+        &script,
+        name.as_deref().unwrap_or(UNNAMED_SCRIPT),
+        &content,
+        note,
+        &runs,
+        |line, response| {
+            eprintln!("###DEBUG### - Script line {}: {}", line, response.trim());
+        },
+    )
+    .await;
+
+    *debugged.lock().await = None;
+    result
+}
+
+// Recorded script runs, oldest first
+#[tauri::command]
+pub async fn list_runs<'a>(
+    runs: State<'a, SharedRunHistory>,
+) -> Result<Vec<ScriptRun>, String> {
+    Ok(runs.lock().await.list().to_vec())
+}
+
+#[tauri::command]
+pub async fn export_runs<'a>(
+    path: PathBuf,
+    format: ExportFormat,
+    runs: State<'a, SharedRunHistory>,
+) -> Result<(), String> {
+    runs.lock().await.export(&path, format)
+}
+
+#[tauri::command]
+pub async fn debug_command<'a>(
+    command: DebugCommand,
+    debugged: State<'a, ScriptDebugger>,
+) -> Result<(), String> {
+    match debugged.lock().await.as_ref() {
+        Some(handle) => handle.send(command),
+        None => Err("No script is being debugged".to_string()),
+    }
+}
+
+// Replaces the breakpoints of the script being debugged, a no-op when none is
+#[tauri::command]
+pub async fn set_breakpoints<'a>(
+    lines: Vec<usize>,
+    debugged: State<'a, ScriptDebugger>,
+) -> Result<(), String> {
+    if let Some(handle) = debugged.lock().await.as_ref() {
+        handle.set_breakpoints(lines);
+    }
+    Ok(())
+}
+
+// Dry run of a script from `start`, or from where the arm is when left out. Without a connection
+// it starts with every joint calibrated at 0°
+#[tauri::command]
+pub async fn validate_script<'a>(
+    content: String,
+    path: Option<PathBuf>,
+    start: Option<StartPose>,
+    robot: State<'a, Robot>,
+    store: State<'a, SharedPositionStore>,
+) -> Result<ValidationReport, String> {
+    let script = parse_script(&content, path.as_deref())?;
+    let start = match start {
+        Some(start) => start,
+        None if robot.is_connected().await => StartPose::current(&robot).await?,
+        None => StartPose::default(),
+    };
+    let positions = store.lock().await;
+
+    Ok(robot.validate_script(&script, &start, Some(&positions)).await)
 }
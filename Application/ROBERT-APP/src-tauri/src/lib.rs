@@ -1,32 +1,156 @@
 mod commands;
-mod utils;
-mod constants;
-mod state;
 
-use tauri::async_runtime::RwLock;
-use state::SharedAppState;
+use robert_core::calibration;
+use robert_core::poller;
+use robert_core::gcode::GcodeConfig;
+use robert_core::history::RunHistory;
+use robert_core::homing::HomingConfig;
+use robert_core::positions::PositionStore;
+use robert_core::teach::SharedRecording;
+use robert_core::Robot;
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+
+// File in the app data dir holding the named positions
+const POSITIONS_FILE: &str = "positions.json";
+// Optional file in the app data dir with the work object frame and M-codes of G-code imports
+const GCODE_CONFIG_FILE: &str = "gcode.json";
+// Optional file in the app data dir with the calibration order, offsets and home poses of homing
+const HOMING_CONFIG_FILE: &str = "homing.json";
+// File in the app data dir with the zero offsets of the joints
+const ZERO_OFFSETS_FILE: &str = "zero_offsets.json";
+// File in the app data dir recording every script run
+const HISTORY_FILE: &str = "history.json";
+
+// Forwards every event reported by the robot to the frontend
+fn forward_robot_events(app: tauri::AppHandle, robot: &Robot) {
+    let mut receiver = robot.subscribe();
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if let Err(e) = app.emit(event.name(), &event) {
+                        eprintln!("Failed to emit {}: {}", event.name(), e);
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
 
-    let shared_state = SharedAppState::new(RwLock::new(state::AppState::new()));
+    let robot = Robot::new();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(shared_state)
+        .plugin(tauri_plugin_dialog::init())
+        .setup({
+            let robot = robot.clone();
+            move |app| {
+                forward_robot_events(app.handle().clone(), &robot);
+
+                robot.set_poll_interval(Some(poller::DEFAULT_POLL_INTERVAL))?;
+                tauri::async_runtime::spawn(robot.poll_state());
+
+                let positions_path = app.path().app_data_dir()?.join(POSITIONS_FILE);
+                app.manage(Arc::new(Mutex::new(PositionStore::load(positions_path)?)));
+
+                let zero_offsets_path = app.path().app_data_dir()?.join(ZERO_OFFSETS_FILE);
+                let zero_offsets = calibration::load_zero_offsets(&zero_offsets_path)?;
+                tauri::async_runtime::block_on(robot.set_zero_offsets(zero_offsets))?;
+                app.manage(commands::ZeroOffsetsPath(zero_offsets_path));
+
+                let history_path = app.path().app_data_dir()?.join(HISTORY_FILE);
+                app.manage(Arc::new(Mutex::new(RunHistory::load(history_path)?)));
+
+                let gcode_config_path = app.path().app_data_dir()?.join(GCODE_CONFIG_FILE);
+                app.manage(match gcode_config_path.exists() {
+                    true => GcodeConfig::load(&gcode_config_path)?,
+                    false => GcodeConfig::default(),
+                });
+
+                let homing_config_path = app.path().app_data_dir()?.join(HOMING_CONFIG_FILE);
+                app.manage(match homing_config_path.exists() {
+                    true => HomingConfig::load(&homing_config_path)?,
+                    false => HomingConfig::default(),
+                });
+
+                Ok(())
+            }
+        })
+        .manage(robot)
+        .manage(commands::GamepadTask::default())
+        .manage(commands::ScriptDebugger::default())
+        .manage(SharedRecording::default())
         .invoke_handler(tauri::generate_handler![
             commands::connect_to_port, 
             commands::disconnect_from_active_connection,
             commands::move_step, 
+            commands::jog_joint,
             commands::set_acceleration, 
             commands::set_velocity, 
+            commands::set_joint_velocity,
+            commands::set_joint_acceleration,
             commands::get_ports, 
             commands::toggle_stepper,
             commands::calibrate_steppers, 
+            commands::home,
+            commands::list_home_poses,
+            commands::get_zero_offsets,
+            commands::set_zero_offsets,
+            commands::set_zero_here,
             commands::drive_steppers_to_angles,
+            commands::plan_steppers_to_angles,
+            commands::move_linear,
+            commands::move_arc,
+            commands::jog_cartesian,
+            commands::get_tool_pose,
+            commands::start_jog,
+            commands::heartbeat_jog,
+            commands::stop_jog,
+            commands::emergency_stop,
+            commands::start_gamepad,
+            commands::stop_gamepad,
             commands::check_steppers_state,
             commands::get_steppers_angles,
             commands::get_parameters,
+            commands::read_parameters,
+            commands::get_parameter_limits,
+            commands::set_speed_override,
+            commands::get_speed_override,
+            commands::set_poll_interval,
+            commands::get_poll_interval,
+            commands::save_position,
+            commands::list_positions,
+            commands::rename_position,
+            commands::delete_position,
+            commands::go_to_position,
+            commands::import_positions,
+            commands::export_positions,
+            commands::record_waypoint,
+            commands::list_waypoints,
+            commands::set_waypoint_dwell,
+            commands::delete_waypoint,
+            commands::clear_recording,
+            commands::recording_script,
+            commands::export_recording,
+            commands::read_script,
+            commands::check_script,
+            commands::run_script,
+            commands::validate_script,
+            commands::import_gcode,
+            commands::debug_script,
+            commands::list_runs,
+            commands::export_runs,
+            commands::debug_command,
+            commands::set_breakpoints,
             ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,54 +1,10 @@
-import { calibrateStepper, driveStepperToAngle, setAPIAcceleration, setAPIVelocity, toggleStepperState } from '../api/commands';
+// Scripts are parsed and run by the backend (robert-core/src/script.rs), this only reads and highlights them
 
-export interface ParsedInstruction {
-  command: string;
-  params: string[];
-}
-
-export const parseFile = (file: File): Promise<ParsedInstruction[]> => {
+export const readFile = (file: File): Promise<string> => {
   return new Promise((resolve, reject) => {
     const reader = new FileReader();
 
-    // On file load, parse the content
-    reader.onload = () => {
-      const fileContent = reader.result as string;
-      const lines = fileContent.split('\n');
-      const instructions: ParsedInstruction[] = [];
-
-      // Regex patterns to match the commands and capture parameters after the '>'
-      const moveRegex = /^MOVE>(.*)$/;
-      const toggleRegex = /^TOGGLE>(.*)$/;
-      const calibrateRegex = /^CALIBRATE>(.*)$/;
-      const setVelRegex = /^SETVEL>(.*)$/;
-      const setAccRegex = /^SETACC>(.*)$/;
-
-      lines.forEach((line) => {
-        line = line.trim();
-
-        if (line === '' || line.startsWith('//')) {
-          return; // Ignore empty lines and comments
-        }
-
-        // Match against the different command types
-        let match: RegExpExecArray | null;
-
-        if ((match = moveRegex.exec(line))) {
-          instructions.push({ command: 'MOVE', params: match[1].split(';').map((param) => param.trim()).filter(param => param.length > 0) });
-        } else if ((match = toggleRegex.exec(line))) {
-          instructions.push({ command: 'TOGGLE', params: match[1].split(';').map((param) => param.trim()).filter(param => param.length > 0) });
-        } else if ((match = calibrateRegex.exec(line))) {
-          instructions.push({ command: 'CALIBRATE', params: match[1].split(';').map((param) => param.trim()).filter(param => param.length > 0) });
-        } else if ((match = setVelRegex.exec(line))) {
-          instructions.push({ command: 'SETVEL', params: [match[1].trim()] });
-        } else if ((match = setAccRegex.exec(line))) {
-          instructions.push({ command: 'SETACC', params: [match[1].trim()] });
-        } else {
-          reject(new Error(`Invalid command: ${line}`));
-        }
-      });
-
-      resolve(instructions);
-    };
+    reader.onload = () => resolve(reader.result as string);
 
     // Handle errors
     reader.onerror = () => {
@@ -60,42 +16,6 @@ export const parseFile = (file: File): Promise<ParsedInstruction[]> => {
   });
 };
 
-export const executeInstruction = async (instruction: ParsedInstruction) => {
-  switch (instruction.command) {
-    case 'MOVE':
-      const jointsAngles = new Map<number, number>();
-      instruction.params.forEach((param) => {
-        const [joint, angle] = param.split('_');
-        jointsAngles.set(parseInt(joint.replace('J', '')), parseInt(angle));
-      });
-      await driveStepperToAngle(jointsAngles);
-      break;
-
-    case 'TOGGLE':
-      instruction.params.forEach(async (param) => {
-        const [joint, state] = param.split('_');
-        await toggleStepperState(parseInt(joint.replace('J', '')), state);
-      });
-      break;
-
-    case 'CALIBRATE':
-      const jointsToCalibrate = instruction.params.map((param) => parseInt(param.replace('J', '')));
-      await calibrateStepper(jointsToCalibrate);
-      break;
-
-    case 'SETVEL':
-      await setAPIVelocity(Number(instruction.params[0].replace(';','')));
-      break;
-
-    case 'SETACC':
-      await setAPIAcceleration(Number(instruction.params[0].replace(';','')));
-      break;
-
-    default:
-      throw new Error(`Unknown command: ${instruction.command}`);
-  }
-};
-
 export const highlightKeywords = (line: string) => {
   // Define a list of keywords and their respective colors
   const keywords = [
@@ -104,13 +24,21 @@ export const highlightKeywords = (line: string) => {
     { keyword: 'CALIBRATE>', color: 'text-purple-500' },
     { keyword: 'SETVEL>', color: 'text-orange-500' },
     { keyword: 'SETACC>', color: 'text-yellow-500' },
+    { keyword: 'LIN>', color: 'text-pink-500' },
+    { keyword: 'ARC>', color: 'text-pink-500' },
+    { keyword: '\\b(?:WAIT|REPEAT|LET|GOTO_POS|HOME|DEF|END|CALL|INCLUDE)\\b', color: 'text-teal-500' },
   ];
 
+  // Comments are left as they are
+  const commentStart = line.indexOf('//');
+  const comment = commentStart === -1 ? '' : line.slice(commentStart);
+  let code = commentStart === -1 ? line : line.slice(0, commentStart);
+
   // Replace each keyword in the line with a colored span
   keywords.forEach(({ keyword, color }) => {
     const keywordRegex = new RegExp(`(${keyword})`, 'g');
-    line = line.replace(keywordRegex, `<span class="${color}">$1</span>`);
+    code = code.replace(keywordRegex, `<span class="${color}">$1</span>`);
   });
 
-  return line;
+  return comment === '' ? code : `${code}<span class="text-gray-500">${comment}</span>`;
 };
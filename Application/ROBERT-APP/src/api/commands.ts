@@ -1,3 +1,12 @@
+import { CartesianAxis, CartesianPose, CartesianPosition, JogFrame, JogTarget } from "../interfaces/CartesianPose";
+import { JointLimits, JointParameters, MotionParameters } from "../interfaces/MotionParameters";
+import { StoredPosition } from "../interfaces/StoredPosition";
+import { DebugCommand } from "../interfaces/ScriptDebugger";
+import { ExportFormat, ScriptRun } from "../interfaces/ScriptRun";
+import { GcodeImport, ValidationReport } from "../interfaces/ScriptValidation";
+import { TrajectoryPlan } from "../interfaces/TrajectoryPlan";
+import { Waypoint } from "../interfaces/Waypoint";
+
 // @ts-ignore
 export const invoke = window.__TAURI__.core.invoke;
 
@@ -32,6 +41,16 @@ export const setAPIVelocity = async (velocity: number | null | undefined): Promi
   return invoke<string[]>("set_velocity", {velocity });
 };
 
+// Overrides the velocity of a joint in degrees/s, null makes it follow the global velocity again
+export const setJointVelocity = async (jointIndex: number, velocity: number | null): Promise<string> => {
+  return invoke<string>("set_joint_velocity", { jointIndex, velocity });
+};
+
+// Overrides the acceleration of a joint in degrees/s², null makes it follow the global acceleration again
+export const setJointAcceleration = async (jointIndex: number, acceleration: number | null): Promise<string> => {
+  return invoke<string>("set_joint_acceleration", { jointIndex, acceleration });
+};
+
 export const moveStep = async (jointIndex: number | null | undefined, nSteps: number | null | undefined): Promise<string[]> => {
 
   if (jointIndex == null || nSteps == null) {
@@ -41,6 +60,11 @@ export const moveStep = async (jointIndex: number | null | undefined, nSteps: nu
   return invoke<string[]>("move_step", {jointIndex, nSteps:nSteps });
 };
 
+// Moves a joint by an angle in degrees, resolves to the angle reached
+export const jogJoint = async (jointIndex: number, deltaDegrees: number): Promise<number> => {
+  return invoke<number>("jog_joint", { jointIndex, deltaDegrees });
+};
+
 export const toggleStepperState = async (jointIndex: number | null | undefined, enabled: string | null | undefined): Promise<string[]> => {
 
   if (jointIndex == null || enabled == null) {
@@ -60,9 +84,27 @@ export const getSteppersAngles = async (): Promise<number[]> => {
   return invoke<number[]>("get_steppers_angles");
 };
 
-export const getParameters = async (): Promise<number[]> => {
+export const getParameters = async (): Promise<MotionParameters> => {
   
-  return invoke<number[]>("get_parameters");
+  return invoke<MotionParameters>("get_parameters");
+};
+
+// What each joint runs with on the firmware, the speed override included
+export const readParameters = async (): Promise<JointParameters[]> => {
+  return invoke<JointParameters[]>("read_parameters");
+};
+
+// Runs every motion, scripts and jogs included, at a percentage (1 to 100) of the configured speed
+export const setSpeedOverride = async (percent: number): Promise<string> => {
+  return invoke<string>("set_speed_override", { percent });
+};
+
+export const getSpeedOverride = async (): Promise<number> => {
+  return invoke<number>("get_speed_override");
+};
+
+export const getParameterLimits = async (): Promise<JointLimits[]> => {
+  return invoke<JointLimits[]>("get_parameter_limits");
 };
 
 export const calibrateStepper = async (jointsIndexes: number[] | null | undefined): Promise<string[]> => {
@@ -74,6 +116,31 @@ export const calibrateStepper = async (jointsIndexes: number[] | null | undefine
   return invoke<string[]>("calibrate_steppers", { jointsIndexes });
 };
 
+// Calibrates `joints` in the configured order and ends in the home pose `pose`, or the configured one.
+// Each stage reports `report-homing-progress`
+export const home = async (joints?: number[], pose?: string): Promise<string> => {
+  return invoke<string>("home", { joints, pose });
+};
+
+// Degrees from the limit switch to the zero of each joint
+export const getZeroOffsets = async (): Promise<number[]> => {
+  return invoke<number[]>("get_zero_offsets");
+};
+
+export const setZeroOffsets = async (offsets: number[]): Promise<void> => {
+  return invoke<void>("set_zero_offsets", { offsets });
+};
+
+// Makes where `joint` is now its zero, returns the offset from its limit switch
+export const setZeroHere = async (joint: number): Promise<number> => {
+  return invoke<number>("set_zero_here", { joint });
+};
+
+// Names of the configured home poses
+export const listHomePoses = async (): Promise<string[]> => {
+  return invoke<string[]>("list_home_poses");
+};
+
 export const driveStepperToAngle = async (jointsAngles: Map<number,number> | null | undefined): Promise<string[]> => {
 
   if (jointsAngles == null) {
@@ -85,4 +152,183 @@ export const driveStepperToAngle = async (jointsAngles: Map<number,number> | nul
   return invoke<string[]>("drive_steppers_to_angles", { jointsAngles: jointsAnglesArray });
 };
 
+// Preview of a move, with the expected duration in seconds
+export const planStepperToAngle = async (jointsAngles: Map<number,number>): Promise<TrajectoryPlan> => {
+  return invoke<TrajectoryPlan>("plan_steppers_to_angles", { jointsAngles: Array.from(jointsAngles.entries()) });
+};
+
+// Straight line move of the tool, every joint must be calibrated. `speed` in mm/s
+export const moveLinear = async (pose: CartesianPose, speed?: number): Promise<string> => {
+  return invoke<string>("move_linear", { pose, speed });
+};
+
+// Arc move of the tool through `via` to `end`, `speed` in mm/s
+export const moveArc = async (via: CartesianPosition, end: CartesianPose, speed?: number): Promise<string> => {
+  return invoke<string>("move_arc", { via, end, speed });
+};
+
+// Jogs the tool by `increment` mm or degrees, resolves to the new tool pose
+export const jogCartesian = async (axis: CartesianAxis, frame: JogFrame, increment: number): Promise<CartesianPose> => {
+  return invoke<CartesianPose>("jog_cartesian", { axis, frame, increment });
+};
+
+// Disables every stepper, recalibrate before moving again
+export const emergencyStop = async (): Promise<string> => {
+  return invoke<string>("emergency_stop");
+};
+
+// Drives the arm with a game controller, see the gamepad mapping in the README
+export const startGamepad = async (mappingPath: string, device?: string): Promise<void> => {
+  return invoke<void>("start_gamepad", { mappingPath, device });
+};
+
+export const stopGamepad = async (): Promise<void> => {
+  return invoke<void>("stop_gamepad");
+};
+
+export const getToolPose = async (): Promise<CartesianPose> => {
+  return invoke<CartesianPose>("get_tool_pose");
+};
+
+// Starts a continuous jog, `direction` is 1 or -1 and `speed` in degrees/s (mm/s for x, y and z).
+// It stops when heartbeats stop arriving, see heartbeatJog
+export const startJog = async (target: JogTarget, direction: number, speed: number, frame: JogFrame = 'base'): Promise<void> => {
+  return invoke<void>("start_jog", { target, direction, speed, frame });
+};
+
+// Keeps the running jog alive, resolves to whether there is one
+export const heartbeatJog = async (): Promise<boolean> => {
+  return invoke<boolean>("heartbeat_jog");
+};
+
+// Resolves once the arm is at rest
+export const stopJog = async (): Promise<void> => {
+  return invoke<void>("stop_jog");
+};
+
+
+
+// Interval in ms of the backend polling reporting state changes as events, 0 stops it
+export const setPollInterval = async (intervalMs: number): Promise<void> => {
+  return invoke<void>("set_poll_interval", { intervalMs });
+};
 
+export const getPollInterval = async (): Promise<number> => {
+  return invoke<number>("get_poll_interval");
+};
+
+export const savePosition = async (name: string, notes?: string, tool?: string): Promise<StoredPosition> => {
+  return invoke<StoredPosition>("save_position", { name, notes, tool });
+};
+
+export const listPositions = async (): Promise<StoredPosition[]> => {
+  return invoke<StoredPosition[]>("list_positions");
+};
+
+export const renamePosition = async (name: string, newName: string): Promise<void> => {
+  return invoke<void>("rename_position", { name, newName });
+};
+
+export const deletePosition = async (name: string): Promise<void> => {
+  return invoke<void>("delete_position", { name });
+};
+
+export const goToPosition = async (name: string): Promise<string> => {
+  return invoke<string>("go_to_position", { name });
+};
+
+export const importPositions = async (path: string): Promise<number> => {
+  return invoke<number>("import_positions", { path });
+};
+
+export const exportPositions = async (path: string): Promise<void> => {
+  return invoke<void>("export_positions", { path });
+};
+
+// Records where the arm is now as the next teach mode waypoint
+export const recordWaypoint = async (dwell: number | null, label: string | null): Promise<Waypoint> => {
+  return invoke<Waypoint>("record_waypoint", { dwell, label });
+};
+
+export const listWaypoints = async (): Promise<Waypoint[]> => {
+  return invoke<Waypoint[]>("list_waypoints");
+};
+
+export const setWaypointDwell = async (index: number, dwell: number | null): Promise<void> => {
+  return invoke<void>("set_waypoint_dwell", { index, dwell });
+};
+
+export const deleteWaypoint = async (index: number): Promise<void> => {
+  return invoke<void>("delete_waypoint", { index });
+};
+
+export const clearRecording = async (): Promise<void> => {
+  return invoke<void>("clear_recording");
+};
+
+// The recording as a .rob script
+export const recordingScript = async (): Promise<string> => {
+  return invoke<string>("recording_script");
+};
+
+export const exportRecording = async (path: string): Promise<void> => {
+  return invoke<void>("export_recording", { path });
+};
+
+// Content of the file at `path`, as picked in the file dialog
+export const readScript = async (path: string): Promise<string> => {
+  return invoke<string>("read_script", { path });
+};
+
+// Rejects with the first parse error of the script, if any. Scripts with a `path` can INCLUDE the
+// files next to them
+export const checkScript = async (content: string, path?: string): Promise<void> => {
+  return invoke<void>("check_script", { content, path });
+};
+
+// Runs a whole script, each line reports `report-script-progress` before it runs. The run is
+// recorded in the history under `name` with the operator's `note`
+export const runScript = async (content: string, path?: string, name?: string, note?: string): Promise<void> => {
+  return invoke<void>("run_script", { content, path, name, note });
+};
+
+// Recorded script runs, oldest first
+export const listRuns = async (): Promise<ScriptRun[]> => {
+  return invoke<ScriptRun[]>("list_runs");
+};
+
+export const exportRuns = async (path: string, format: ExportFormat): Promise<void> => {
+  return invoke<void>("export_runs", { path, format });
+};
+
+// Dry run of a script from where the arm is, nothing moves
+export const validateScript = async (content: string, path?: string): Promise<ValidationReport> => {
+  return invoke<ValidationReport>("validate_script", { content, path });
+};
+
+// Converts G-code into a .rob script
+export const importGcode = async (content: string): Promise<GcodeImport> => {
+  return invoke<GcodeImport>("import_gcode", { content });
+};
+
+// Runs a script pausing at `breakpoints`, and before its first line with `pauseAtStart`. Each pause
+// reports `report-script-paused`, resolves once the script ends or is stopped
+export const debugScript = async (
+  content: string,
+  path: string | undefined,
+  breakpoints: number[],
+  pauseAtStart: boolean,
+  name?: string,
+  note?: string,
+): Promise<void> => {
+  return invoke<void>("debug_script", { content, path, breakpoints, pauseAtStart, name, note });
+};
+
+export const debugCommand = async (command: DebugCommand): Promise<void> => {
+  return invoke<void>("debug_command", { command });
+};
+
+// Replaces the breakpoints of the script being debugged
+export const setBreakpoints = async (lines: number[]): Promise<void> => {
+  return invoke<void>("set_breakpoints", { lines });
+};
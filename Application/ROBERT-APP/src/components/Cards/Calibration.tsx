@@ -1,9 +1,18 @@
+import { useEffect, useState } from 'react';
+import { listHomePoses } from '../../api/commands';
 import { useConnection } from '../../context/ConnectionContext';
 import { useStepperContext } from '../../context/StepperContext';
+import ZeroOffsets from '../ZeroOffsets';
 
 const Calibration = () => {
   const { isConnected } = useConnection();
-  const { calibrationStates, handleCalibrate, handleCalibrateAll } = useStepperContext();
+  const { calibrationStates, handleCalibrate, handleCalibrateAll, handleHome } = useStepperContext();
+  const [homePoses, setHomePoses] = useState<string[]>([]);
+  const [homePose, setHomePose] = useState<string>(''); // Empty for the configured one
+
+  useEffect(() => {
+    listHomePoses().then(setHomePoses).catch(() => setHomePoses([]));
+  }, []);
 
   return (
     <div style={{ fontFamily: 'nothing' }} className="p-4 space-y-3">
@@ -38,6 +47,36 @@ const Calibration = () => {
           Calibrate All
         </button>
       </div>
+
+      <div className="flex items-center space-x-2">
+        <button
+          onClick={() => handleHome(homePose || undefined)}
+          className={`bg-white border border-gray-300 text-gray-700 px-2 py-1 rounded w-40 text-left hover:bg-gray-50 focus:outline-none ${
+            !isConnected ? 'cursor-not-allowed opacity-50' : ''
+          }`}
+          disabled={!isConnected}
+          title="Calibrate in the configured order and move to the home pose"
+        >
+          Home
+        </button>
+        {homePoses.length > 0 && (
+          <select
+            value={homePose}
+            onChange={(e) => setHomePose(e.target.value)}
+            className="border border-gray-300 rounded px-2 py-1 text-gray-700"
+          >
+            <option value="">Default pose</option>
+            {homePoses.map((pose) => (
+              <option key={pose} value={pose}>
+                {pose}
+              </option>
+            ))}
+          </select>
+        )}
+      </div>
+
+      <hr className="my-4 border-t border-gray-300" />
+      <ZeroOffsets />
     </div>
   );
 };
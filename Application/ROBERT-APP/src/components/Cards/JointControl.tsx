@@ -1,13 +1,14 @@
 import { useConnection } from '../../context/ConnectionContext';
-import { driveStepperToAngle, moveStep } from '../../api/commands';
-import { useState } from 'react';
+import { driveStepperToAngle, heartbeatJog, startJog, stopJog } from '../../api/commands';
+import { useRef, useState } from 'react';
 
 import toast from 'react-hot-toast';
-import { DEFAULT_INCREMENT_STEPS, STEPPER_LIMITS } from '../../constants/steppersContants';
+import { DEFAULT_JOG_SPEED, JOG_HEARTBEAT_PERIOD, STEPPER_LIMITS } from '../../constants/steppersContants';
 
 const JointControl = () => {
   const { isConnected } = useConnection();
   const [jointValues, setJointValues] = useState<(number | string)[]>(Array(6).fill(''));
+  const heartbeatRef = useRef<ReturnType<typeof setInterval> | null>(null);
 
   const handleInputChange = (index: number, value: string) => {
     const newValues = [...jointValues];
@@ -33,28 +34,33 @@ const JointControl = () => {
       .catch((err) => toast.error(err));
   };
 
-  //Individual increase of joint angle
-  const handleJointIncrement = (jointIndex: number) => {
-    if (!isConnected) return;
+  //Joint moves while its button is held, heartbeats keep the jog alive until it is released
+  const handleJogStart = (jointIndex: number, direction: number) => {
+    if (!isConnected || heartbeatRef.current) return;
 
-    moveStep(jointIndex, DEFAULT_INCREMENT_STEPS)
-      .then((res) => console.log(res))
-      .catch((err) => toast.error(err));
+    heartbeatRef.current = setInterval(() => {
+      heartbeatJog().catch((err) => console.log(err));
+    }, JOG_HEARTBEAT_PERIOD);
+
+    startJog({ joint: jointIndex }, direction, DEFAULT_JOG_SPEED).catch((err) => {
+      handleJogStop();
+      toast.error(err);
+    });
   };
 
-  //Individual decrease of joint angle
-  const handleJointDecrement = (jointIndex: number) => {
-    if (!isConnected) return;
+  const handleJogStop = () => {
+    if (!heartbeatRef.current) return;
 
-    moveStep(jointIndex, -DEFAULT_INCREMENT_STEPS)
-      .then((res) => console.log(res))
-      .catch((err) => toast.error(err));
+    clearInterval(heartbeatRef.current);
+    heartbeatRef.current = null;
+
+    stopJog().catch((err) => toast.error(err));
   };
 
   return (
     <div style={{ fontFamily: 'nothing' }} className="h-full w-full flex">
       <div className="w-2/5 p-4 border-r border-gray-300">
-        <h2 className="text-lg font-semibold mb-4">Increment / Decrement Angle</h2>
+        <h2 className="text-lg font-semibold mb-4">Hold to Jog</h2>
 
         <div className="space-y-3">
           {[...Array(6)].map((_, index) => (
@@ -62,7 +68,9 @@ const JointControl = () => {
               <button
                 className={`bg-red-500 text-white text-xl rounded-lg px-3 py-1 w-10 h-10 select-none 
                           ${isConnected ? 'hover:bg-red-600' : 'cursor-not-allowed opacity-50'}`}
-                onClick={() => handleJointDecrement(index + 1)}
+                onPointerDown={() => handleJogStart(index + 1, -1)}
+                onPointerUp={handleJogStop}
+                onPointerLeave={handleJogStop}
                 disabled={!isConnected}
               >
                 -
@@ -71,7 +79,9 @@ const JointControl = () => {
               <button
                 className={`bg-red-500 text-white text-xl rounded-lg px-3 py-1 w-10 h-10 select-none 
                   ${isConnected ? 'hover:bg-red-600' : 'cursor-not-allowed opacity-50'}`}
-                onClick={() => handleJointIncrement(index + 1)}
+                onPointerDown={() => handleJogStart(index + 1, 1)}
+                onPointerUp={handleJogStop}
+                onPointerLeave={handleJogStop}
                 disabled={!isConnected}
               >
                 +
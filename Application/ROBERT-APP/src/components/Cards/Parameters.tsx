@@ -2,15 +2,31 @@ import React from 'react';
 import { useEffect, useState } from 'react';
 import { useConnection } from '../../context/ConnectionContext';
 import { useStepperContext } from '../../context/StepperContext';
+import { POLL_INTERVALS } from '../../constants/steppersContants';
 
 const Parameters = () => {
-  const { acceleration, velocity, setVelocity, setAcceleration } = useStepperContext();
+  const { parameters, parameterLimits, speedOverride, pollInterval, setVelocity, setAcceleration, setSpeedOverride, setPollInterval } = useStepperContext();
   const { isConnected } = useConnection();
 
+  // Joint the sliders apply to (0 to 5), null for every joint without an override
+  const [selectedJoint, setSelectedJoint] = useState<number | null>(null);
+
+  const jointOverride = selectedJoint === null ? null : parameters.joints[selectedJoint];
+  const velocity = jointOverride?.velocity ?? parameters.velocity;
+  const acceleration = jointOverride?.acceleration ?? parameters.acceleration;
+  const hasOverride = jointOverride !== null && (jointOverride.velocity !== null || jointOverride.acceleration !== null);
+
+  // Global values have to suit every joint
+  const limits = selectedJoint === null ? parameterLimits : parameterLimits.filter((limit) => limit.joint === selectedJoint + 1);
+  const maxVelocity = limits.length > 0 ? Math.floor(Math.min(...limits.map((limit) => limit.velocity))) : 100;
+  const maxAcceleration = limits.length > 0 ? Math.floor(Math.min(...limits.map((limit) => limit.acceleration))) : 100;
+
   // **Local state for sliders (prevents direct API calls)**
   const [sliderVelocity, setSliderVelocity] = useState(velocity);
   const [sliderAcceleration, setSliderAcceleration] = useState(acceleration);
 
+  const [sliderSpeedOverride, setSliderSpeedOverride] = useState(speedOverride);
+
   // Track if sliders have been modified
   const [isModified, setIsModified] = useState(false);
 
@@ -25,9 +41,9 @@ const Parameters = () => {
   const debouncedApiCall = (value: number, type: 'velocity' | 'acceleration') => {
     const timeout = setTimeout(() => {
       if (type === 'velocity') {
-        setVelocity(value); // Trigger velocity API call
+        setVelocity(value, selectedJoint ?? undefined); // Trigger velocity API call
       } else {
-        setAcceleration(value); // Trigger acceleration API call
+        setAcceleration(value, selectedJoint ?? undefined); // Trigger acceleration API call
       }
     }, 500); // Wait 500ms after the user stops changing the slider value
 
@@ -66,6 +82,10 @@ const Parameters = () => {
     }
   }, [velocity, acceleration, isConnected]); // Runs when the velocity or acceleration values are updated
 
+  useEffect(() => {
+    setSliderSpeedOverride(speedOverride);
+  }, [speedOverride]);
+
   // Sync local state with context
   useEffect(() => {
     setSliderVelocity(velocity); 
@@ -103,16 +123,56 @@ const Parameters = () => {
     setIsModified(true); // Mark as modified when slider is changed
   };
 
+  const handleJointChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+    setIsModified(false); // Sliders follow the new selection without setting it
+    setSelectedJoint(e.target.value === '' ? null : Number(e.target.value));
+  };
+
+  // Makes the selected joint follow the global values again
+  const handleResetJoint = async () => {
+    if (selectedJoint === null) return;
+
+    setIsModified(false);
+    await setVelocity(null, selectedJoint);
+    await setAcceleration(null, selectedJoint);
+  };
+
   return (
     <div className="space-y-4 p-4 pl-2">
       <div className="flex flex-col space-y-4">
+        {/* Joint the sliders apply to */}
+        <div className="flex items-center">
+          <label className="w-28 font-semibold">Applies to</label>
+          <select
+            value={selectedJoint ?? ''}
+            onChange={handleJointChange}
+            className="ml-2 border border-gray-300 rounded-md px-2 py-1"
+          >
+            <option value="">All joints</option>
+            {parameters.joints.map((_, index) => (
+              <option key={index} value={index}>
+                J{index + 1}
+              </option>
+            ))}
+          </select>
+          {hasOverride && (
+            <button
+              onClick={handleResetJoint}
+              disabled={!isConnected}
+              className="ml-2 px-2 py-1 text-sm border border-gray-300 rounded-md"
+            >
+              Use global
+            </button>
+          )}
+        </div>
+
         {/* Acceleration Slider */}
         <div className="flex items-center">
           <label className="w-28 font-semibold">Acceleration</label>
           <input
             type="range"
-            min="0"
-            max="100"
+            min="1"
+            max={maxAcceleration}
             value={sliderAcceleration}
             onChange={handleAccelerationChange}
             className="w-[150px] h-1 ml-2 appearance-none bg-gray-300 bg-dotted-slider rounded-md"
@@ -121,7 +181,7 @@ const Parameters = () => {
               outline: 'none',
             }}
           />
-          <span className="ml-2 w-8 text-center">{sliderAcceleration}%</span>
+          <span className="ml-2 w-16 text-center">{Math.round(sliderAcceleration)}°/s²</span>
         </div>
 
         {/* Velocity Slider */}
@@ -129,8 +189,8 @@ const Parameters = () => {
           <label className="w-28 font-semibold">Velocity</label>
           <input
             type="range"
-            min="0"
-            max="100"
+            min="1"
+            max={maxVelocity}
             value={sliderVelocity}
             onChange={handleVelocityChange}
             className="w-[150px] h-1 ml-11 appearance-none bg-gray-300 bg-dotted-slider rounded-md"
@@ -139,7 +199,44 @@ const Parameters = () => {
               outline: 'none',
             }}
           />
-          <span className="ml-2 w-8 text-center">{sliderVelocity}%</span>
+          <span className="ml-2 w-16 text-center">{Math.round(sliderVelocity)}°/s</span>
+        </div>
+
+        {/* Speed override, applied to every motion once the slider is released */}
+        <div className="flex items-center">
+          <label className="w-28 font-semibold">Speed</label>
+          <input
+            type="range"
+            min="1"
+            max="100"
+            value={sliderSpeedOverride}
+            disabled={!isConnected}
+            onChange={(e) => setSliderSpeedOverride(Number(e.target.value))}
+            onPointerUp={() => setSpeedOverride(sliderSpeedOverride)}
+            onKeyUp={() => setSpeedOverride(sliderSpeedOverride)}
+            className="w-[150px] h-1 ml-2 appearance-none bg-gray-300 bg-dotted-slider rounded-md"
+            style={{
+              backgroundImage: 'repeating-linear-gradient(90deg, #E5E7EB, #E5E7EB 2px, transparent 2px, transparent 5px)',
+              outline: 'none',
+            }}
+          />
+          <span className="ml-2 w-16 text-center">{sliderSpeedOverride}%</span>
+        </div>
+
+        {/* Rate at which the backend checks the arm for changes */}
+        <div className="flex items-center">
+          <label className="w-28 font-semibold">Poll rate</label>
+          <select
+            value={pollInterval}
+            onChange={(e) => setPollInterval(Number(e.target.value))}
+            className="ml-2 border border-gray-300 rounded-md px-2 py-1"
+          >
+            {POLL_INTERVALS.map((interval) => (
+              <option key={interval} value={interval}>
+                {interval === 0 ? 'Off' : `${interval} ms`}
+              </option>
+            ))}
+          </select>
         </div>
       </div>
     </div>
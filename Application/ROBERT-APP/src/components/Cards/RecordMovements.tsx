@@ -1,5 +1,6 @@
 import RecordPositions from '../RecordPositions';
 import ScriptRunner from '../ScriptRunner';
+import TeachRecorder from '../TeachRecorder';
 
 const RecordMovements = () => {
   return (
@@ -7,6 +8,8 @@ const RecordMovements = () => {
       <ScriptRunner />
       <hr className="my-4 border-t border-gray-300" />
       <RecordPositions />
+      <hr className="my-4 border-t border-gray-300" />
+      <TeachRecorder />
     </div>
   );
 };
@@ -1,120 +1,265 @@
-import React, { useEffect, useState } from 'react';
-import { FaPlay, FaStepForward } from 'react-icons/fa'; // Importing icons
-import { executeInstruction, highlightKeywords, ParsedInstruction, parseFile } from '../Utils/ScriptParserUtils';
+import React, { useEffect, useRef, useState } from 'react';
+import { FaBug, FaForward, FaLevelDownAlt, FaMousePointer, FaPause, FaPlay, FaStepForward, FaStop } from 'react-icons/fa'; // Importing icons
+import { highlightKeywords, readFile } from '../Utils/ScriptParserUtils';
+import { debugCommand, debugScript, runScript, setBreakpoints, validateScript } from '../api/commands';
+import { ValidationReport } from '../interfaces/ScriptValidation';
+import { DebugCommand, ScriptPaused, ScriptProgress } from '../interfaces/ScriptDebugger';
+import { listen } from '@tauri-apps/api/event';
 import toast from 'react-hot-toast';
 
 interface CodeViewerProps {
   file: File | null; // Pass a File object
+  path?: string; // Where the file is, INCLUDE paths are relative to it
 }
 
-const CodeViewer: React.FC<CodeViewerProps> = ({ file }) => {
+const CodeViewer: React.FC<CodeViewerProps> = ({ file, path }) => {
   const [fileContent, setFileContent] = useState<string>(''); // State to store file content
-  const [currentLine, setCurrentLine] = useState<number>(0); // Track the current line being executed
-  const [isRunningLine, setIsRunningLine] = useState<boolean>(false); // Track if the script is running
+  const [currentLine, setCurrentLine] = useState<number>(-1); // Track the current line being executed
+  const [isRunning, setIsRunning] = useState<boolean>(false); // Track if the script is running
+  const [isDebugging, setIsDebugging] = useState<boolean>(false);
+  const [paused, setPaused] = useState<ScriptPaused | null>(null); // Set while a debugged script waits
+  const [breakpoints, setBreakpointLines] = useState<number[]>([]); // Line numbers, from 1
+  const [cursorLine, setCursorLine] = useState<number | null>(null); // Line picked for Run to Cursor
+  const [validation, setValidation] = useState<ValidationReport | null>(null);
+  const [note, setNote] = useState<string>(''); // Recorded with the run in the history
 
-  const [parsedInstructionStack, setParsedInstructionStack] = useState<ParsedInstruction[]>([]);
+  // Only progress of a whole run maps to lines of this file
+  const isRunningScript = useRef<boolean>(false);
 
   const lines = fileContent.split('\n'); // Split content into lines
 
   useEffect(() => {
     if (!file) return;
 
-    const reader = new FileReader();
-
-    // When file is loaded, set content
-    reader.onload = () => {
-      const content = reader.result as string;
-      setFileContent(content); // Set file content to state
-    };
-
-    // Read the file as text
-    reader.readAsText(file);
-
-    parseFile(file)
-      .then((res) => {
-        setParsedInstructionStack(res);
-        setCurrentLine(0);
+    readFile(file)
+      .then((content) => {
+        setFileContent(content);
+        setCurrentLine(-1);
+        setBreakpointLines([]);
+        setCursorLine(null);
+        return validateScript(content, path);
       })
+      .then(setValidation)
       .catch((error) => {
-        toast.error(error.message);
+        toast.error(String(error));
       });
-  }, [file]);
+  }, [file, path]);
 
-  const runAllScript = async () => {
-    setCurrentLine(0); // Reset current line to 0
+  // Problems found on a line of the script
+  const lineDiagnostics = (index: number) =>
+    validation?.diagnostics.filter((diagnostic) => diagnostic.file === null && diagnostic.line === index + 1) ?? [];
 
-    for (let line = 0; line < parsedInstructionStack.length; line++) {
-      try {
-        await runLine(line); // Wait for each line to finish before proceeding
-      } catch (error) {
-        toast.error(String(error));
-        setCurrentLine(0);
-        return; // Stop execution on error
+  useEffect(() => {
+    const unlistenProgress = listen<ScriptProgress>('report-script-progress', (event) => {
+      if (isRunningScript.current) {
+        // Lines of included files show up as the line that got there
+        setCurrentLine(event.payload.scriptLine - 1);
+        setPaused(null);
       }
-    }
-    toast.success('Script finished!');
-  };
+    });
+    const unlistenPaused = listen<ScriptPaused>('report-script-paused', (event) => {
+      setCurrentLine(event.payload.scriptLine - 1);
+      setPaused(event.payload);
+    });
 
-  const runLine = async (lineNumber: number): Promise<void> => {
-    setIsRunningLine(true);
+    return () => {
+      unlistenProgress.then((unlisten) => unlisten());
+      unlistenPaused.then((unlisten) => unlisten());
+    };
+  }, []);
 
-    if (lineNumber >= parsedInstructionStack.length) {
-      toast.success('Script finished!');
-      setCurrentLine(0);
-      return;
-    }
+  // Runs the whole script, under the debugger when `debug` is set
+  const startScript = async (debug: boolean, pauseAtStart: boolean) => {
+    setCurrentLine(-1);
+    setIsRunning(true);
+    setIsDebugging(debug);
+    isRunningScript.current = true;
 
     try {
-      await executeInstruction(parsedInstructionStack[lineNumber]); // Wait for execution to finish
-      setCurrentLine((prevLineCount) => prevLineCount + 1);
+      await (debug
+        ? debugScript(fileContent, path, breakpoints, pauseAtStart, file?.name, note)
+        : runScript(fileContent, path, file?.name, note));
+      toast.success('Script finished!');
     } catch (error) {
       toast.error(String(error));
-      setCurrentLine(0);
-      throw error; // Rethrow error to stop execution in `runAllScript`
     } finally {
-      setIsRunningLine(false);
+      isRunningScript.current = false;
+      setIsRunning(false);
+      setIsDebugging(false);
+      setPaused(null);
+      setCurrentLine(-1);
+    }
+  };
+
+  const sendCommand = (command: DebugCommand) => {
+    debugCommand(command).catch((error) => toast.error(String(error)));
+  };
+
+  // Steps over the current line, starting the debugger paused on the first line if needed
+  const stepOver = () => {
+    if (isDebugging) {
+      sendCommand({ command: 'stepOver' });
+    } else {
+      startScript(true, true);
     }
   };
 
+  const toggleBreakpoint = (line: number) => {
+    const updated = breakpoints.includes(line)
+      ? breakpoints.filter((breakpoint) => breakpoint !== line)
+      : [...breakpoints, line];
+
+    setBreakpointLines(updated);
+    setBreakpoints(updated).catch((error) => toast.error(String(error)));
+  };
+
+  const buttonClass = (enabled: boolean, color: 'gray' | 'red') =>
+    `flex items-center px-4 py-2 rounded-md transition 
+                    ${!enabled ? 'bg-gray-500 text-gray-300 cursor-not-allowed' : color === 'red' ? 'bg-red-600 hover:bg-red-700 text-white' : 'bg-gray-600 hover:bg-gray-700 text-white'}`;
+
   return (
     <div className="w-full max-w-3xl bg-gray-900 text-white rounded-lg p-4 shadow-lg overflow-auto max-h-80vh border border-gray-700">
-      {/* Buttons for running the script */}
-      <div className="flex gap-4 mb-4">
+      <input
+        type="text"
+        value={note}
+        onChange={(e) => setNote(e.target.value)}
+        disabled={isRunning}
+        placeholder="Note for the run history, e.g. batch or operator"
+        className="w-full mb-4 p-2 rounded-md bg-gray-800 border border-gray-700 text-white"
+        style={{ fontFamily: 'nothing' }}
+      />
+
+      {/* Buttons for running and debugging the script */}
+      <div className="flex flex-wrap gap-4 mb-4">
         <button
-          onClick={runAllScript}
-          disabled={isRunningLine}
+          onClick={() => startScript(false, false)}
+          disabled={isRunning}
           style={{ fontFamily: 'nothing' }}
-          className={`flex items-center px-4 py-2 rounded-md transition 
-                    ${isRunningLine ? 'bg-gray-500 text-gray-300 cursor-not-allowed' : 'bg-gray-600 hover:bg-gray-700 text-white'}`}
+          className={buttonClass(!isRunning, 'gray')}
         >
           <FaPlay className="mr-2" />
           Run Script
         </button>
 
         <button
-          onClick={() => runLine(currentLine)}
-          disabled={isRunningLine}
+          onClick={() => startScript(true, false)}
+          disabled={isRunning}
+          style={{ fontFamily: 'nothing' }}
+          className={buttonClass(!isRunning, 'gray')}
+          title="Run until a breakpoint, click line numbers to set them"
+        >
+          <FaBug className="mr-2" />
+          Debug
+        </button>
+
+        <button
+          onClick={stepOver}
+          disabled={isRunning && !paused}
           style={{ fontFamily: 'nothing' }}
-          className={`flex items-center px-4 py-2 rounded-md transition 
-                      ${isRunningLine ? 'bg-red-500 text-gray-300 cursor-not-allowed' : 'bg-red-600 hover:bg-red-700 text-white'}`}
+          className={buttonClass(!isRunning || !!paused, 'red')}
         >
           <FaStepForward className="mr-2" />
-          Run Current Line
+          Step Over
+        </button>
+
+        <button
+          onClick={() => sendCommand({ command: 'stepInto' })}
+          disabled={!paused}
+          style={{ fontFamily: 'nothing' }}
+          className={buttonClass(!!paused, 'red')}
+        >
+          <FaLevelDownAlt className="mr-2" />
+          Step Into
+        </button>
+
+        <button
+          onClick={() => cursorLine !== null && sendCommand({ command: 'runToLine', line: cursorLine + 1 })}
+          disabled={!paused || cursorLine === null}
+          style={{ fontFamily: 'nothing' }}
+          className={buttonClass(!!paused && cursorLine !== null, 'red')}
+          title="Run until the line picked by clicking its code"
+        >
+          <FaMousePointer className="mr-2" />
+          Run to Cursor
+        </button>
+
+        <button
+          onClick={() => sendCommand({ command: paused ? 'continue' : 'pause' })}
+          disabled={!isDebugging}
+          style={{ fontFamily: 'nothing' }}
+          className={buttonClass(isDebugging, 'gray')}
+        >
+          {paused ? <FaForward className="mr-2" /> : <FaPause className="mr-2" />}
+          {paused ? 'Continue' : 'Pause'}
+        </button>
+
+        <button
+          onClick={() => sendCommand({ command: 'stop' })}
+          disabled={!isDebugging}
+          style={{ fontFamily: 'nothing' }}
+          className={buttonClass(isDebugging, 'red')}
+        >
+          <FaStop className="mr-2" />
+          Stop
         </button>
       </div>
 
-      <pre className="p-4 text-sm">
-        {lines.map((line, index) => (
-          <div
-            key={index}
-            className={`flex ${currentLine === index ? 'bg-red-500' : ''}`} // Highlight current line being executed
-          >
-            {/* Line number */}
-            <span className="text-gray-500 pr-4 w-10 text-right">{index + 1}</span>
-            {/* Code line with highlighted keywords */}
-            <span className="whitespace-pre-wrap" dangerouslySetInnerHTML={{ __html: highlightKeywords(line) }}></span>
+      {validation && (
+        <div className="text-sm mb-2" style={{ fontFamily: 'nothing' }}>
+          Estimated duration: {validation.estimatedDuration.toFixed(1)} s
+          {validation.diagnostics.length > 0 && ` · ${validation.diagnostics.length} problem(s) found`}
+        </div>
+      )}
+
+      {/* What the paused script sees */}
+      {paused && (
+        <div className="text-sm mb-2 border border-gray-700 rounded-md p-2" style={{ fontFamily: 'nothing' }}>
+          <div>
+            Paused at line {paused.line}
+            {paused.file !== null && ` of ${paused.file}`}
+            {paused.callStack.length > 0 && ` in ${paused.callStack.map((site) => site.name).join(' > ')}`}
+          </div>
+          <div>
+            Variables:{' '}
+            {Object.keys(paused.variables).length > 0
+              ? Object.entries(paused.variables).map(([name, value]) => `${name} = ${value}`).join(', ')
+              : 'none'}
+          </div>
+          <div>
+            Joints:{' '}
+            {paused.angles.map((angle, i) => `J${i + 1} ${angle === null ? '-' : `${angle.toFixed(1)}°`}`).join(', ')}
           </div>
-        ))}
+        </div>
+      )}
+
+      <pre className="p-4 text-sm">
+        {lines.map((line, index) => {
+          const diagnostics = lineDiagnostics(index);
+          const hasError = diagnostics.some((diagnostic) => diagnostic.severity === 'error');
+
+          return (
+            <div
+              key={index}
+              className={`flex ${currentLine === index ? 'bg-red-500' : cursorLine === index ? 'bg-gray-700' : ''}`} // Highlight current line being executed
+              title={diagnostics.map((diagnostic) => diagnostic.message).join('\n')}
+            >
+              {/* Line number, marked when the dry run found a problem. Clicking it toggles a breakpoint */}
+              <span
+                onClick={() => toggleBreakpoint(index + 1)}
+                className={`pr-4 w-10 text-right cursor-pointer ${hasError ? 'text-red-400' : diagnostics.length > 0 ? 'text-yellow-400' : 'text-gray-500'}`}
+              >
+                {breakpoints.includes(index + 1) ? '\u25CF ' : ''}
+                {index + 1}
+              </span>
+              {/* Code line with highlighted keywords, clicking it picks the line for Run to Cursor */}
+              <span
+                onClick={() => setCursorLine(index)}
+                className="whitespace-pre-wrap"
+                dangerouslySetInnerHTML={{ __html: highlightKeywords(line) }}
+              ></span>
+            </div>
+          );
+        })}
       </pre>
     </div>
   );
@@ -2,15 +2,12 @@ import { useEffect, useState } from 'react';
 import PlayButton from './PlayButton';
 import SelectComponent from './SelectComponent';
 import { useConnection } from '../context/ConnectionContext';
-import { useStepperContext } from '../context/StepperContext';
-import { driveStepperToAngle } from '../api/commands';
+import { deletePosition, goToPosition, listPositions, savePosition } from '../api/commands';
 import toast from 'react-hot-toast';
-import { deletePosition, getStoredPositionsIDs, storePosition } from '../Utils/LocalStorageUtils';
 import DeleteButton from './DeleteButton';
 
 const RecordPositions = () => {
   const { isConnected } = useConnection();
-  const { angles } = useStepperContext();
 
   const [currentPosID, setCurrentPosID] = useState<string | null>(null);
   const [storedPositionIDs, setStoredPositionsIDs] = useState<string[]>([]);
@@ -20,26 +17,33 @@ const RecordPositions = () => {
   }, []);
 
   const updateStoredPositionsID = () => {
-    setStoredPositionsIDs(getStoredPositionsIDs());
+    listPositions()
+      .then((positions) => setStoredPositionsIDs(positions.map((position) => position.name)))
+      .catch((err) => toast.error(err));
   };
 
   const handleStorePosition = () => {
-    storePosition(angles).then((res) => {
-      if (res) {
+    // Saving under an existing name replaces it, so pick the first free pos_N
+    let index = 0;
+    while (storedPositionIDs.includes(`pos_${index}`)) index++;
+
+    savePosition(`pos_${index}`)
+      .then(() => {
         toast.success('Position stored successfully!');
         updateStoredPositionsID();
-      }
-      else toast.error('Failed to store position');
-    });
+      })
+      .catch((err) => toast.error(err));
   };
 
   const handleDeletePosition = () => {
-    deletePosition(currentPosID).then((res) => {
-      if (res) {
+    if (currentPosID === null) return;
+
+    deletePosition(currentPosID)
+      .then(() => {
         updateStoredPositionsID();
         setCurrentPosID(null);
-      } else toast.error('Failed to delete position');
-    });
+      })
+      .catch((err) => toast.error(err));
   };
 
   const handleChangePosition = (value: string) => {
@@ -48,26 +52,11 @@ const RecordPositions = () => {
 
   const handleDriveToSelectedPosition = () => {
     if (currentPosID) {
-      const storedAngle = localStorage.getItem(currentPosID);
-      if (storedAngle) {
-
-        const parsedAngle = JSON.parse(storedAngle);
-        const jointAngles = new Map<number, number>();
-
-        // Iterate over the object using Object.entries
-        Object.entries(parsedAngle).forEach(([key, value]) => {
-          const idx = Number(key); // Convert the key to a number
-          if (value !== null) {
-            jointAngles.set(idx + 1, Number(value)); // Joint numbers are 1-based
-          }
-        });
-
-        driveStepperToAngle(jointAngles)
-          .then((res) => {
-            console.log(res);
-          })
-          .catch((err) => toast.error(err));
-      }
+      goToPosition(currentPosID)
+        .then((res) => {
+          console.log(res);
+        })
+        .catch((err) => toast.error(err));
     } else {
       toast.error('No position selected');
     }
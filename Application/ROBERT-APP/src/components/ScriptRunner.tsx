@@ -5,12 +5,14 @@ import UploadFile from './UploadFile';
 import toast from 'react-hot-toast';
 import { useConnection } from '../context/ConnectionContext';
 import ScriptRunnerModal from './ScriptRunnerModal';
-import { parseFile } from '../Utils/ScriptParserUtils';
+import { readFile } from '../Utils/ScriptParserUtils';
+import { checkScript } from '../api/commands';
 
 const ScriptRunner = () => {
   const { isConnected } = useConnection();
 
   const [file, setFile] = useState<File | null>(null);
+  const [path, setPath] = useState<string | undefined>(undefined);
   const [isModalOpen, setIsModalOpen] = useState<boolean>(false);
 
   const handleCloseModal = () => {
@@ -24,21 +26,23 @@ const ScriptRunner = () => {
     }
 
     //parse file contents to check for errors
-    parseFile(file)
+    readFile(file)
+      .then((content) => checkScript(content, path))
       .then(() => setIsModalOpen(true))
       .catch((error) => {
-        toast.error(error.message);
+        toast.error(String(error));
         return;
       });
   };
 
-  const onFileUpload = (file: File | null): void => {
+  const onFileUpload = (file: File | null, path?: string): void => {
     setFile(file);
+    setPath(path);
   };
 
   return (
     <>
-      <ScriptRunnerModal modalIsOpen={isModalOpen} closeModal={handleCloseModal} file={file} />
+      <ScriptRunnerModal modalIsOpen={isModalOpen} closeModal={handleCloseModal} file={file} path={path} />
       <div className="flex justify-between items-start w-full gap-8 h-[70px]">
         <UploadFile onFileUpload={onFileUpload} file={file} />
         {/* TODO: disable based on isConnected */}
@@ -9,6 +9,7 @@ interface ScriptRunnerModalProps {
   afterOpenModal?: () => void;
   closeModal: () => void;
   file: File | null;
+  path?: string;
 }
 
 const customStyles: Modal.Styles = {
@@ -30,7 +31,7 @@ const customStyles: Modal.Styles = {
   },
 };
 
-const ScriptRunnerModal: React.FC<ScriptRunnerModalProps> = ({ modalIsOpen, afterOpenModal, closeModal, file }) => {
+const ScriptRunnerModal: React.FC<ScriptRunnerModalProps> = ({ modalIsOpen, afterOpenModal, closeModal, file, path }) => {
   return (
     <Modal
       isOpen={modalIsOpen}
@@ -59,7 +60,7 @@ const ScriptRunnerModal: React.FC<ScriptRunnerModalProps> = ({ modalIsOpen, afte
             {/* Title */}
             <h2 className="text-2xl font-semibold text-gray-800 mb-4" style={{ fontFamily: 'nothing' }}>Run Script</h2>
 
-            <CodeViewer file={file} />
+            <CodeViewer file={file} path={path} />
           </motion.div>
         )}
       </AnimatePresence>
@@ -0,0 +1,132 @@
+import { useEffect, useState } from 'react';
+import PlayButton from './PlayButton';
+import DeleteButton from './DeleteButton';
+import { useConnection } from '../context/ConnectionContext';
+import { clearRecording, deleteWaypoint, listWaypoints, recordWaypoint, recordingScript, runScript } from '../api/commands';
+import { Waypoint } from '../interfaces/Waypoint';
+import toast from 'react-hot-toast';
+
+// Teach mode: jog the arm, record waypoints and play them back or save them as a .rob script
+const TeachRecorder = () => {
+  const { isConnected } = useConnection();
+
+  const [waypoints, setWaypoints] = useState<Waypoint[]>([]);
+  const [dwell, setDwell] = useState<string>(''); // Milliseconds, empty for none
+  const [label, setLabel] = useState<string>('');
+  const [isPlaying, setIsPlaying] = useState<boolean>(false);
+
+  useEffect(() => {
+    updateWaypoints();
+  }, []);
+
+  const updateWaypoints = () => {
+    listWaypoints()
+      .then(setWaypoints)
+      .catch((err) => toast.error(String(err)));
+  };
+
+  const handleRecord = () => {
+    recordWaypoint(dwell === '' ? null : Number(dwell), label === '' ? null : label)
+      .then(() => {
+        setLabel('');
+        updateWaypoints();
+      })
+      .catch((err) => toast.error(String(err)));
+  };
+
+  // Drops the last waypoint, the usual fix after recording one by mistake
+  const handleUndo = () => {
+    if (waypoints.length === 0) return;
+
+    deleteWaypoint(waypoints.length - 1)
+      .then(updateWaypoints)
+      .catch((err) => toast.error(String(err)));
+  };
+
+  const handleClear = () => {
+    clearRecording()
+      .then(updateWaypoints)
+      .catch((err) => toast.error(String(err)));
+  };
+
+  const handlePlay = async () => {
+    setIsPlaying(true);
+
+    try {
+      await runScript(await recordingScript());
+      toast.success('Playback finished!');
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setIsPlaying(false);
+    }
+  };
+
+  // The webview cannot pick where to save, so the script goes through a download
+  const handleSave = () => {
+    recordingScript()
+      .then((script) => {
+        const url = URL.createObjectURL(new Blob([script], { type: 'text/plain' }));
+        const link = document.createElement('a');
+        link.href = url;
+        link.download = 'teach.rob';
+        link.click();
+        URL.revokeObjectURL(url);
+      })
+      .catch((err) => toast.error(String(err)));
+  };
+
+  return (
+    <div className="flex flex-col gap-4 w-full max-w-md">
+      <div className="flex gap-2 w-full">
+        <input
+          type="text"
+          value={label}
+          onChange={(e) => setLabel(e.target.value)}
+          placeholder="Label"
+          className="flex-1 p-2 border border-gray-300 rounded-md hover:border-blue-400 hover:bg-blue-50"
+        />
+        <input
+          type="number"
+          min={0}
+          value={dwell}
+          onChange={(e) => setDwell(e.target.value)}
+          placeholder="Dwell (ms)"
+          className="w-28 p-2 border border-gray-300 rounded-md text-center hover:border-blue-400 hover:bg-blue-50"
+        />
+      </div>
+
+      <button
+        className={`text-white font-medium py-2 rounded-md w-full 
+            ${isConnected ? 'bg-gray-600 hover:bg-gray-700' : 'bg-gray-400 cursor-not-allowed'}`}
+        onClick={handleRecord}
+        disabled={!isConnected}
+      >
+        Record Waypoint ({waypoints.length})
+      </button>
+
+      <div className="flex justify-between items-center w-full gap-2">
+        <button
+          className={`flex-1 text-white font-medium py-2 rounded-md 
+              ${waypoints.length > 0 ? 'bg-gray-600 hover:bg-gray-700' : 'bg-gray-400 cursor-not-allowed'}`}
+          onClick={handleUndo}
+          disabled={waypoints.length === 0}
+        >
+          Undo
+        </button>
+        <button
+          className={`flex-1 text-white font-medium py-2 rounded-md 
+              ${waypoints.length > 0 ? 'bg-gray-600 hover:bg-gray-700' : 'bg-gray-400 cursor-not-allowed'}`}
+          onClick={handleSave}
+          disabled={waypoints.length === 0}
+        >
+          Save .rob
+        </button>
+        <DeleteButton disabled={waypoints.length === 0} onClick={handleClear} />
+        <PlayButton disabled={!isConnected || isPlaying || waypoints.length === 0} onClick={handlePlay} />
+      </div>
+    </div>
+  );
+};
+
+export default TeachRecorder;
@@ -1,27 +1,72 @@
 import React, { useState } from 'react';
+import toast from 'react-hot-toast';
+import { open } from '@tauri-apps/plugin-dialog';
+import { importGcode, readScript } from '../api/commands';
+
+// G-code is converted to a .rob script when uploaded
+const GCODE_EXTENSION = /\.(gcode|nc|ngc)$/i;
 
 interface UploadFileProps {
-  onFileUpload: (file: File | null) => void; // This is the function to send file to the parent component
+  onFileUpload: (file: File | null, path?: string) => void; // This is the function to send file to the parent component
   file: File | null; // This is the file state from the parent component
 }
 
 const UploadFile: React.FC<UploadFileProps> = ({ onFileUpload,file }) => {
   const [error, setError] = useState<string>(''); // New state to track error messages
 
-  const handleFileChange = (event: React.ChangeEvent<HTMLInputElement>) => {
-    const selectedFile = event.target.files ? event.target.files[0] : null;
-  
-    if (selectedFile && selectedFile.name.endsWith('.rob')) {
-      setError('');
-      onFileUpload(selectedFile);
-      event.target.value = ''; // Reset input value to allow re-upload of the same file
+  // Picked through the native dialog, a file input does not tell where the file is and INCLUDE needs it
+  const handleOpenFile = async () => {
+    const path = await open({
+      multiple: false,
+      directory: false,
+      filters: [{ name: 'Scripts and G-code', extensions: ['rob', 'gcode', 'nc', 'ngc'] }],
+    });
+    if (!path) return;
+
+    const name = path.split(/[\\/]/).pop() ?? path;
+
+    if (name.endsWith('.rob')) {
+      readScript(path)
+        .then((content) => {
+          setError('');
+          onFileUpload(new File([content], name), path);
+        })
+        .catch((err) => {
+          setError(String(err));
+          onFileUpload(null);
+        });
+    } else if (GCODE_EXTENSION.test(name)) {
+      handleGcode(path, name);
     } else {
-      setError('Please upload a .rob file');
+      setError('Please upload a .rob or G-code file');
       onFileUpload(null);
-      event.target.value = ''; // Reset input value to clear previous selection
     }
   };
-  
+
+  // The converted script is treated as if it were saved next to the G-code
+  const handleGcode = (path: string, name: string) => {
+    readScript(path)
+      .then(importGcode)
+      .then(({ script, diagnostics }) => {
+        const errors = diagnostics.filter((diagnostic) => diagnostic.severity === 'error');
+        diagnostics
+          .filter((diagnostic) => diagnostic.severity === 'warning')
+          .forEach((diagnostic) => toast(`Line ${diagnostic.line}: ${diagnostic.message}`));
+
+        if (errors.length > 0) {
+          setError(errors.map((diagnostic) => `Line ${diagnostic.line}: ${diagnostic.message}`).join('\n'));
+          onFileUpload(null);
+          return;
+        }
+
+        setError('');
+        onFileUpload(new File([script], name.replace(GCODE_EXTENSION, '.rob')), path.replace(GCODE_EXTENSION, '.rob'));
+      })
+      .catch((err) => {
+        setError(String(err));
+        onFileUpload(null);
+      });
+  };
 
   const handleRemoveFile = () => {
     setError(''); // Reset error message
@@ -30,8 +75,8 @@ const UploadFile: React.FC<UploadFileProps> = ({ onFileUpload,file }) => {
 
   return (
     <div className="flex flex-col items-center gap-1 flex-1 w-max">
-      <label
-        htmlFor="uploadFile1"
+      <button
+        onClick={handleOpenFile}
         className={`flex bg-gray-600 hover:bg-gray-700 text-white text-base font-medium px-4 py-2.5 outline-none rounded-md w-full cursor-pointer ${
           file ? 'hidden' : ''
         }`}
@@ -41,14 +86,7 @@ const UploadFile: React.FC<UploadFileProps> = ({ onFileUpload,file }) => {
           <path d="M20.293 19.707a1 1 0 0 0 1.414-1.414l-5-5a1 1 0 0 0-1.414 0l-5 5a1 1 0 0 0 1.414 1.414L15 16.414V29a1 1 0 0 0 2 0V16.414z" />
         </svg>
         Upload File
-        <input
-          type="file"
-          id="uploadFile1"
-          className="hidden"
-          onChange={handleFileChange}
-          accept=".rob" // Accept only .rob files
-        />
-      </label>
+      </button>
 
       {file ? (
         <div className="flex items-center justify-between w-full max-w-[200px] mt-2">
@@ -58,7 +96,7 @@ const UploadFile: React.FC<UploadFileProps> = ({ onFileUpload,file }) => {
           </button>
         </div>
       ) : (
-        <p className={`text-sm ${error ? 'text-red-500' : 'text-gray-500'} mr-4`}>{error || 'Please upload a .rob file'}</p>
+        <p className={`text-sm whitespace-pre-line ${error ? 'text-red-500' : 'text-gray-500'} mr-4`}>{error || 'Please upload a .rob or G-code file'}</p>
       )}
     </div>
   );
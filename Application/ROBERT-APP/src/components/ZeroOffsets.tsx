@@ -0,0 +1,81 @@
+import { useEffect, useState } from 'react';
+import toast from 'react-hot-toast';
+import { getZeroOffsets, setZeroHere, setZeroOffsets } from '../api/commands';
+import { useConnection } from '../context/ConnectionContext';
+import { useStepperContext } from '../context/StepperContext';
+
+// Guided capture of the zero offsets: calibrate the joint, jog it to its zero mark, then set the zero there
+const ZeroOffsets = () => {
+  const { isConnected } = useConnection();
+  const { fetchSteppersAngles } = useStepperContext();
+  const [offsets, setOffsets] = useState<number[]>([0, 0, 0, 0, 0, 0]);
+  const [joint, setJoint] = useState<number>(1);
+
+  useEffect(() => {
+    getZeroOffsets().then(setOffsets).catch((err) => toast.error(String(err)));
+  }, []);
+
+  const handleSetZero = () => {
+    setZeroHere(joint)
+      .then((offset) => {
+        setOffsets((prev) => prev.map((value, i) => (i === joint - 1 ? offset : value)));
+        toast.success(`J${joint} zero set ${offset.toFixed(2)}° from its limit switch`);
+        fetchSteppersAngles();
+      })
+      .catch((err) => toast.error(String(err)));
+  };
+
+  const handleReset = () => {
+    const updated = offsets.map((value, i) => (i === joint - 1 ? 0 : value));
+
+    setZeroOffsets(updated)
+      .then(() => {
+        setOffsets(updated);
+        fetchSteppersAngles();
+      })
+      .catch((err) => toast.error(String(err)));
+  };
+
+  return (
+    <div className="space-y-2">
+      <p className="text-xs text-gray-500">
+        Calibrate the joint, jog it until it lines up with its zero mark, then set its zero there.
+      </p>
+
+      <div className="flex items-center space-x-2">
+        <select
+          value={joint}
+          onChange={(e) => setJoint(Number(e.target.value))}
+          className="border border-gray-300 rounded px-2 py-1 text-gray-700"
+        >
+          {offsets.map((offset, i) => (
+            <option key={i} value={i + 1}>
+              J{i + 1} ({offset.toFixed(2)}°)
+            </option>
+          ))}
+        </select>
+      </div>
+
+      <div className="flex items-center space-x-2">
+        <button
+          onClick={handleSetZero}
+          className={`bg-white border border-gray-300 text-gray-700 px-2 py-1 rounded hover:bg-gray-50 focus:outline-none ${
+            !isConnected ? 'cursor-not-allowed opacity-50' : ''
+          }`}
+          disabled={!isConnected}
+        >
+          Set zero here
+        </button>
+        <button
+          onClick={handleReset}
+          className="bg-white border border-gray-300 text-gray-700 px-2 py-1 rounded hover:bg-gray-50 focus:outline-none"
+          title="Measure the joint from its limit switch again"
+        >
+          Reset
+        </button>
+      </div>
+    </div>
+  );
+};
+
+export default ZeroOffsets;
@@ -1,4 +1,11 @@
-export const DEFAULT_INCREMENT_STEPS = 10;
+// Speed in degrees/s of the continuous jog started by holding a joint control button
+export const DEFAULT_JOG_SPEED = 10;
+
+// Period in ms of the heartbeats keeping a continuous jog alive, the backend stops it after 500 ms without one
+export const JOG_HEARTBEAT_PERIOD = 150;
+
+// Intervals in ms offered for the backend state polling, 0 turns it off
+export const POLL_INTERVALS = [0, 250, 500, 1000, 2000];
 
 export const STEPPER_LIMITS: Record<number, number> = {
   1: 270,
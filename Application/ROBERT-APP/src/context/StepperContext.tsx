@@ -1,27 +1,42 @@
-import React, { createContext, useState, useContext, ReactNode } from 'react';
+import React, { createContext, useState, useContext, useEffect, ReactNode } from 'react';
 import {
   checkSteppersState,
   getParameters,
+  getParameterLimits,
+  getSpeedOverride,
+  setSpeedOverride,
   getSteppersAngles,
   toggleStepperState,
   setAPIAcceleration,
   setAPIVelocity,
+  setJointAcceleration,
+  setJointVelocity,
   calibrateStepper,
+  home,
+  getPollInterval,
+  setPollInterval,
 } from '../api/commands';
 import { listen } from '@tauri-apps/api/event';
 import { SteppersAngles } from '../interfaces/SteppersAngles';
+import { PositionDrift } from '../interfaces/PositionDrift';
+import { JointLimits, MotionParameters } from '../interfaces/MotionParameters';
 import toast from 'react-hot-toast';
 import { CalibrationStates } from '../constants/steppersContants';
+import { HomingProgress } from '../interfaces/HomingProgress';
 
 interface StepperState {
   states: Record<number, boolean>; // Maps joint ID to state
   angles: Record<number, number | null>; // Maps joint ID to angles
   calibrationStates: Record<number, CalibrationStates>; // Tracks if stepper is calibrated
-  velocity: number;
-  acceleration: number;
+  parameters: MotionParameters; // Global and per joint velocity (°/s) and acceleration (°/s²)
+  parameterLimits: JointLimits[];
+  speedOverride: number; // Percentage of the configured speed every motion runs at
+  pollInterval: number;
   setStates: (jointId: number, state: boolean) => void;
-  setVelocity: (velocity: number) => void;
-  setAcceleration: (acceleration: number) => void;
+  setVelocity: (velocity: number | null, jointId?: number) => Promise<void>; // null clears the joint override
+  setAcceleration: (acceleration: number | null, jointId?: number) => Promise<void>;
+  setPollInterval: (intervalMs: number) => Promise<void>;
+  setSpeedOverride: (percent: number) => Promise<void>;
   fetchSteppersState: () => Promise<void>;
   fetchSteppersAngles: () => Promise<void>;
   resetStepperState: () => void;
@@ -29,6 +44,7 @@ interface StepperState {
   toggleStepper: (jointId: number) => Promise<void>;
   handleCalibrate: (index: number) => void;
   handleCalibrateAll: () => void;
+  handleHome: (pose?: string) => void;
 }
 
 const StepperContext = createContext<StepperState | undefined>(undefined);
@@ -50,8 +66,14 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
 
   const [states, setStates] = useState<Record<number, boolean>>(Object.fromEntries([...Array(6)].map((_, i) => [i, false])));
 
-  const [acceleration, setAcceleration] = useState(50);
-  const [velocity, setVelocity] = useState(50);
+  const [parameters, setParameters] = useState<MotionParameters>({
+    velocity: 30,
+    acceleration: 30,
+    joints: [...Array(6)].map(() => ({ velocity: null, acceleration: null })),
+  });
+  const [parameterLimits, setParameterLimits] = useState<JointLimits[]>([]);
+  const [speedOverride, setSpeedOverrideState] = useState(100);
+  const [pollInterval, setPollIntervalState] = useState(0);
 
   const [calibrationStates, setCalibrationStates] = useState<Record<number, CalibrationStates>>(
     Object.fromEntries([...Array(6)].map((_, i) => [i, CalibrationStates.NOT_CALIBRATED]))
@@ -94,9 +116,9 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
 
   const fetchParameters = async () => {
     try {
-      const params: number[] = await getParameters();
-      setVelocity(params[0]);
-      setAcceleration(params[1]);
+      setParameters(await getParameters());
+      setParameterLimits(await getParameterLimits());
+      setSpeedOverrideState(await getSpeedOverride());
     } catch (error) {
       toast.error('Error fetching parameters');
     }
@@ -148,27 +170,92 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
       
   };
 
+  // Homes the same joints as Calibrate All, showing each stage as it starts
+  const handleHome = (pose?: string) => {
+    const joints = [1, 2, 3, 4];
+    const toastId = toast.loading('Homing...');
+    for (const joint of joints) {
+      updateCalibrationState(joint - 1, CalibrationStates.CALIBRATING);
+    }
+
+    const unlisten = listen<HomingProgress>('report-homing-progress', (event) => {
+      const { stage, joints, pose, step, steps } = event.payload;
+      const names = joints.map((joint) => `J${joint}`).join(', ');
+      const messages = {
+        calibrating: `Calibrating ${names}`,
+        offsetting: `Moving ${names} off the limit switches`,
+        movingToPose: `Moving to home pose ${pose}`,
+        done: 'Homing done',
+      };
+      toast.loading(`[${Math.min(step + 1, steps)}/${steps}] ${messages[stage]}`, { id: toastId });
+    });
+
+    home(joints, pose)
+      .then((res) => {
+        toast.success(res, { id: toastId });
+        for (const joint of joints) {
+          updateCalibrationState(joint - 1, CalibrationStates.CALIBRATED);
+        }
+      })
+      .catch((err) => {
+        toast.error(String(err), { id: toastId });
+        for (const joint of joints) {
+          updateCalibrationState(joint - 1, CalibrationStates.NOT_CALIBRATED);
+        }
+      })
+      .finally(() => {
+        unlisten.then((unlisten) => unlisten());
+        fetchSteppersAngles();
+      });
+  };
+
   const initializeSteppersInfo = async () => {
     await fetchSteppersState();
     await fetchSteppersAngles();
     await fetchParameters();
   };
 
-  const updateVelocity = async (vel: number) => {
+  // @jointId: from 0 to 5, the global velocity is set without it
+  const updateVelocity = async (vel: number | null, jointId?: number) => {
+    try {
+      if (jointId === undefined) {
+        await setAPIVelocity(vel);
+      } else {
+        await setJointVelocity(jointId + 1, vel);
+      }
+      setParameters(await getParameters());
+    } catch (error) {
+      toast.error(`Error updating velocity: ${error}`);
+    }
+  };
+
+  const updateAcceleration = async (acc: number | null, jointId?: number) => {
     try {
-      setAPIVelocity(vel);
-      setVelocity(vel);
+      if (jointId === undefined) {
+        await setAPIAcceleration(acc);
+      } else {
+        await setJointAcceleration(jointId + 1, acc);
+      }
+      setParameters(await getParameters());
     } catch (error) {
-      toast.error('Error updating velocity');
+      toast.error(`Error updating acceleration: ${error}`);
     }
   };
 
-  const updateAcceleration = async (acc: number) => {
+  const updatePollInterval = async (intervalMs: number) => {
     try {
-      setAPIAcceleration(acc);
-      setAcceleration(acc);
+      await setPollInterval(intervalMs);
+      setPollIntervalState(intervalMs);
     } catch (error) {
-      toast.error('Error updating acceleration');
+      toast.error(`Error updating poll rate: ${error}`);
+    }
+  };
+
+  const updateSpeedOverride = async (percent: number) => {
+    try {
+      await setSpeedOverride(percent);
+    } catch (error) {
+      toast.error(`Error updating speed override: ${error}`);
     }
   };
 
@@ -191,19 +278,55 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
     setCalibrationStates(Object.fromEntries([...Array(6)].map((_, i) => [i, CalibrationStates.NOT_CALIBRATED])));
   };
 
-  // Listen for stepper angles update event
-  listen<SteppersAngles>('report-steppers-angles', (event) => {
-    const { j1, j2, j3, j4, j5, j6 } = event.payload;
-  
-    setAngles({
-      0: j1 !== null ? Math.abs(j1) : null, 
-      1: j2 !== null ? Math.abs(j2) : null, 
-      2: j3 !== null ? Math.abs(j3) : null, 
-      3: j4 !== null ? Math.abs(j4) : null, 
-      4: j5 !== null ? Math.abs(j5) : null, 
-      5: j6 !== null ? Math.abs(j6) : null, 
+  // The backend polls the arm and reports angles, states and parameters whenever they change
+  useEffect(() => {
+    getPollInterval().then(setPollIntervalState).catch(() => {});
+
+    const unlistenAngles = listen<SteppersAngles>('report-steppers-angles', (event) => {
+      const { j1, j2, j3, j4, j5, j6 } = event.payload;
+
+      setAngles({
+        0: j1 !== null ? Math.abs(j1) : null,
+        1: j2 !== null ? Math.abs(j2) : null,
+        2: j3 !== null ? Math.abs(j3) : null,
+        3: j4 !== null ? Math.abs(j4) : null,
+        4: j5 !== null ? Math.abs(j5) : null,
+        5: j6 !== null ? Math.abs(j6) : null,
+      });
     });
-  });
+
+    const unlistenStates = listen<boolean[]>('report-steppers-state', (event) => {
+      setStates(Object.fromEntries(event.payload.map((state, index) => [index, state])));
+    });
+
+    const unlistenParams = listen<MotionParameters>('report-params', (event) => {
+      setParameters(event.payload);
+    });
+
+    // Sent whenever the override changes, whoever changed it
+    const unlistenSpeedOverride = listen<number>('report-speed-override', (event) => {
+      setSpeedOverrideState(event.payload);
+    });
+
+    return () => {
+      unlistenAngles.then((stop) => stop());
+      unlistenStates.then((stop) => stop());
+      unlistenParams.then((stop) => stop());
+      unlistenSpeedOverride.then((stop) => stop());
+    };
+  }, []);
+
+  // The backend tracks positions from the moves it sends, warn when the arm reports otherwise
+  useEffect(() => {
+    const unlisten = listen<PositionDrift[]>('report-position-drift', (event) => {
+      const joints = event.payload.map((drift) => `J${drift.joint}`).join(', ');
+      toast.error(`Position drift detected on ${joints}, angles were updated from the arm`);
+    });
+
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
   
 
   return (
@@ -212,11 +335,15 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
         angles,
         calibrationStates,
         states,
-        velocity,
-        acceleration,
+        parameters,
+        parameterLimits,
+        speedOverride,
+        pollInterval,
         setStates: updateStates,
         setVelocity: updateVelocity,
         setAcceleration: updateAcceleration,
+        setPollInterval: updatePollInterval,
+        setSpeedOverride: updateSpeedOverride,
         fetchSteppersState,
         fetchSteppersAngles,
         resetStepperState,
@@ -224,6 +351,7 @@ export const StepperProvider: React.FC<StepperProviderProps> = ({ children }) =>
         toggleStepper,
         handleCalibrate,
         handleCalibrateAll,
+        handleHome,
       }}
     >
       {children}
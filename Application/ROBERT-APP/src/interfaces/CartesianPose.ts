@@ -0,0 +1,23 @@
+// Position in mm and orientation in degrees (roll, pitch, yaw around the base X, Y, Z axes)
+export type CartesianPose = {
+    x: number;
+    y: number;
+    z: number;
+    roll: number;
+    pitch: number;
+    yaw: number;
+}
+
+// Position in mm
+export type CartesianPosition = {
+    x: number;
+    y: number;
+    z: number;
+}
+
+export type CartesianAxis = 'x' | 'y' | 'z' | 'roll' | 'pitch' | 'yaw';
+
+export type JogFrame = 'base' | 'tool';
+
+// What a continuous jog moves: a joint (1 to 6) or the tool along or around an axis
+export type JogTarget = { joint: number } | { axis: CartesianAxis };
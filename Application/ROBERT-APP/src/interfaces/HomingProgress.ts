@@ -0,0 +1,11 @@
+// Stage homing has started, sent as `report-homing-progress`
+export type HomingProgress = {
+    stage: 'calibrating' | 'offsetting' | 'movingToPose' | 'done';
+    // Joints the stage moves, from 1
+    joints: number[];
+    // Home pose moved to, only set on the last stages
+    pose: string | null;
+    // Stages done so far and how many there are
+    step: number;
+    steps: number;
+}
@@ -0,0 +1,27 @@
+// Values set on a single joint, null follows the global value
+export type JointOverride = {
+    velocity: number | null;
+    acceleration: number | null;
+}
+
+// Velocity in degrees/s and acceleration in degrees/s²
+export type MotionParameters = {
+    velocity: number;
+    acceleration: number;
+    // Overrides of J1 to J6
+    joints: JointOverride[];
+}
+
+// Highest velocity and acceleration a joint accepts
+export type JointLimits = {
+    joint: number;
+    velocity: number;
+    acceleration: number;
+}
+
+// Velocity and acceleration a joint runs with, read back from the firmware
+export type JointParameters = {
+    joint: number;
+    velocity: number;
+    acceleration: number;
+}
@@ -0,0 +1,6 @@
+// Joint the arm reports away from where the backend expected it, in absolute motor steps
+export type PositionDrift = {
+    joint: number;
+    expected: number | null;
+    reported: number | null;
+}
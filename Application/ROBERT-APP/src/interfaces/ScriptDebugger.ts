@@ -0,0 +1,32 @@
+// CALL of a subroutine that is still running
+export type CallSite = {
+    name: string;
+    line: number;
+    // Included file the CALL is in, null for the script itself
+    file: string | null;
+}
+
+// Sent right before a script line runs
+export type ScriptProgress = {
+    line: number;
+    file: string | null;
+    // Line of the script itself, the CALL or INCLUDE when `line` is in another file
+    scriptLine: number;
+    // Outermost call first
+    callStack: CallSite[];
+}
+
+// Sent when a script being debugged pauses before a line
+export type ScriptPaused = ScriptProgress & {
+    variables: Record<string, number>;
+    // Positive angles as scripts use them, null for joints that are not calibrated
+    angles: (number | null)[];
+}
+
+export type DebugCommand =
+    | { command: 'continue' }
+    | { command: 'stepInto' }
+    | { command: 'stepOver' }
+    | { command: 'runToLine'; line: number }
+    | { command: 'pause' }
+    | { command: 'stop' };
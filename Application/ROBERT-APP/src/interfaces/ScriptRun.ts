@@ -0,0 +1,18 @@
+// Script run recorded in the history
+export type ScriptRun = {
+    id: number;
+    script: string;
+    // SHA-256 of the script content
+    hash: string;
+    // Milliseconds since the Unix epoch
+    startedAt: number;
+    endedAt: number;
+    note: string | null;
+    result: 'completed' | 'aborted' | 'error';
+    // Line of the script where execution stopped, null when it completed
+    stoppedAt: number | null;
+    error: string | null;
+    responses: { line: number; response: string }[];
+}
+
+export type ExportFormat = 'json' | 'csv';
@@ -0,0 +1,22 @@
+// Problem found by the dry run of a script
+export type Diagnostic = {
+    line: number;
+    // Included file the line is in, null for the script itself
+    file: string | null;
+    severity: 'error' | 'warning';
+    message: string;
+}
+
+export type ValidationReport = {
+    diagnostics: Diagnostic[];
+    // Seconds spent moving and waiting
+    estimatedDuration: number;
+    finalAngles: (number | null)[];
+}
+
+// .rob script converted from G-code, lines with unsupported codes are left out
+export type GcodeImport = {
+    script: string;
+    // Lines are those of the G-code
+    diagnostics: Diagnostic[];
+}
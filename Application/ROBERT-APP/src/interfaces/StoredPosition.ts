@@ -0,0 +1,7 @@
+export type StoredPosition = {
+    name: string;
+    angles: (number | null)[];
+    notes: string | null;
+    tool: string | null;
+    createdAt: number;
+}
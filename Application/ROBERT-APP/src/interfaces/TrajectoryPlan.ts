@@ -0,0 +1,12 @@
+export type JointProfile = {
+    joint: number;
+    steps: number;
+    velocity: number;
+    acceleration: number;
+}
+
+export type TrajectoryPlan = {
+    joints: JointProfile[];
+    accelTime: number;
+    duration: number;
+}
@@ -0,0 +1,12 @@
+import { MotionParameters } from "./MotionParameters";
+
+// Position recorded in teach mode
+export type Waypoint = {
+    // Positive angles from the joint zero, null for joints that were not calibrated
+    angles: (number | null)[];
+    // Velocity and acceleration the move to the waypoint runs with
+    parameters: MotionParameters;
+    // Milliseconds to wait once the waypoint is reached
+    dwell: number | null;
+    label: string | null;
+}
@@ -25,11 +25,15 @@ void processCommand(String command) {
         break;
 
       case SETVEL:
-        setVelocity(atoi(commandAction.c_str()));
+        // SETVEL>200 sets every stepper, SETVEL>J1_120.5;J2_80; sets individual steppers
+        if (commandAction.indexOf("_") != -1) processJointParametersCommand(commandAction, true);
+        else setVelocity(atoi(commandAction.c_str()));
         break;
 
       case SETACC:
-        setAcceleration(atoi(commandAction.c_str()));
+        // SETACC>200 sets every stepper, SETACC>J1_120.5;J2_80; sets individual steppers
+        if (commandAction.indexOf("_") != -1) processJointParametersCommand(commandAction, false);
+        else setAcceleration(atoi(commandAction.c_str()));
         break;
 
       case TOGGLE:
@@ -56,6 +60,15 @@ void processCommand(String command) {
         getSteppersCalibration();
         break;
 
+      case JOG:
+        // Starts the move and returns, the steppers keep running from loop() until done or stopped
+        processJogCommand(commandAction);
+        break;
+
+      case STOP:
+        stopJoggingSteppers();
+        break;
+
       default:
         Serial.println(CommandNotDefined);
         break;
@@ -76,25 +89,48 @@ CommandCode getCommandCode(const String& command) {
   if (command == SteppersStepsCommand) return STEPS;
   if (command == GetParamsCommand) return PARAMS;
   if (command == GetCalibrationStateCommand) return CALSTATE;
+  if (command == JogCommand) return JOG;
+  if (command == StopCommand) return STOP;
   return UNKNOWN;
 }
 
 void processMoveCommand(String actionString) {
+  const int MAX_STEPPERS = 6;
+  int steps[MAX_STEPPERS] = {0}; // Array to store step values for each stepper
+
+  // Move Command actions should have the format -> MOVE>J1_-200;J2_300;
+  if (!parseStepsAction(actionString, steps)) return;
+
+  // Move all steppers simultaneously
+  moveSteppers(steps);
+}
+
+void processJogCommand(String actionString) {
+  const int MAX_STEPPERS = 6;
+  int steps[MAX_STEPPERS] = {0};
+
+  // Jog Command actions have the same format as moves -> JOG>J1_-200;J2_300;
+  if (!parseStepsAction(actionString, steps)) return;
+
+  jogSteppers(steps);
+}
+
+// Fills steps[] from actions with the format J1_-200;J2_300; returns false on a format error
+bool parseStepsAction(String actionString, int steps[]) {
   String actionLeft = actionString;
-  
+
   if (actionString.indexOf(";") == -1) {
     Serial.println(CommandFormatError);
-    return;
+    return false;
   }
 
-  const int MAX_STEPPERS = 6;  
-  int steps[MAX_STEPPERS] = {0}; // Array to store step values for each stepper
-
-  // Move Command actions should have the format -> MOVE>J1_-200;J2_300;
   while (actionLeft.indexOf(";") != -1) {
     int delimiterIndex = actionLeft.indexOf(";");
     String currentAction = actionLeft.substring(0, delimiterIndex);
 
+    // Update String
+    actionLeft = actionLeft.substring(delimiterIndex + 1);
+
     int jointDelimiter = currentAction.indexOf("_");
     if (jointDelimiter == -1) continue;
 
@@ -103,17 +139,14 @@ void processMoveCommand(String actionString) {
 
     int stepCount = atoi(currentAction.substring(jointDelimiter + 1).c_str());
 
-    if (stepperNumber >= 1 && stepperNumber <= MAX_STEPPERS) {
+    if (stepperNumber >= 1 && stepperNumber <= 6) {
       steps[stepperNumber - 1] = stepCount;  // Store step count for each stepper
     }
-
-    // Update String
-    actionLeft = actionLeft.substring(delimiterIndex + 1);
   }
 
-  // Move all steppers simultaneously
-  moveSteppers(steps);
+  return true;
 }
+
 void processToggleCommand(String actionString) {
   //Toggle command actions should have the format -> TOGGLE>JOINT_STATE;
   String actionLeft = actionString;
@@ -197,3 +230,43 @@ void processCalibrateCommand(String actionString) {
     Serial.println(failedJoints);  // Return list of failed joints
   }
 }
+
+void processJointParametersCommand(String actionString, bool isVelocity) {
+  //Joint parameters actions should have the format -> J1_120.5;J2_80;
+  String actionLeft = actionString;
+  String response = isVelocity ? "Velocity set to: " : "Acceleration Set to: ";
+
+  if (actionString.indexOf(";") == -1) {
+    Serial.println(CommandFormatError);
+    return;
+  }
+
+  while (actionLeft.indexOf(";") != -1) {
+    int delimiterIndex = actionLeft.indexOf(";");
+    String currentAction = actionLeft.substring(0, delimiterIndex);
+
+    int jointDelimiter = currentAction.indexOf("_");
+    if (jointDelimiter == -1) {
+      Serial.println(CommandFormatError);
+      return;
+    }
+
+    int stepperNumber = atoi(currentAction.substring(1, jointDelimiter).c_str());
+    float value = atof(currentAction.substring(jointDelimiter + 1).c_str());
+
+    if (stepperNumber < 1 || stepperNumber > 6) {
+      Serial.println(InvalidStepper);
+      return;
+    }
+
+    if (isVelocity) setStepperVelocity(stepperNumber, value);
+    else setStepperAcceleration(stepperNumber, value);
+
+    response += currentAction + " ";
+
+    //Update String
+    actionLeft = actionLeft.substring(delimiterIndex + 1);
+  }
+
+  Serial.println(response);
+}
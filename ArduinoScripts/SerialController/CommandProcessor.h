@@ -14,6 +14,8 @@ enum CommandCode {
     STEPS,
     PARAMS,
     CALSTATE,
+    JOG,
+    STOP,
     UNKNOWN
 };
 
@@ -22,6 +24,9 @@ CommandCode getCommandCode(const String& command);
 void processCommand(String command);
 void processToggleCommand(String actionString);
 void processMoveCommand(String actionString);
+void processJogCommand(String actionString);
+bool parseStepsAction(String actionString, int steps[]);
 void processCalibrateCommand(String actionString);
+void processJointParametersCommand(String actionString, bool isVelocity);
 
 #endif
@@ -15,6 +15,8 @@ constexpr char CalibrateStepperCommand[] = "CALIBRATE";
 constexpr char SteppersStateCommand[] = "STATE";
 constexpr char SteppersStepsCommand[] = "STEPS";
 constexpr char GetCalibrationStateCommand[] = "CALSTATE";
+constexpr char JogCommand[] = "JOG";
+constexpr char StopCommand[] = "STOP";
 
 constexpr char Enabled[] = "ENABLED";
 constexpr char Disabled[] = "DISABLED";
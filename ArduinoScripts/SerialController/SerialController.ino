@@ -9,8 +9,11 @@ void setup() {
 }
 
 void loop() {
-  while(Serial.available()) {
+  if (Serial.available()) {
     String command = Serial.readStringUntil('~');
     processCommand(command);
   }
+
+  // Keeps jogging steppers moving between commands
+  runJoggingSteppers();
 }
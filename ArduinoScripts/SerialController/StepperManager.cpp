@@ -19,6 +19,9 @@ const int limitPins[6] = { J1limitPin, J2limitPin, J3limitPin, J4limitPin, J5lim
 // Track if joints have been calibrated
 bool isCalibrated[6] = { false, false, false, false, false, false };
 
+// Steps requested for each stepper by the last jog, 0 once it is done
+int jogSteps[6] = { 0, 0, 0, 0, 0, 0 };
+
 int lastAcceleration = 200;
 int lastVelocity = 200;
 
@@ -175,6 +178,57 @@ void moveSteppers(int steps[]) {
   } while (anyStepperMoving);  // Continue running until all steppers finish
 }
 
+//Like moveSteppers but returns right away, runJoggingSteppers() drives the steppers from loop()
+//so commands keep being read while they move and STOP> can decelerate them
+void jogSteppers(int steps[]) {
+  String jogSteppersResponse = String(InfoResponse) + "JOGGING_STEPS:";
+
+  for (int i = 0; i < 6; i++) {
+    if (steps[i] == 0) continue;
+
+    jogSteps[i] = steps[i];
+    steppers[i].move(steps[i]);
+    jogSteppersResponse += "J" + String(i + 1) + "_" + String(steps[i]) + " ";
+  }
+
+  Serial.println(jogSteppersResponse);
+}
+
+void runJoggingSteppers() {
+  for (int i = 0; i < 6; i++) {
+    if (jogSteps[i] == 0) continue;
+
+    if (steppers[i].distanceToGo() == 0) {
+      jogSteps[i] = 0;
+      continue;
+    }
+
+    int positiveToLimitSwitch = moveStepperPositiveSteps(i + 1);
+
+    // Check for limit switch
+    if ((positiveToLimitSwitch == 1 && jogSteps[i] > 0) || (positiveToLimitSwitch == 0 && jogSteps[i] < 0)) {
+      if (digitalRead(limitPins[i]) == LOW) {
+        steppers[i].stop();
+        steppers[i].setCurrentPosition(0);
+        isCalibrated[i] = true;
+        jogSteps[i] = 0;
+        continue;
+      }
+    }
+
+    steppers[i].run();
+  }
+}
+
+//Decelerates the jogging steppers with their acceleration, they come to rest from loop()
+void stopJoggingSteppers() {
+  for (int i = 0; i < 6; i++) {
+    if (jogSteps[i] != 0) steppers[i].stop();
+  }
+
+  Serial.println(String(InfoResponse) + "STOPPING");
+}
+
 bool calibrateStepper(int stepperNum) {
   int limitPin = getLimitSwitchPin(stepperNum);
   if (limitPin == -1) {
@@ -249,6 +303,22 @@ void setVelocity(int velocity) {
   Serial.println(velocity);
 }
 
+//Only lasts until the next global SETVEL>, used to synchronize the steppers taking part in a move
+void setStepperVelocity(int stepperNum, float velocity) {
+  AccelStepper* stepper = getStepperByIndex(stepperNum);
+  if (stepper == nullptr) return;
+
+  stepper->setMaxSpeed(velocity);
+}
+
+//Only lasts until the next global SETACC>, used to synchronize the steppers taking part in a move
+void setStepperAcceleration(int stepperNum, float acceleration) {
+  AccelStepper* stepper = getStepperByIndex(stepperNum);
+  if (stepper == nullptr) return;
+
+  stepper->setAcceleration(acceleration);
+}
+
 void getSteppersState() {
   String steppersState = SteppersStateResponse;
 
@@ -290,14 +360,11 @@ void getSteppersSteps() {
 void getStepperParameters() {
   String steppersState = SteppersParamsResponse;
 
-  // Assuming all steppers share the same velocity and acceleration, we can use any stepper to fetch these values
-  // Here we use the first stepper to fetch the velocity and acceleration
-  int velocity = steppers[0].maxSpeed();
-  int acceleration = steppers[0].acceleration();
-
-  // Format the response with the current velocity and acceleration
-  steppersState += "VEL_" + String(velocity) + ";";
-  steppersState += "ACC_" + String(acceleration) + ";";
+  // Velocity and acceleration of every stepper, with the two decimals the host sets them with, so they read back unchanged
+  // Format: J1_VELOCITY_ACCELERATION;
+  for (int i = 0; i < 6; i++) {
+    steppersState += "J" + String(i + 1) + "_" + String(steppers[i].maxSpeed(), 2) + "_" + String(steppers[i].acceleration(), 2) + ";";
+  }
 
   // Print the response
   Serial.println(steppersState);
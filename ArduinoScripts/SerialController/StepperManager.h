@@ -7,8 +7,13 @@ void initializeSteppers();
 void toggleStepper(int stepperNum, bool enabled);
 void moveStepper(int stepperNum, int steps);
 void moveSteppers(int steps[]);
+void jogSteppers(int steps[]);
+void runJoggingSteppers();
+void stopJoggingSteppers();
 void setVelocity(int velocity);
 void setAcceleration(int acceleration);
+void setStepperVelocity(int stepperNum, float velocity);
+void setStepperAcceleration(int stepperNum, float acceleration);
 bool calibrateStepper(int stepper);
 int getLimitSwitchPin(int stepperIndex);
 void reportSteppersPositions();